@@ -0,0 +1,107 @@
+use anyhow::{Result, Context};
+use ethers_core::types::Address;
+use ethers_core::utils::{hash_message, keccak256, to_checksum};
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+use crate::kms::KmsClient;
+use crate::signer::Signer;
+
+/// Header carrying the relay authentication signature.
+pub const FLASHBOTS_SIGNATURE_HEADER: &str = "X-Flashbots-Signature";
+
+#[derive(Clone)]
+enum AuthKey {
+    Signer(Arc<dyn Signer>),
+    Kms(Arc<dyn KmsClient>),
+}
+
+/// Relay reputation/authentication key. Signs each request body the way
+/// Flashbots relays expect: EIP-191 `personal_sign` over the 0x-prefixed hex
+/// keccak256 of the body, sent as `X-Flashbots-Signature: <address>:<signature>`.
+///
+/// This key only identifies the searcher to the relay; it never holds funds
+/// and should not be the key that signs bundle transactions.
+pub struct FlashbotsAuth {
+    key: AuthKey,
+    address: OnceCell<Address>,
+}
+
+impl FlashbotsAuth {
+    /// Use a `Signer` that supports `address` and `sign_message` (e.g. `BasicEnvSigner`).
+    pub fn from_signer(signer: Arc<dyn Signer>) -> Self {
+        Self { key: AuthKey::Signer(signer), address: OnceCell::new() }
+    }
+
+    /// Use a KMS/HSM key. The client must report its address so `v` can be recovered.
+    pub fn from_kms(kms: Arc<dyn KmsClient>) -> Self {
+        Self { key: AuthKey::Kms(kms), address: OnceCell::new() }
+    }
+
+    /// Address of the auth key (fetched once, then cached).
+    pub async fn address(&self) -> Result<Address> {
+        self.address.get_or_try_init(|| async {
+            let addr = match &self.key {
+                AuthKey::Signer(s) => s.address().await?,
+                AuthKey::Kms(k) => k.get_address().await?,
+            };
+            addr.ok_or_else(|| anyhow::anyhow!("auth key does not expose its address"))
+        }).await.copied()
+    }
+
+    /// Build the `X-Flashbots-Signature` header value for a request body.
+    pub async fn sign_body(&self, body: &[u8]) -> Result<String> {
+        let address = self.address().await?;
+        let message = format!("0x{}", hex::encode(keccak256(body)));
+        let signature = match &self.key {
+            AuthKey::Signer(s) => s.sign_message(message.as_bytes()).await.context("auth signer failed")?,
+            AuthKey::Kms(k) => {
+                let digest = hash_message(message.as_bytes());
+                let der = k.sign(digest.as_bytes()).await.context("auth kms sign failed")?;
+                crate::crypto::der::der_to_ethers_signature(&der, digest.as_bytes(), Some(address))?.to_vec()
+            }
+        };
+        if signature.len() != 65 {
+            return Err(anyhow::anyhow!("auth signature must be 65 bytes, got {}", signature.len()));
+        }
+        Ok(format!("{}:0x{}", to_checksum(&address, None), hex::encode(signature)))
+    }
+}
+
+/// Check a `X-Flashbots-Signature` header value against a body and return the signer address.
+pub fn verify_signature_header(header: &str, body: &[u8]) -> Result<Address> {
+    let (addr, sig) = header.split_once(':').ok_or_else(|| anyhow::anyhow!("header must be <address>:<signature>"))?;
+    let addr: Address = addr.parse().context("invalid address in signature header")?;
+    let sig: ethers_core::types::Signature = sig.parse().context("invalid signature in signature header")?;
+    let message = format!("0x{}", hex::encode(keccak256(body)));
+    sig.verify(message, addr).context("signature does not match address")?;
+    Ok(addr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kms::MockKms;
+    use crate::signer::BasicEnvSigner;
+
+    const SECRET: &str = "0123456789012345678901234567890123456789012345678901234567890123";
+
+    #[tokio::test]
+    async fn signer_and_kms_produce_verifiable_headers() {
+        let body = br#"{"jsonrpc":"2.0","id":1,"method":"eth_sendBundle","params":[]}"#;
+        let from_signer = FlashbotsAuth::from_signer(Arc::new(BasicEnvSigner::from_secret(SECRET.to_string())));
+        let from_kms = FlashbotsAuth::from_kms(Arc::new(MockKms::new(SECRET.to_string())));
+
+        let h1 = from_signer.sign_body(body).await.unwrap();
+        let h2 = from_kms.sign_body(body).await.unwrap();
+        let expected = from_signer.address().await.unwrap();
+        assert_eq!(verify_signature_header(&h1, body).unwrap(), expected);
+        assert_eq!(verify_signature_header(&h2, body).unwrap(), expected);
+        assert!(verify_signature_header(&h1, b"tampered").is_err());
+    }
+
+    #[tokio::test]
+    async fn signer_without_message_support_is_rejected() {
+        let auth = FlashbotsAuth::from_signer(Arc::new(crate::signer::InMemorySigner {}));
+        assert!(auth.sign_body(b"{}").await.is_err());
+    }
+}
@@ -2,7 +2,11 @@ use anyhow::{Result, Context};
 use std::time::Duration;
 use ethers_core::types::{Bytes, H256, U256, transaction::eip2718::TypedTransaction};
 use ethers_providers::{Provider, Http, Middleware};
-use crate::executor::RelayClient;
+use crate::executor::{BundleOptions, BundleRelay, SendBundleResponse};
+use crate::retarget::{BundleRetargeter, RetargetConfig, RetargetReport, SLOT_SECS};
+use crate::fees::{FeeOracle, FeeRecommendation, Urgency};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use tokio::time::sleep;
 use tracing::instrument;
 
@@ -19,6 +23,28 @@ pub struct AutosubmitConfig {
     pub kill_switch_max_gas_wei: Option<u128>,
     /// Maximum allowed net loss (wei) relative to expected PnL (kill switch)
     pub kill_switch_max_loss_wei: Option<i128>,
+    /// OP-stack L1 data fee parameters; when set, kill-switch checks add the L1 data fee
+    pub l1_fee_params: Option<crate::l2fee::L1FeeParams>,
+    /// Number of upcoming blocks a bundle is targeted at on each new head
+    pub target_blocks_ahead: u64,
+    /// Default route for `submit_and_monitor*`; see `SubmissionPolicy`
+    pub submission_policy: SubmissionPolicy,
+    /// Broadcast publicly when the private route fails. Off by default: a public
+    /// arbitrage tx is visible to every other searcher before it lands.
+    pub public_fallback: bool,
+}
+
+/// How transactions reach block builders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SubmissionPolicy {
+    /// Relay bundle only; transactions never enter the public mempool.
+    #[default]
+    BundleOnly,
+    /// Each transaction via `eth_sendPrivateTransaction`, valid for `max_blocks`
+    /// blocks; `fast` shares it with all registered builders.
+    PrivateTx { max_blocks: u64, fast: bool },
+    /// Public mempool via `eth_sendRawTransaction`.
+    Public,
 }
 
 impl Default for AutosubmitConfig {
@@ -31,23 +57,186 @@ impl Default for AutosubmitConfig {
             max_bumps: 3,
             kill_switch_max_gas_wei: None,
             kill_switch_max_loss_wei: None,
+            l1_fee_params: None,
+            target_blocks_ahead: 3,
+            submission_policy: SubmissionPolicy::BundleOnly,
+            public_fallback: false,
         }
     }
 }
 
+/// A replaceable bundle currently live on the relays.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutstandingBundle {
+    pub replacement_uuid: String,
+    pub bundle_hash: H256,
+    pub target_block: Option<u64>,
+    /// Number of times the bundle has been submitted under this UUID
+    pub revision: u32,
+}
+
 pub struct Autosubmitter {
     pub config: AutosubmitConfig,
     pub rpc_url: String,
+    /// Optional fee oracle used to price gas bumps from recent blocks
+    pub fee_oracle: Option<Arc<FeeOracle>>,
+    /// Replaceable bundles keyed by opportunity, so an update replaces rather than competes
+    outstanding: Mutex<HashMap<String, OutstandingBundle>>,
 }
 
 impl Autosubmitter {
     pub fn new(rpc_url: String, config: AutosubmitConfig) -> Self {
-        Self { rpc_url, config }
+        Self { rpc_url, config, fee_oracle: None, outstanding: Mutex::new(HashMap::new()) }
+    }
+
+    /// Price bumped transactions with `oracle` instead of only scaling the previous bid.
+    pub fn with_fee_oracle(mut self, oracle: Arc<FeeOracle>) -> Self {
+        self.fee_oracle = Some(oracle);
+        self
+    }
+
+    /// Refresh the fee oracle (if any) and recommend fees for the given bump.
+    /// Falls back to the oracle's cached history when the refresh fails.
+    async fn bump_recommendation(&self, bump_idx: usize) -> Option<FeeRecommendation> {
+        let oracle = self.fee_oracle.as_ref()?;
+        if let Err(e) = oracle.refresh().await {
+            tracing::warn!("fee oracle refresh failed: {:?}", e);
+        }
+        oracle.recommend(Urgency::for_bump(bump_idx)).ok()
+    }
+
+    /// Worst-case spend (wei) of `unsigned` with gas prices scaled by `factor`: gas limit
+    /// times max fee per tx, plus the L1 data fee when `l1_fee_params` is configured.
+    pub fn worst_case_cost_wei(&self, unsigned: &[TypedTransaction], factor: f64) -> u128 {
+        let mut worst_case_cost: u128 = 0u128;
+        for tx in unsigned.iter() {
+            // Match on TypedTransaction variants to extract fields
+            match tx.clone() {
+                TypedTransaction::Eip1559(req) => {
+                    let gas_limit = req.gas.unwrap_or(U256::from(21000u64)).as_u128();
+                    let base_price = req.max_fee_per_gas.map(|m| m.as_u128()).unwrap_or(0u128);
+                    let new_price = ((base_price as f64) * factor) as u128;
+                    worst_case_cost = worst_case_cost.saturating_add(gas_limit.saturating_mul(new_price));
+                }
+                TypedTransaction::Legacy(req) => {
+                    let gas_limit = req.gas.unwrap_or(U256::from(21000u64)).as_u128();
+                    let base_price = req.gas_price.map(|p| p.as_u128()).unwrap_or(0u128);
+                    let new_price = ((base_price as f64) * factor) as u128;
+                    worst_case_cost = worst_case_cost.saturating_add(gas_limit.saturating_mul(new_price));
+                }
+                _ => {
+                    // Unknown tx type: assume minimal gas and zero price
+                    worst_case_cost = worst_case_cost.saturating_add(21000u128.saturating_mul(0u128));
+                }
+            }
+            // On OP-stack chains the L1 data fee is paid regardless of the L2 gas price
+            if let Some(params) = self.config.l1_fee_params.as_ref() {
+                let l1_fee = params.l1_fee_unsigned(&tx.rlp());
+                worst_case_cost = worst_case_cost.saturating_add(l1_fee.try_into().unwrap_or(u128::MAX));
+            }
+        }
+        worst_case_cost
+    }
+
+    /// Submit `signed_blob` as the current version of the bundle for `key` (e.g. an
+    /// opportunity id). The first call assigns a replacement UUID; later calls reuse
+    /// it so the relay replaces the earlier bundle instead of holding both.
+    pub async fn submit_replaceable(
+        &self,
+        key: &str,
+        signed_blob: &[Vec<u8>],
+        relay: &dyn BundleRelay,
+        block_number: Option<u64>,
+        mut options: BundleOptions,
+    ) -> Result<OutstandingBundle> {
+        // Reserve the UUID before sending so concurrent updates for `key` share it.
+        let uuid = self.outstanding_entry(key, |b| b.replacement_uuid.clone());
+        options.replacement_uuid = Some(uuid);
+
+        let uuid = options.replacement_uuid.clone().unwrap_or_default();
+        let SendBundleResponse { bundle_hash } = match relay.send_bundle_with_options(signed_blob, block_number, &options).await {
+            Ok(resp) => resp,
+            Err(e) => {
+                // A UUID no relay has accepted yet is not worth cancelling later.
+                let mut map = self.outstanding.lock().unwrap();
+                if map.get(key).is_some_and(|b| b.replacement_uuid == uuid && b.revision == 0) {
+                    map.remove(key);
+                }
+                return Err(anyhow::anyhow!("replaceable bundle submission failed: {}", e));
+            }
+        };
+        let updated = self.outstanding.lock().unwrap().get_mut(key).filter(|b| b.replacement_uuid == uuid).map(|entry| {
+            entry.bundle_hash = bundle_hash;
+            entry.target_block = block_number;
+            entry.revision += 1;
+            entry.clone()
+        });
+        let Some(updated) = updated else {
+            // Cancelled or cleared while this version was in flight: withdraw it too.
+            relay.cancel_bundle(&uuid).await.map_err(|e| anyhow::anyhow!("eth_cancelBundle failed: {}", e))?;
+            return Err(anyhow::anyhow!("bundle for {} was withdrawn while being submitted", key));
+        };
+        tracing::info!(key, uuid = %updated.replacement_uuid, revision = updated.revision, ?bundle_hash, "replaceable bundle submitted");
+        Ok(updated)
+    }
+
+    /// Apply `f` to the entry for `key`, creating it with a fresh UUID if needed.
+    fn outstanding_entry<T>(&self, key: &str, f: impl FnOnce(&mut OutstandingBundle) -> T) -> T {
+        let mut map = self.outstanding.lock().unwrap();
+        let entry = map.entry(key.to_string()).or_insert_with(|| OutstandingBundle {
+            replacement_uuid: crate::executor::new_replacement_uuid(),
+            bundle_hash: H256::zero(),
+            target_block: None,
+            revision: 0,
+        });
+        f(entry)
+    }
+
+    /// Withdraw the outstanding bundle for `key`, if any. Returns whether one was cancelled.
+    /// A bundle whose first submission is still in flight has nothing to cancel yet.
+    pub async fn cancel_outstanding(&self, key: &str, relay: &dyn BundleRelay) -> Result<bool> {
+        let uuid = match self.outstanding.lock().unwrap().get(key) {
+            Some(b) if b.revision > 0 => b.replacement_uuid.clone(),
+            _ => return Ok(false),
+        };
+        relay.cancel_bundle(&uuid).await.map_err(|e| anyhow::anyhow!("eth_cancelBundle failed: {}", e))?;
+        self.outstanding.lock().unwrap().remove(key);
+        tracing::info!(key, uuid = %uuid, "bundle cancelled");
+        Ok(true)
+    }
+
+    /// Forget `key` without cancelling (e.g. after the bundle was included).
+    pub fn clear_outstanding(&self, key: &str) -> Option<OutstandingBundle> {
+        self.outstanding.lock().unwrap().remove(key)
+    }
+
+    /// The latest submitted version of the bundle for `key`.
+    pub fn outstanding(&self, key: &str) -> Option<OutstandingBundle> {
+        self.outstanding.lock().unwrap().get(key).filter(|b| b.revision > 0).cloned()
     }
 
-    /// Submit via relay if available, fallback to direct provider submission.
-    /// Then monitor for inclusion by polling the provider for each tx hash.
-    pub async fn submit_and_monitor(&self, signed_blob: &[Vec<u8>], relay: &RelayClient) -> Result<Vec<serde_json::Value>> {
+    /// Submit a bundle for the next `target_blocks_ahead` blocks and re-target it on
+    /// every new head until it is included, its `max_timestamp` passes, or
+    /// `max_wait_secs` worth of blocks have gone by. The report lists each
+    /// submission and, for blocks that passed without inclusion, the relay's reason.
+    pub async fn submit_until_included(&self, signed_blob: &[Vec<u8>], relay: &dyn BundleRelay, options: BundleOptions) -> Result<RetargetReport> {
+        let provider = Provider::<Http>::try_from(self.rpc_url.as_str()).context("invalid rpc url")?;
+        let current = provider.get_block_number().await.context("eth_blockNumber failed")?.as_u64();
+        let config = RetargetConfig {
+            blocks_ahead: self.config.target_blocks_ahead.max(1),
+            slot_secs: SLOT_SECS,
+            max_block: Some(current + (self.config.max_wait_secs / SLOT_SECS).max(1)),
+        };
+        let heads = crate::retarget::spawn_head_watcher(&self.rpc_url, Duration::from_secs(1))?;
+        let mut retargeter = BundleRetargeter::new(relay, self.rpc_url.clone(), signed_blob.to_vec(), options, config);
+        let outcome = retargeter.run(heads).await?;
+        Ok(RetargetReport { outcome, attempts: retargeter.attempts().to_vec() })
+    }
+
+    /// Submit following `config.submission_policy` through `relay` (a single
+    /// `RelayClient` or a `RelaySet`), then monitor for inclusion by polling the
+    /// provider for each tx hash.
+    pub async fn submit_and_monitor(&self, signed_blob: &[Vec<u8>], relay: &dyn BundleRelay) -> Result<Vec<serde_json::Value>> {
         // direct path without rebidding/signing capability
         self.submit_and_monitor_with_rebump(None, None, signed_blob.to_vec(), relay, None).await
     }
@@ -59,35 +248,32 @@ impl Autosubmitter {
         &self,
         unsigned_txs: Option<&[TypedTransaction]>,
         signer: Option<std::sync::Arc<dyn crate::signer::Signer>>,
-        mut signed_blob: Vec<Vec<u8>>,
-        relay: &RelayClient,
+        signed_blob: Vec<Vec<u8>>,
+        relay: &dyn BundleRelay,
         expected_pnl: Option<&[i128]>,
     ) -> Result<Vec<serde_json::Value>> {
-        // Try relay first
-        if let Ok(resp) = relay.submit_flashbots_bundle(&signed_blob, None).await {
-            tracing::info!("submitted to relay: {:?}", resp);
-            #[cfg(feature = "with-metrics")]
-            {
-                metrics::increment_counter!("autosubmit.submissions.relay", 1);
-            }
-        } else {
-            tracing::warn!("relay submission failed or not configured; falling back to provider");
-        }
+        self.submit_and_monitor_with_policy(self.config.submission_policy, unsigned_txs, signer, signed_blob, relay, expected_pnl).await
+    }
 
-        // Fallback direct submission: send raw txs sequentially and monitor receipts
+    /// Like `submit_and_monitor_with_rebump`, with an explicit per-opportunity policy.
+    /// Re-broadcasts and gas bumps go through the same route as the first submission.
+    #[instrument(skip(self, unsigned_txs, signer, signed_blob, relay, expected_pnl))]
+    pub async fn submit_and_monitor_with_policy(
+        &self,
+        policy: SubmissionPolicy,
+        unsigned_txs: Option<&[TypedTransaction]>,
+        signer: Option<std::sync::Arc<dyn crate::signer::Signer>>,
+        mut signed_blob: Vec<Vec<u8>>,
+        relay: &dyn BundleRelay,
+        expected_pnl: Option<&[i128]>,
+    ) -> Result<Vec<serde_json::Value>> {
         let provider = Provider::<Http>::try_from(self.rpc_url.as_str()).context("invalid rpc url")?;
+        let mut last = self.dispatch(policy, &signed_blob, relay, &provider).await?;
 
         // Compute expected tx hashes (keccak256 of signed raw bytes)
-        let mut tx_hashes: Vec<H256> = Vec::new();
-        for raw in signed_blob.iter() {
-            let bytes = Bytes::from(raw.clone());
-            let tx_hash = H256::from(ethers_core::utils::keccak256(&bytes));
-            // attempt submission; if already sent by relay it may still not be present yet
-            if let Err(e) = provider.send_raw_transaction(bytes.clone()).await {
-                tracing::warn!("direct send_raw_transaction failed: {:?}", e);
-            }
-            tx_hashes.push(tx_hash);
-        }
+        let mut tx_hashes: Vec<H256> = signed_blob.iter()
+            .map(|raw| H256::from(ethers_core::utils::keccak256(raw)))
+            .collect();
 
         // Poll receipts
         let mut receipts_json: Vec<serde_json::Value> = Vec::new();
@@ -114,36 +300,25 @@ impl Autosubmitter {
                     return Err(anyhow::anyhow!("timed out waiting for inclusion and no retries configured"));
                 }
                 tracing::warn!("inclusion not seen; attempting resubmission (retries left)");
+                explain_miss(relay, last).await;
 
                 // If we have unsigned txs and a signer, attempt gas bump re-signing
                 if let (Some(unsigned), Some(signer_arc)) = (unsigned_txs, signer.as_ref()) {
                     for bump_idx in 0..self.config.max_bumps {
+                        if bump_idx > 0 {
+                            explain_miss(relay, last).await;
+                        }
                         let factor = self.config.bump_factor.powi(bump_idx as i32 + 1);
                         tracing::info!("attempting gas bump {} (factor {:.3})", bump_idx + 1, factor);
 
+                        // Build bumped unsigned txs; with a fee oracle the bid never drops below its recommendation
+                        let recommendation = self.bump_recommendation(bump_idx).await;
+                        let bumped: Vec<TypedTransaction> = unsigned.iter()
+                            .map(|tx| bump_tx_fees(tx, factor, recommendation.as_ref()))
+                            .collect();
+
                         // Apply kill-switch: estimate worst-case gas for this bump
-                        let mut worst_case_cost: u128 = 0u128;
-                        for tx in unsigned.iter() {
-                            // Match on TypedTransaction variants to extract fields
-                            match tx.clone() {
-                                TypedTransaction::Eip1559(req) => {
-                                    let gas_limit = req.gas.unwrap_or(U256::from(21000u64)).as_u128();
-                                    let base_price = req.max_fee_per_gas.map(|m| m.as_u128()).unwrap_or(0u128);
-                                    let new_price = ((base_price as f64) * factor) as u128;
-                                    worst_case_cost = worst_case_cost.saturating_add(gas_limit.saturating_mul(new_price));
-                                }
-                                TypedTransaction::Legacy(req) => {
-                                    let gas_limit = req.gas.unwrap_or(U256::from(21000u64)).as_u128();
-                                    let base_price = req.gas_price.map(|p| p.as_u128()).unwrap_or(0u128);
-                                    let new_price = ((base_price as f64) * factor) as u128;
-                                    worst_case_cost = worst_case_cost.saturating_add(gas_limit.saturating_mul(new_price));
-                                }
-                                _ => {
-                                    // Unknown tx type: assume minimal gas and zero price
-                                    worst_case_cost = worst_case_cost.saturating_add(21000u128.saturating_mul(0u128));
-                                }
-                            }
-                        }
+                        let worst_case_cost = self.worst_case_cost_wei(&bumped, 1.0);
 
                         if let Some(max_gas) = self.config.kill_switch_max_gas_wei {
                             if worst_case_cost > max_gas {
@@ -165,43 +340,16 @@ impl Autosubmitter {
                             }
                         }
 
-                        // Construct bumped unsigned txs
                         let mut bumped_signed_blob: Vec<Vec<u8>> = Vec::new();
-                        for tx in unsigned.iter() {
-                            match tx.clone() {
-                                TypedTransaction::Eip1559(req) => {
-                                    let mfp = req.max_fee_per_gas.unwrap_or_else(|| U256::from(0u64));
-                                    let new_mfp = U256::from(((mfp.as_u128() as f64) * factor) as u128);
-                                    let mpp = req.max_priority_fee_per_gas.unwrap_or_else(|| U256::from(0u64));
-                                    let new_mpp = U256::from(((mpp.as_u128() as f64) * factor) as u128);
-                                    let mut req2 = req.clone();
-                                    req2 = req2.max_fee_per_gas(new_mfp);
-                                    req2 = req2.max_priority_fee_per_gas(new_mpp);
-                                    let t2 = TypedTransaction::Eip1559(req2);
-                                    let signed = signer_arc.sign_typed_transaction(&t2).await.map_err(|e| anyhow::anyhow!(e.to_string()))?;
-                                    bumped_signed_blob.push(signed);
-                                }
-                                TypedTransaction::Legacy(req) => {
-                                    let gp = req.gas_price.unwrap_or_else(|| U256::from(0u64));
-                                    let new_gp = U256::from(((gp.as_u128() as f64) * factor) as u128);
-                                    let mut req2 = req.clone();
-                                    req2 = req2.gas_price(new_gp);
-                                    let t2 = TypedTransaction::Legacy(req2);
-                                    let signed = signer_arc.sign_typed_transaction(&t2).await.map_err(|e| anyhow::anyhow!(e.to_string()))?;
-                                    bumped_signed_blob.push(signed);
-                                }
-                                other => {
-                                    // For unknown typed txs, attempt to sign as-is
-                                    let signed = signer_arc.sign_typed_transaction(&other).await.map_err(|e| anyhow::anyhow!(e.to_string()))?;
-                                    bumped_signed_blob.push(signed);
-                                }
-                            }
+                        for t2 in bumped.iter() {
+                            let signed = signer_arc.sign_typed_transaction(t2).await.map_err(|e| anyhow::anyhow!(e.to_string()))?;
+                            bumped_signed_blob.push(signed);
                         }
 
-                        // Broadcast bumped submissions
-                        for raw in bumped_signed_blob.iter() {
-                            let b = Bytes::from(raw.clone());
-                            let _ = provider.send_raw_transaction(b).await;
+                        // Resubmit bumped txs through the same route
+                        match self.dispatch(policy, &bumped_signed_blob, relay, &provider).await {
+                            Ok(dispatched) => last = dispatched,
+                            Err(e) => tracing::warn!("bumped resubmission failed: {:?}", e),
                         }
 
                         #[cfg(feature = "with-metrics")]
@@ -226,14 +374,15 @@ impl Autosubmitter {
                     }
 
                     // All bumps exhausted
+                    explain_miss(relay, last).await;
                     return Err(anyhow::anyhow!("exhausted gas bump attempts without inclusion"));
                 } else {
                     // No signer available — perform direct re-broadcasts only
                     let mut retry_count = 0usize;
                     while retry_count < self.config.max_retries {
-                        for raw in signed_blob.iter() {
-                            let b = Bytes::from(raw.clone());
-                            let _ = provider.send_raw_transaction(b).await;
+                        match self.dispatch(policy, &signed_blob, relay, &provider).await {
+                            Ok(dispatched) => last = dispatched,
+                            Err(e) => tracing::warn!("resubmission failed: {:?}", e),
                         }
                         retry_count += 1;
                         sleep(Duration::from_secs(1)).await;
@@ -244,4 +393,387 @@ impl Autosubmitter {
             sleep(Duration::from_secs(self.config.poll_interval_secs)).await;
         }
     }
+
+    /// Send `signed_blob` along the route chosen by `policy`, falling back to the
+    /// public mempool only when `config.public_fallback` is set. Txs the private route
+    /// already accepted are not re-sent publicly.
+    async fn dispatch(&self, policy: SubmissionPolicy, signed_blob: &[Vec<u8>], relay: &dyn BundleRelay, provider: &Provider<Http>) -> Result<Dispatched> {
+        let dispatched = self.route(policy, signed_blob, relay, provider).await?;
+        tracing::debug!(route = dispatched.label(), "submission dispatched");
+        #[cfg(feature = "with-metrics")]
+        {
+            metrics::counter!(format!("autosubmit.submissions.{}", dispatched.label())).increment(1);
+        }
+        Ok(dispatched)
+    }
+
+    /// `dispatch` without the metrics.
+    async fn route(&self, policy: SubmissionPolicy, signed_blob: &[Vec<u8>], relay: &dyn BundleRelay, provider: &Provider<Http>) -> Result<Dispatched> {
+        let private = match policy {
+            SubmissionPolicy::Public => return broadcast_public(signed_blob, provider).await.map(|()| Dispatched::Public),
+            SubmissionPolicy::BundleOnly => send_bundle(signed_blob, relay, provider).await,
+            SubmissionPolicy::PrivateTx { max_blocks, fast } => {
+                send_private(signed_blob, relay, provider, max_blocks, fast).await.map(|()| Dispatched::Private)
+            }
+        };
+        match private {
+            Ok(dispatched) => Ok(dispatched),
+            Err((accepted, e)) if self.config.public_fallback => {
+                tracing::warn!(accepted, "private submission failed ({:?}); public fallback enabled for the remaining txs", e);
+                broadcast_public(&signed_blob[accepted..], provider).await.map(|()| Dispatched::Public)
+            }
+            Err((_, e)) => Err(e),
+        }
+    }
+}
+
+/// Route `dispatch` sent a blob along. A relay bundle keeps what its stats are looked up by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dispatched {
+    Bundle { bundle_hash: H256, block: u64 },
+    Private,
+    Public,
+}
+
+impl Dispatched {
+    fn label(&self) -> &'static str {
+        match self {
+            Dispatched::Bundle { .. } => "relay",
+            Dispatched::Private => "private",
+            Dispatched::Public => "public",
+        }
+    }
+}
+
+/// Log why a relay bundle was not included; other routes have no stats to look up.
+async fn explain_miss(relay: &dyn BundleRelay, dispatched: Dispatched) {
+    if let Dispatched::Bundle { bundle_hash, block } = dispatched {
+        crate::retarget::explain_miss(relay, bundle_hash, block).await;
+    }
+}
+
+/// Send the bundle for the block after the current head.
+async fn send_bundle(signed_blob: &[Vec<u8>], relay: &dyn BundleRelay, provider: &Provider<Http>) -> std::result::Result<Dispatched, (usize, anyhow::Error)> {
+    let block = provider.get_block_number().await.context("eth_blockNumber failed").map_err(|e| (0, e))?.as_u64() + 1;
+    let resp = relay.send_bundle(signed_blob, Some(block)).await
+        .map_err(|e| (0, anyhow::anyhow!("relay bundle submission failed: {}", e)))?;
+    tracing::info!(bundle_hash = ?resp.bundle_hash, block, "submitted bundle to relay");
+    Ok(Dispatched::Bundle { bundle_hash: resp.bundle_hash, block })
+}
+
+/// Send each tx with `eth_sendPrivateTransaction`, in order. On failure, returns how
+/// many txs were accepted before it along with the error.
+async fn send_private(signed_blob: &[Vec<u8>], relay: &dyn BundleRelay, provider: &Provider<Http>, max_blocks: u64, fast: bool) -> std::result::Result<(), (usize, anyhow::Error)> {
+    let head = provider.get_block_number().await.context("eth_blockNumber failed").map_err(|e| (0, e))?.as_u64();
+    for (accepted, raw) in signed_blob.iter().enumerate() {
+        let hash = relay.send_private_transaction(raw, Some(head + max_blocks.max(1)), fast).await
+            .map_err(|e| (accepted, anyhow::anyhow!("eth_sendPrivateTransaction failed: {}", e)))?;
+        tracing::info!(tx_hash = ?hash, max_block = head + max_blocks.max(1), "submitted private transaction");
+    }
+    Ok(())
+}
+
+async fn broadcast_public(signed_blob: &[Vec<u8>], provider: &Provider<Http>) -> Result<()> {
+    for raw in signed_blob.iter() {
+        provider.send_raw_transaction(Bytes::from(raw.clone())).await.context("send_raw_transaction failed")?;
+    }
+    Ok(())
+}
+
+/// Scale the fee fields of `tx` by `factor`. When a recommendation is given, each
+/// field is raised to at least the recommended value.
+pub fn bump_tx_fees(tx: &TypedTransaction, factor: f64, recommendation: Option<&FeeRecommendation>) -> TypedTransaction {
+    let scale = |v: U256| U256::from(((v.as_u128() as f64) * factor) as u128);
+    match tx.clone() {
+        TypedTransaction::Eip1559(req) => {
+            let mut new_mfp = scale(req.max_fee_per_gas.unwrap_or_else(|| U256::from(0u64)));
+            let mut new_mpp = scale(req.max_priority_fee_per_gas.unwrap_or_else(|| U256::from(0u64)));
+            if let Some(rec) = recommendation {
+                new_mfp = new_mfp.max(rec.max_fee_per_gas);
+                new_mpp = new_mpp.max(rec.max_priority_fee_per_gas);
+            }
+            TypedTransaction::Eip1559(req.max_fee_per_gas(new_mfp).max_priority_fee_per_gas(new_mpp.min(new_mfp)))
+        }
+        TypedTransaction::Legacy(req) => {
+            let mut new_gp = scale(req.gas_price.unwrap_or_else(|| U256::from(0u64)));
+            if let Some(rec) = recommendation {
+                new_gp = new_gp.max(rec.max_fee_per_gas);
+            }
+            TypedTransaction::Legacy(req.gas_price(new_gp))
+        }
+        // For unknown typed txs, re-sign as-is
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::l2fee::L1FeeParams;
+    use crate::tx::build_eip1559_tx;
+    use ethers_core::types::Address;
+
+    #[test]
+    fn worst_case_cost_includes_l1_data_fee() {
+        let tx = build_eip1559_tx(
+            U256::from(0u64),
+            Address::zero(),
+            U256::from(0u64),
+            Bytes::from(vec![]),
+            U256::from(21000u64),
+            U256::from(1u64),
+            U256::from(10u64),
+            10u64,
+        );
+        let unsigned = vec![tx.clone()];
+        let plain = Autosubmitter::new("http://127.0.0.1:8545".to_string(), AutosubmitConfig::default());
+        assert_eq!(plain.worst_case_cost_wei(&unsigned, 1.0), 210_000);
+
+        let params = L1FeeParams::Bedrock {
+            l1_base_fee: U256::from(1_000u64),
+            overhead: U256::from(0u64),
+            scalar: U256::from(1u64),
+            decimals: U256::from(0u64),
+        };
+        let config = AutosubmitConfig { l1_fee_params: Some(params), ..Default::default() };
+        let l2 = Autosubmitter::new("http://127.0.0.1:8545".to_string(), config);
+        let l1_fee = params.l1_fee_unsigned(&tx.rlp()).as_u128();
+        assert!(l1_fee > 0);
+        assert_eq!(l2.worst_case_cost_wei(&unsigned, 1.0), 210_000 + l1_fee);
+    }
+
+    #[tokio::test]
+    async fn replaceable_bundles_reuse_uuid_and_cancel() {
+        let server = httpmock::MockServer::start();
+        let send = server.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/").body_contains("eth_sendBundle").body_contains("replacementUuid");
+            then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"result":{"bundleHash":"0x2228f5d8954ce31dc1601a8ba264dbd401bf1428388ce88238932815c5d6f23f"}}"#);
+        });
+        let cancel = server.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/").body_contains("eth_cancelBundle");
+            then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"result":null}"#);
+        });
+        let relay = crate::executor::RelayClient::with_url(server.url("/")).unwrap();
+        let a = Autosubmitter::new("http://127.0.0.1:8545".to_string(), AutosubmitConfig::default());
+
+        assert!(a.outstanding("opp-1").is_none());
+        let first = a.submit_replaceable("opp-1", &[vec![0x01]], &relay, Some(10), BundleOptions::default()).await.unwrap();
+        let second = a.submit_replaceable("opp-1", &[vec![0x02]], &relay, Some(11), BundleOptions::default()).await.unwrap();
+        assert_eq!(first.replacement_uuid, second.replacement_uuid);
+        assert_eq!(second.revision, 2);
+        assert_eq!(a.outstanding("opp-1").unwrap().target_block, Some(11));
+        send.assert_hits(2);
+
+        assert!(a.cancel_outstanding("opp-1", &relay).await.unwrap());
+        assert!(!a.cancel_outstanding("opp-1", &relay).await.unwrap());
+        assert!(a.outstanding("opp-1").is_none());
+        cancel.assert_hits(1);
+
+        // a failed first submission leaves nothing to cancel
+        let down = httpmock::MockServer::start();
+        down.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/");
+            then.status(503).body("unavailable");
+        });
+        let down_relay = crate::executor::RelayClient::with_url(down.url("/")).unwrap();
+        assert!(a.submit_replaceable("opp-2", &[vec![0x01]], &down_relay, Some(10), BundleOptions::default()).await.is_err());
+        assert!(!a.cancel_outstanding("opp-2", &relay).await.unwrap());
+        cancel.assert_hits(1);
+
+        // cancelling during the first send does not orphan the bundle the relay ends up holding
+        let slow = httpmock::MockServer::start();
+        slow.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/").body_contains("eth_sendBundle");
+            then.status(200).delay(Duration::from_millis(200))
+                .body(r#"{"jsonrpc":"2.0","id":1,"result":{"bundleHash":"0x2228f5d8954ce31dc1601a8ba264dbd401bf1428388ce88238932815c5d6f23f"}}"#);
+        });
+        let slow_relay = crate::executor::RelayClient::with_url(slow.url("/")).unwrap();
+        let blob = [vec![0x01]];
+        let (sent, cancelled) = tokio::join!(
+            a.submit_replaceable("opp-3", &blob, &slow_relay, Some(10), BundleOptions::default()),
+            async {
+                sleep(Duration::from_millis(50)).await;
+                a.cancel_outstanding("opp-3", &relay).await
+            }
+        );
+        assert!(!cancelled.unwrap());
+        assert_eq!(a.outstanding("opp-3").unwrap().replacement_uuid, sent.unwrap().replacement_uuid);
+    }
+
+    #[tokio::test]
+    async fn bundle_only_never_touches_public_mempool() {
+        let rpc = httpmock::MockServer::start();
+        rpc.mock(|when, then| {
+            when.method(httpmock::Method::POST).body_contains("eth_blockNumber");
+            then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"result":"0x64"}"#);
+        });
+        let raw_send = rpc.mock(|when, then| {
+            when.method(httpmock::Method::POST).body_contains("eth_sendRawTransaction");
+            then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"result":"0x2228f5d8954ce31dc1601a8ba264dbd401bf1428388ce88238932815c5d6f23f"}"#);
+        });
+        let relay_server = httpmock::MockServer::start();
+        relay_server.mock(|when, then| {
+            when.method(httpmock::Method::POST);
+            then.status(503).body("unavailable");
+        });
+        let relay = crate::executor::RelayClient::with_url(relay_server.url("/")).unwrap();
+        let provider = Provider::<Http>::try_from(rpc.url("/").as_str()).unwrap();
+
+        let a = Autosubmitter::new(rpc.url("/"), AutosubmitConfig::default());
+        assert!(a.dispatch(SubmissionPolicy::BundleOnly, &[vec![0x01]], &relay, &provider).await.is_err());
+        raw_send.assert_hits(0);
+
+        let config = AutosubmitConfig { public_fallback: true, ..Default::default() };
+        let a = Autosubmitter::new(rpc.url("/"), config);
+        a.dispatch(SubmissionPolicy::BundleOnly, &[vec![0x01]], &relay, &provider).await.unwrap();
+        raw_send.assert_hits(1);
+    }
+
+    #[tokio::test]
+    async fn private_policy_sets_max_block_and_fast_mode() {
+        let rpc = httpmock::MockServer::start();
+        rpc.mock(|when, then| {
+            when.method(httpmock::Method::POST).body_contains("eth_blockNumber");
+            then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"result":"0x64"}"#);
+        });
+        let relay_server = httpmock::MockServer::start();
+        let private = relay_server.mock(|when, then| {
+            when.method(httpmock::Method::POST)
+                .body_contains("eth_sendPrivateTransaction")
+                .body_contains(r#""maxBlockNumber":"0x69""#)
+                .body_contains(r#""fast":true"#)
+                .body_contains(r#""tx":"0x0102""#);
+            then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"result":"0x2228f5d8954ce31dc1601a8ba264dbd401bf1428388ce88238932815c5d6f23f"}"#);
+        });
+        let relay = crate::executor::RelayClient::with_url(relay_server.url("/")).unwrap();
+        let provider = Provider::<Http>::try_from(rpc.url("/").as_str()).unwrap();
+
+        let a = Autosubmitter::new(rpc.url("/"), AutosubmitConfig::default());
+        let policy = SubmissionPolicy::PrivateTx { max_blocks: 5, fast: true };
+        a.dispatch(policy, &[vec![0x01, 0x02]], &relay, &provider).await.unwrap();
+        private.assert();
+    }
+
+    #[tokio::test]
+    async fn public_fallback_skips_txs_already_sent_privately() {
+        let rpc = httpmock::MockServer::start();
+        rpc.mock(|when, then| {
+            when.method(httpmock::Method::POST).body_contains("eth_blockNumber");
+            then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"result":"0x64"}"#);
+        });
+        let raw = |tx: &'static str| rpc.mock(move |when, then| {
+            when.method(httpmock::Method::POST).body_contains("eth_sendRawTransaction").body_contains(format!(r#""{}""#, tx));
+            then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"result":"0x2228f5d8954ce31dc1601a8ba264dbd401bf1428388ce88238932815c5d6f23f"}"#);
+        });
+        let raw_sends = [raw("0x01"), raw("0x02"), raw("0x03")];
+        let relay_server = httpmock::MockServer::start();
+        let accepted = relay_server.mock(|when, then| {
+            when.method(httpmock::Method::POST).body_contains("eth_sendPrivateTransaction").body_contains(r#""tx":"0x01""#);
+            then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"result":"0x2228f5d8954ce31dc1601a8ba264dbd401bf1428388ce88238932815c5d6f23f"}"#);
+        });
+        let rejected = relay_server.mock(|when, then| {
+            when.method(httpmock::Method::POST).body_contains("eth_sendPrivateTransaction").body_contains(r#""tx":"0x02""#);
+            then.status(503).body("unavailable");
+        });
+        let relay = crate::executor::RelayClient::with_url(relay_server.url("/")).unwrap();
+        let provider = Provider::<Http>::try_from(rpc.url("/").as_str()).unwrap();
+        let policy = SubmissionPolicy::PrivateTx { max_blocks: 5, fast: false };
+        let blob = [vec![0x01], vec![0x02], vec![0x03]];
+
+        let a = Autosubmitter::new(rpc.url("/"), AutosubmitConfig::default());
+        assert!(a.dispatch(policy, &blob, &relay, &provider).await.is_err());
+        assert!(raw_sends.iter().all(|m| m.hits() == 0));
+
+        let config = AutosubmitConfig { public_fallback: true, ..Default::default() };
+        let a = Autosubmitter::new(rpc.url("/"), config);
+        a.dispatch(policy, &blob, &relay, &provider).await.unwrap();
+        accepted.assert_hits(2);
+        rejected.assert_hits(2);
+        // the first tx went private; only the rest reach the mempool
+        assert_eq!(raw_sends.iter().map(|m| m.hits()).collect::<Vec<_>>(), vec![0, 1, 1]);
+    }
+
+    #[tokio::test]
+    async fn resubmission_explains_why_the_bundle_missed() {
+        let rpc = httpmock::MockServer::start();
+        rpc.mock(|when, then| {
+            when.method(httpmock::Method::POST).body_contains("eth_blockNumber");
+            then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"result":"0x64"}"#);
+        });
+        rpc.mock(|when, then| {
+            when.method(httpmock::Method::POST).body_contains("eth_getTransactionReceipt");
+            then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"result":null}"#);
+        });
+        let config = AutosubmitConfig { poll_interval_secs: 1, max_wait_secs: 1, max_bumps: 1, ..Default::default() };
+        let tx = build_eip1559_tx(U256::zero(), Address::zero(), U256::zero(), Bytes::default(), U256::from(21000u64), U256::from(2u64), U256::from(100u64), 1);
+        let signer: Arc<dyn crate::signer::Signer> = Arc::new(crate::signer::BasicEnvSigner::from_secret("0123456789012345678901234567890123456789012345678901234567890123".to_string()));
+        let signed = vec![signer.sign_typed_transaction(&tx).await.unwrap()];
+        let bumped = signer.sign_typed_transaction(&bump_tx_fees(&tx, config.bump_factor, None)).await.unwrap();
+
+        let relay_server = httpmock::MockServer::start();
+        let bundle = |raw: &[u8], hash: &'static str| {
+            let raw = format!("0x{}", hex::encode(raw));
+            relay_server.mock(move |when, then| {
+                when.method(httpmock::Method::POST).body_contains("eth_sendBundle").body_contains(raw).body_contains(r#""blockNumber":"0x65""#);
+                then.status(200).body(format!(r#"{{"jsonrpc":"2.0","id":1,"result":{{"bundleHash":"{}"}}}}"#, hash));
+            })
+        };
+        let first = bundle(&signed[0], "0x2228f5d8954ce31dc1601a8ba264dbd401bf1428388ce88238932815c5d6f23f");
+        let second = bundle(&bumped, "0x1111111111111111111111111111111111111111111111111111111111111111");
+        let stats = |hash: &'static str| {
+            relay_server.mock(move |when, then| {
+                when.method(httpmock::Method::POST)
+                    .body_contains("flashbots_getBundleStatsV2")
+                    .body_contains(hash)
+                    .body_contains(r#""blockNumber":"0x65""#);
+                then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"result":{"isHighPriority":false,"isSimulated":false}}"#);
+            })
+        };
+        let first_stats = stats("0x2228f5d8954ce31dc1601a8ba264dbd401bf1428388ce88238932815c5d6f23f");
+        let second_stats = stats("0x1111111111111111111111111111111111111111111111111111111111111111");
+        let relay = crate::executor::RelayClient::with_url(relay_server.url("/")).unwrap();
+
+        let a = Autosubmitter::new(rpc.url("/"), config);
+        let res = a.submit_and_monitor_with_policy(SubmissionPolicy::BundleOnly, Some(&[tx]), Some(signer), signed, &relay, None).await;
+        assert!(res.is_err());
+        // the first submission and the bump each have their own miss looked up
+        first.assert();
+        second.assert();
+        first_stats.assert();
+        second_stats.assert();
+    }
+
+    #[test]
+    fn bump_tx_fees_respects_recommendation_floor() {
+        let tx = build_eip1559_tx(
+            U256::from(0u64),
+            Address::zero(),
+            U256::from(0u64),
+            Bytes::from(vec![]),
+            U256::from(21000u64),
+            U256::from(2u64),
+            U256::from(100u64),
+            1u64,
+        );
+        let scaled = bump_tx_fees(&tx, 1.5, None);
+        if let TypedTransaction::Eip1559(r) = scaled {
+            assert_eq!(r.max_fee_per_gas, Some(U256::from(150u64)));
+            assert_eq!(r.max_priority_fee_per_gas, Some(U256::from(3u64)));
+        } else {
+            panic!("expected eip1559");
+        }
+
+        let rec = FeeRecommendation {
+            predicted_base_fee: U256::from(180u64),
+            max_fee_per_gas: U256::from(250u64),
+            max_priority_fee_per_gas: U256::from(10u64),
+        };
+        let bumped = bump_tx_fees(&tx, 1.5, Some(&rec));
+        if let TypedTransaction::Eip1559(r) = bumped {
+            assert_eq!(r.max_fee_per_gas, Some(U256::from(250u64)));
+            assert_eq!(r.max_priority_fee_per_gas, Some(U256::from(10u64)));
+        } else {
+            panic!("expected eip1559");
+        }
+    }
 }
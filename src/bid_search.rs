@@ -0,0 +1,319 @@
+use anyhow::{anyhow, Result};
+use ethers_core::types::transaction::eip2718::TypedTransaction;
+use ethers_core::types::{Address, U256};
+use futures_util::future::join_all;
+use std::sync::Arc;
+use crate::fees::FeeRecommendation;
+use crate::sim::{sign_from_nonce, BalanceWatch, Scorer, SimulationBackend, SimulationOutcome};
+use crate::sim_pool::{ForkWorker, SimPool};
+use crate::signer::Signer;
+use crate::tx::build_coinbase_transfer_tx;
+
+/// One point of the bid grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BidParams {
+    pub priority_fee: U256,
+    /// Paid to the block's coinbase by a transfer appended to the bundle; zero adds no leg
+    pub coinbase_payment: U256,
+    /// Passed to the bundle builder, e.g. the input amount of the trade
+    pub trade_size: U256,
+}
+
+/// Chance that a bundle with these bid parameters is included in its target block.
+pub trait InclusionModel: Send + Sync {
+    /// `gas_used` is the simulated gas of the whole bundle.
+    fn inclusion_probability(&self, params: &BidParams, gas_used: u64) -> f64;
+}
+
+/// Builders order bundles by what they pay per unit of gas (tips plus coinbase payment).
+/// Inclusion rises with that effective tip as `tip / (tip + half_at)`, so a bundle paying
+/// `half_at` wei per gas lands half of the time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TipInclusionModel {
+    pub half_at: U256,
+}
+
+impl TipInclusionModel {
+    pub fn new(half_at: U256) -> Self {
+        Self { half_at }
+    }
+}
+
+impl InclusionModel for TipInclusionModel {
+    fn inclusion_probability(&self, params: &BidParams, gas_used: u64) -> f64 {
+        if gas_used == 0 {
+            return 0.0;
+        }
+        let paid = params.priority_fee.saturating_mul(U256::from(gas_used)).saturating_add(params.coinbase_payment);
+        let tip = wei_to_f64(paid / U256::from(gas_used));
+        let half = wei_to_f64(self.half_at);
+        if tip + half == 0.0 { 0.0 } else { tip / (tip + half) }
+    }
+}
+
+/// A simulated grid point, ranked by `expected_value`.
+#[derive(Debug, Clone)]
+pub struct BidCandidate {
+    pub params: BidParams,
+    pub score: i128,
+    pub inclusion_probability: f64,
+    /// `score * inclusion_probability`; a bundle that is not included costs nothing
+    pub expected_value: f64,
+    pub signed_txs: Vec<Vec<u8>>,
+    pub outcome: SimulationOutcome,
+}
+
+/// Grid search over priority fee, coinbase payment and trade size. Every combination is
+/// built, signed from `base_nonce`, simulated, scored with `Scorer::score_outcome` and
+/// weighted by an `InclusionModel`. `run` simulates the points one after another on one
+/// backend; `run_pooled` leases a `SimPool` worker per point and runs them side by side.
+#[derive(Debug, Clone)]
+pub struct BidSearch {
+    pub priority_fees: Vec<U256>,
+    pub coinbase_payments: Vec<U256>,
+    pub trade_sizes: Vec<U256>,
+    /// Fee cap and base fee prediction; each point swaps in its own priority fee
+    pub fees: FeeRecommendation,
+    pub base_nonce: u64,
+    pub chain_id: u64,
+    pub coinbase: Address,
+    /// Accounts and tokens the scorer's realized PnL is read from
+    pub watch: BalanceWatch,
+}
+
+impl BidSearch {
+    pub fn new(fees: FeeRecommendation, base_nonce: u64, chain_id: u64, coinbase: Address, watch: BalanceWatch) -> Self {
+        Self {
+            priority_fees: vec![fees.max_priority_fee_per_gas],
+            coinbase_payments: vec![U256::zero()],
+            trade_sizes: vec![U256::zero()],
+            fees,
+            base_nonce,
+            chain_id,
+            coinbase,
+            watch,
+        }
+    }
+
+    pub fn with_priority_fees(mut self, fees: Vec<U256>) -> Self {
+        self.priority_fees = fees;
+        self
+    }
+
+    pub fn with_coinbase_payments(mut self, payments: Vec<U256>) -> Self {
+        self.coinbase_payments = payments;
+        self
+    }
+
+    pub fn with_trade_sizes(mut self, sizes: Vec<U256>) -> Self {
+        self.trade_sizes = sizes;
+        self
+    }
+
+    pub fn grid(&self) -> Vec<BidParams> {
+        let mut grid = Vec::with_capacity(self.priority_fees.len() * self.coinbase_payments.len() * self.trade_sizes.len());
+        for &trade_size in &self.trade_sizes {
+            for &priority_fee in &self.priority_fees {
+                for &coinbase_payment in &self.coinbase_payments {
+                    grid.push(BidParams { priority_fee, coinbase_payment, trade_size });
+                }
+            }
+        }
+        grid
+    }
+
+    /// Fees for one grid point: the cap keeps the base fee headroom of `self.fees`.
+    fn fees_for(&self, priority_fee: U256) -> FeeRecommendation {
+        let headroom = self.fees.max_fee_per_gas.saturating_sub(self.fees.max_priority_fee_per_gas);
+        FeeRecommendation { max_priority_fee_per_gas: priority_fee, max_fee_per_gas: headroom.saturating_add(priority_fee), ..self.fees }
+    }
+
+    /// Unsigned bundle for `params`: the trade from `build`, with fees set, followed by
+    /// the coinbase transfer when one is paid.
+    pub fn bundle_for<F>(&self, params: &BidParams, build: &F) -> Result<Vec<TypedTransaction>>
+    where
+        F: Fn(U256) -> Result<Vec<TypedTransaction>>,
+    {
+        let fees = self.fees_for(params.priority_fee);
+        let mut txs: Vec<TypedTransaction> = build(params.trade_size)?.iter().map(|tx| fees.apply_to(tx)).collect();
+        if !params.coinbase_payment.is_zero() {
+            let nonce = U256::from(self.base_nonce + txs.len() as u64);
+            txs.push(build_coinbase_transfer_tx(nonce, self.coinbase, params.coinbase_payment, fees.max_priority_fee_per_gas, fees.max_fee_per_gas, self.chain_id));
+        }
+        Ok(txs)
+    }
+
+    /// Simulate every grid point and return the candidates, best expected value first.
+    /// Points that fail to build or simulate are logged and skipped.
+    pub async fn run<B, S, C, M, F>(&self, backend: &B, signer: &S, scorer: &C, model: &M, build: F) -> Result<Vec<BidCandidate>>
+    where
+        B: SimulationBackend + ?Sized,
+        S: Signer + ?Sized,
+        C: Scorer + ?Sized,
+        M: InclusionModel + ?Sized,
+        F: Fn(U256) -> Result<Vec<TypedTransaction>>,
+    {
+        let mut candidates = Vec::new();
+        for params in self.grid() {
+            candidates.extend(self.candidate(backend, signer, scorer, model, params, &build).await);
+        }
+        Ok(rank(candidates))
+    }
+
+    /// `run` with each grid point simulated on its own leased worker, at most
+    /// `pool.size()` points at a time.
+    pub async fn run_pooled<W, S, C, M, F>(&self, pool: &Arc<SimPool<W>>, signer: &S, scorer: &C, model: &M, build: F) -> Result<Vec<BidCandidate>>
+    where
+        W: ForkWorker,
+        S: Signer + ?Sized,
+        C: Scorer + ?Sized,
+        M: InclusionModel + ?Sized,
+        F: Fn(U256) -> Result<Vec<TypedTransaction>>,
+    {
+        let build = &build;
+        let attempts = self.grid().into_iter().map(|params| async move {
+            match pool.lease().await {
+                Ok(worker) => self.candidate(&*worker, signer, scorer, model, params, build).await,
+                Err(e) => {
+                    tracing::warn!(?params, "no simulator for bid attempt: {:?}", e);
+                    None
+                }
+            }
+        });
+        Ok(rank(join_all(attempts).await.into_iter().flatten().collect()))
+    }
+
+    /// Build, sign, simulate and score one grid point. Failures are logged and give `None`.
+    async fn candidate<B, S, C, M, F>(&self, backend: &B, signer: &S, scorer: &C, model: &M, params: BidParams, build: &F) -> Option<BidCandidate>
+    where
+        B: SimulationBackend + ?Sized,
+        S: Signer + ?Sized,
+        C: Scorer + ?Sized,
+        M: InclusionModel + ?Sized,
+        F: Fn(U256) -> Result<Vec<TypedTransaction>>,
+    {
+        let attempt = async {
+            let unsigned = self.bundle_for(&params, build)?;
+            let signed_txs = sign_from_nonce(&unsigned, signer, self.base_nonce).await?;
+            let outcome = backend.simulate_bundle_with_balances(&signed_txs, Some(self.fees.predicted_base_fee), &self.watch).await?;
+            Ok::<_, anyhow::Error>((signed_txs, outcome))
+        };
+        let (signed_txs, outcome) = match attempt.await {
+            Ok(r) => r,
+            Err(e) => {
+                tracing::warn!(?params, "bid attempt failed: {:?}", e);
+                return None;
+            }
+        };
+        let score = scorer.score_outcome(&outcome, &signed_txs);
+        let gas_used: u64 = outcome.receipts.iter().map(|r| r.gas_used.unwrap_or_default().low_u64()).sum();
+        let inclusion_probability = model.inclusion_probability(&params, gas_used).clamp(0.0, 1.0);
+        Some(BidCandidate { params, score, inclusion_probability, expected_value: score as f64 * inclusion_probability, signed_txs, outcome })
+    }
+
+    /// The candidate with the best expected value, if any point simulated.
+    pub async fn best<B, S, C, M, F>(&self, backend: &B, signer: &S, scorer: &C, model: &M, build: F) -> Result<BidCandidate>
+    where
+        B: SimulationBackend + ?Sized,
+        S: Signer + ?Sized,
+        C: Scorer + ?Sized,
+        M: InclusionModel + ?Sized,
+        F: Fn(U256) -> Result<Vec<TypedTransaction>>,
+    {
+        self.run(backend, signer, scorer, model, build).await?.into_iter().next().ok_or_else(|| anyhow!("no bid candidate simulated"))
+    }
+}
+
+/// Best expected value first.
+fn rank(mut candidates: Vec<BidCandidate>) -> Vec<BidCandidate> {
+    candidates.sort_by(|a, b| b.expected_value.total_cmp(&a.expected_value));
+    candidates
+}
+
+fn wei_to_f64(v: U256) -> f64 {
+    match <U256 as TryInto<u128>>::try_into(v) {
+        Ok(v) => v as f64,
+        Err(_) => f64::MAX,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::revm_backend::fixtures::{funded_db, signer, FixedProfit};
+    use crate::revm_backend::{RevmBackend, SimBlock};
+    use crate::sim::Token;
+    use crate::tx::build_eip1559_tx;
+    use ethers_core::types::Bytes;
+    use revm::db::InMemoryDB;
+
+    #[test]
+    fn tip_model_rises_with_effective_tip() {
+        let model = TipInclusionModel::new(U256::from(2_000_000_000u64));
+        let params = |priority_fee: u64, coinbase_payment: u64| BidParams { priority_fee: priority_fee.into(), coinbase_payment: coinbase_payment.into(), trade_size: U256::zero() };
+        assert_eq!(model.inclusion_probability(&params(2_000_000_000, 0), 100_000), 0.5);
+        // 2e14 wei over 100k gas adds another 2 gwei per gas
+        let with_payment = model.inclusion_probability(&params(2_000_000_000, 200_000_000_000_000), 100_000);
+        assert!((with_payment - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(model.inclusion_probability(&params(0, 0), 100_000), 0.0);
+    }
+
+    #[tokio::test]
+    async fn picks_best_expected_value_over_fee_payment_and_size() {
+        let signer = signer();
+        let from = signer.address().await.unwrap().unwrap();
+        let db = funded_db(&signer, U256::exp10(20)).await;
+        let coinbase = Address::repeat_byte(0xcb);
+        let block = SimBlock { base_fee: U256::from(1_000_000_000u64), coinbase, ..Default::default() };
+        let backend = RevmBackend::new(db, 1, block);
+
+        let gwei = U256::exp10(9);
+        let fees = FeeRecommendation { predicted_base_fee: gwei, max_fee_per_gas: gwei * 3, max_priority_fee_per_gas: gwei };
+        let search = BidSearch::new(fees, 0, 1, coinbase, BalanceWatch::new(vec![from]))
+            .with_priority_fees(vec![gwei, gwei * 2])
+            .with_coinbase_payments(vec![U256::zero(), U256::exp10(14)])
+            .with_trade_sizes(vec![U256::from(1u64), U256::from(64u64)]);
+        assert_eq!(search.grid().len(), 8);
+
+        // a bigger "trade" only costs more calldata gas; the scorer credits a fixed profit on top of realized PnL
+        let build = |size: U256| Ok(vec![build_eip1559_tx(U256::zero(), Address::repeat_byte(0x77), U256::zero(), Bytes::from(vec![0xff; size.as_usize()]), U256::from(100_000u64), gwei, gwei * 3, 1)]);
+        let model = TipInclusionModel::new(gwei);
+        let candidates = search.run(&backend, &signer, &FixedProfit(10i128.pow(15)), &model, build).await.unwrap();
+        assert_eq!(candidates.len(), 8);
+        assert!(candidates.windows(2).all(|w| w[0].expected_value >= w[1].expected_value));
+
+        let best = &candidates[0];
+        assert_eq!(best.params.trade_size, U256::from(1u64));
+        // paying the builder buys more inclusion than it costs here
+        assert_eq!(best.params.coinbase_payment, U256::exp10(14));
+        assert_eq!(best.signed_txs.len(), 2);
+        assert!(best.outcome.pnl[&Token::Native] < -(10i128.pow(14)));
+        let gas_used = best.outcome.receipts.iter().map(|r| r.gas_used.unwrap().low_u64()).sum();
+        assert_eq!(best.inclusion_probability, model.inclusion_probability(&best.params, gas_used));
+        assert_eq!(best.expected_value, best.score as f64 * best.inclusion_probability);
+
+        // the same grid spread over pooled workers ranks the same way
+        let pool = SimPool::new((0..3).map(|_| Pinned(RevmBackend::new(backend.db().clone(), 1, backend.block()))).collect());
+        let pooled = search.run_pooled(&pool, &signer, &FixedProfit(10i128.pow(15)), &model, build).await.unwrap();
+        let ranked = |c: &[BidCandidate]| c.iter().map(|c| (c.params, c.score)).collect::<Vec<_>>();
+        assert_eq!(ranked(&pooled), ranked(&candidates));
+        assert_eq!(pool.idle(), 3);
+    }
+
+    /// A pool worker over a fixed in-memory state.
+    struct Pinned(RevmBackend<InMemoryDB>);
+
+    #[async_trait::async_trait]
+    impl SimulationBackend for Pinned {
+        async fn simulate_bundle_delayed(&self, signed_raw_txs: &[Vec<u8>], delay: &crate::sim::InclusionDelay, watch: &BalanceWatch) -> Result<SimulationOutcome> {
+            self.0.simulate_bundle_delayed(signed_raw_txs, delay, watch).await
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ForkWorker for Pinned {
+        async fn reset_to(&mut self, _head: &ethers_core::types::Block<ethers_core::types::H256>) -> Result<()> {
+            Ok(())
+        }
+    }
+}
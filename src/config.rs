@@ -1,10 +1,14 @@
 use serde::Deserialize;
+use crate::scoring::ScorerConfig;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
     pub rpc_urls: Vec<String>,
     pub ws_urls: Vec<String>,
     pub profit_threshold_wei: u128,
+    /// Scoring policy for simulated bundles; defaults to the `ConfigurableScorer` preset
+    #[serde(default)]
+    pub scoring: ScorerConfig,
 }
 
 impl Default for Config {
@@ -13,6 +17,7 @@ impl Default for Config {
             rpc_urls: vec!["http://localhost:8545".to_string()],
             ws_urls: vec![],
             profit_threshold_wei: 1_000_000_000_000_000, // example: 0.001 ETH
+            scoring: ScorerConfig::default(),
         }
     }
 }
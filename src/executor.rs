@@ -1,41 +1,490 @@
-use anyhow::{Result, Context};
+use anyhow::Result;
+use ethers_core::types::{Address, H256, U256};
 use reqwest::Client;
+use serde::{Deserialize, Deserializer, Serialize, de::DeserializeOwned};
+use std::sync::Arc;
 use std::time::Duration;
+use crate::auth::{FlashbotsAuth, FLASHBOTS_SIGNATURE_HEADER};
+
+/// Errors returned by relay calls. Callers can match on the variant to decide
+/// whether to retry, fall back to another relay, or give up on the bundle.
+#[derive(Debug)]
+pub enum RelayError {
+    /// No relay URL configured for this client.
+    NotConfigured,
+    /// Connection failure, timeout or other transport-level error.
+    Transport(reqwest::Error),
+    /// Relay answered with a non-success HTTP status and no JSON-RPC error body.
+    Http { status: u16, body: String },
+    /// Relay returned a JSON-RPC error object.
+    JsonRpc { code: i64, message: String, data: Option<serde_json::Value> },
+    /// Response body could not be decoded into the expected shape.
+    InvalidResponse(String),
+    /// Signing the request for `X-Flashbots-Signature` failed.
+    Auth(String),
+    /// The relay did not answer within the caller's deadline.
+    Timeout(Duration),
+    /// The endpoint's circuit breaker is open; the request was not sent.
+    CircuitOpen,
+}
+
+impl RelayError {
+    /// Whether the relay failed to answer in time (client deadline or transport timeout).
+    pub fn is_timeout(&self) -> bool {
+        match self {
+            RelayError::Timeout(_) => true,
+            RelayError::Transport(e) => e.is_timeout(),
+            _ => false,
+        }
+    }
+
+    /// Whether retrying the same request later may succeed (timeouts, 5xx, rate limits).
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            RelayError::Transport(_) | RelayError::Timeout(_) | RelayError::CircuitOpen => true,
+            RelayError::Http { status, .. } => *status == 429 || *status >= 500,
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for RelayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RelayError::NotConfigured => write!(f, "FLASHBOTS_RELAY_URL not configured"),
+            RelayError::Transport(e) => write!(f, "relay request failed: {}", e),
+            RelayError::Http { status, body } => write!(f, "relay returned HTTP {}: {}", status, body),
+            RelayError::JsonRpc { code, message, .. } => write!(f, "relay JSON-RPC error {}: {}", code, message),
+            RelayError::InvalidResponse(msg) => write!(f, "invalid relay response: {}", msg),
+            RelayError::Auth(msg) => write!(f, "relay request signing failed: {}", msg),
+            RelayError::Timeout(d) => write!(f, "relay did not answer within {:?}", d),
+            RelayError::CircuitOpen => write!(f, "relay circuit breaker is open"),
+        }
+    }
+}
+
+impl std::error::Error for RelayError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RelayError::Transport(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Result of `eth_sendBundle`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SendBundleResponse {
+    pub bundle_hash: H256,
+}
+
+/// How a relay or builder speaks `eth_sendBundle`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelayDialect {
+    /// Flashbots relay semantics: result must be `{"bundleHash": ...}`.
+    Flashbots,
+    /// Builder endpoints that accept the Flashbots request but answer with `null`,
+    /// a bare string or an object; the bundle hash is computed locally when missing.
+    Builder,
+}
+
+/// Bundle hash as defined by Flashbots: keccak256 of the concatenated tx hashes.
+pub fn compute_bundle_hash(signed_txs: &[Vec<u8>]) -> H256 {
+    let mut hashes = Vec::with_capacity(signed_txs.len() * 32);
+    for raw in signed_txs.iter() {
+        hashes.extend_from_slice(&ethers_core::utils::keccak256(raw));
+    }
+    H256::from(ethers_core::utils::keccak256(&hashes))
+}
+
+/// Optional `eth_sendBundle` constraints beyond the target block.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BundleOptions {
+    /// Earliest block timestamp (unix seconds) the bundle may be included at
+    pub min_timestamp: Option<u64>,
+    /// Latest block timestamp (unix seconds) the bundle may be included at
+    pub max_timestamp: Option<u64>,
+    /// Transactions allowed to revert without invalidating the bundle
+    pub reverting_tx_hashes: Vec<H256>,
+    /// UUIDv4 identifying a replaceable bundle; a later submission with the same
+    /// UUID replaces this one and `eth_cancelBundle` withdraws it
+    pub replacement_uuid: Option<String>,
+}
+
+impl BundleOptions {
+    /// Whether a block with `timestamp` satisfies the min/max timestamp window.
+    pub fn allows_timestamp(&self, timestamp: u64) -> bool {
+        self.min_timestamp.is_none_or(|min| timestamp >= min) && self.max_timestamp.is_none_or(|max| timestamp <= max)
+    }
+}
+
+/// Random UUIDv4 for `BundleOptions::replacement_uuid`.
+pub fn new_replacement_uuid() -> String {
+    let mut b: [u8; 16] = rand::random();
+    b[6] = (b[6] & 0x0f) | 0x40;
+    b[8] = (b[8] & 0x3f) | 0x80;
+    let h = hex::encode(b);
+    format!("{}-{}-{}-{}-{}", &h[0..8], &h[8..12], &h[12..16], &h[16..20], &h[20..32])
+}
+
+/// Anything that can take a signed bundle: a single `RelayClient` or a fan-out
+/// `relay_set::RelaySet`.
+#[async_trait::async_trait]
+pub trait BundleRelay: Send + Sync {
+    async fn send_bundle_with_options(&self, signed_txs: &[Vec<u8>], block_number: Option<u64>, options: &BundleOptions) -> Result<SendBundleResponse, RelayError>;
+
+    /// Withdraw every bundle submitted with `replacement_uuid`.
+    async fn cancel_bundle(&self, replacement_uuid: &str) -> Result<(), RelayError>;
+
+    /// Send one signed transaction privately via `eth_sendPrivateTransaction`,
+    /// valid until `max_block_number`. Returns the transaction hash.
+    async fn send_private_transaction(&self, _raw_tx: &[u8], _max_block_number: Option<u64>, _fast: bool) -> Result<H256, RelayError> {
+        Err(RelayError::NotConfigured)
+    }
+
+    /// Relay-side status of a bundle for its target block. Only Flashbots-style
+    /// relays expose this; others report `NotConfigured`.
+    async fn bundle_stats(&self, _bundle_hash: H256, _block_number: u64) -> Result<BundleStats, RelayError> {
+        Err(RelayError::NotConfigured)
+    }
+
+    async fn send_bundle(&self, signed_txs: &[Vec<u8>], block_number: Option<u64>) -> Result<SendBundleResponse, RelayError> {
+        self.send_bundle_with_options(signed_txs, block_number, &BundleOptions::default()).await
+    }
+
+    /// Submit the same bundle once per block in `first_block..first_block + count`.
+    /// Relays only consider a bundle for the block it names, so multi-block targeting
+    /// means one `eth_sendBundle` per block.
+    async fn send_bundle_for_blocks(&self, signed_txs: &[Vec<u8>], first_block: u64, count: u64, options: &BundleOptions) -> Vec<(u64, Result<SendBundleResponse, RelayError>)> {
+        let mut results = Vec::with_capacity(count as usize);
+        for block in first_block..first_block + count {
+            results.push((block, self.send_bundle_with_options(signed_txs, Some(block), options).await));
+        }
+        results
+    }
+}
+
+#[async_trait::async_trait]
+impl BundleRelay for RelayClient {
+    async fn send_bundle_with_options(&self, signed_txs: &[Vec<u8>], block_number: Option<u64>, options: &BundleOptions) -> Result<SendBundleResponse, RelayError> {
+        self.submit_bundle_with_options(signed_txs, block_number, RelayDialect::Flashbots, options).await
+    }
+
+    async fn cancel_bundle(&self, replacement_uuid: &str) -> Result<(), RelayError> {
+        RelayClient::cancel_bundle(self, replacement_uuid).await
+    }
+
+    async fn bundle_stats(&self, bundle_hash: H256, block_number: u64) -> Result<BundleStats, RelayError> {
+        self.get_bundle_stats(bundle_hash, block_number).await
+    }
+
+    async fn send_private_transaction(&self, raw_tx: &[u8], max_block_number: Option<u64>, fast: bool) -> Result<H256, RelayError> {
+        RelayClient::send_private_transaction(self, raw_tx, max_block_number, fast).await
+    }
+}
+
+/// Parameters of an `eth_callBundle` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CallBundleParams {
+    /// Block the bundle would be included in
+    pub block_number: u64,
+    /// Block whose post-state the simulation starts from; `None` means "latest"
+    pub state_block_number: Option<u64>,
+    /// Timestamp of the simulated block; relay default is parent timestamp + 12s
+    pub timestamp: Option<u64>,
+    /// Base fee of the simulated block (supported by most builders)
+    pub base_fee: Option<U256>,
+}
+
+impl CallBundleParams {
+    /// Simulate for `block_number` on top of the latest state with relay defaults.
+    pub fn for_block(block_number: u64) -> Self {
+        Self { block_number, state_block_number: None, timestamp: None, base_fee: None }
+    }
+}
+
+/// Result of `eth_callBundle` (relay-side bundle simulation).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallBundleResponse {
+    #[serde(default)]
+    pub bundle_hash: Option<H256>,
+    #[serde(default, deserialize_with = "de_u256")]
+    pub bundle_gas_price: U256,
+    #[serde(default, deserialize_with = "de_u256")]
+    pub coinbase_diff: U256,
+    #[serde(default, deserialize_with = "de_u256")]
+    pub eth_sent_to_coinbase: U256,
+    #[serde(default, deserialize_with = "de_u256")]
+    pub gas_fees: U256,
+    #[serde(default, deserialize_with = "de_u64")]
+    pub state_block_number: u64,
+    #[serde(default, deserialize_with = "de_u64")]
+    pub total_gas_used: u64,
+    #[serde(default)]
+    pub results: Vec<CallBundleTxResult>,
+}
+
+impl CallBundleResponse {
+    /// First tx that failed, if any.
+    pub fn first_revert(&self) -> Option<&CallBundleTxResult> {
+        self.results.iter().find(|r| r.is_revert())
+    }
+}
+
+/// Per-transaction result inside an `eth_callBundle` response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallBundleTxResult {
+    pub tx_hash: H256,
+    #[serde(default)]
+    pub from_address: Option<Address>,
+    #[serde(default)]
+    pub to_address: Option<Address>,
+    #[serde(default, deserialize_with = "de_u64")]
+    pub gas_used: u64,
+    #[serde(default, deserialize_with = "de_u256")]
+    pub gas_price: U256,
+    #[serde(default, deserialize_with = "de_u256")]
+    pub gas_fees: U256,
+    #[serde(default, deserialize_with = "de_u256")]
+    pub coinbase_diff: U256,
+    #[serde(default, deserialize_with = "de_u256")]
+    pub eth_sent_to_coinbase: U256,
+    /// Return data of the call (hex), when it succeeded
+    #[serde(default)]
+    pub value: Option<String>,
+    /// Error string reported by the relay (e.g. "execution reverted")
+    #[serde(default)]
+    pub error: Option<String>,
+    /// Decoded revert reason or raw revert data, when present
+    #[serde(default)]
+    pub revert: Option<String>,
+}
+
+impl CallBundleTxResult {
+    pub fn is_revert(&self) -> bool {
+        self.error.is_some() || self.revert.is_some()
+    }
+}
+
+/// A builder that acted on a bundle, from `flashbots_getBundleStatsV2`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuilderTimestamp {
+    /// Builder BLS public key
+    pub pubkey: String,
+    pub timestamp: String,
+}
+
+/// Result of `flashbots_getBundleStatsV2` for one bundle and target block.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleStats {
+    #[serde(default)]
+    pub is_high_priority: bool,
+    #[serde(default)]
+    pub is_simulated: bool,
+    #[serde(default)]
+    pub simulated_at: Option<String>,
+    #[serde(default)]
+    pub received_at: Option<String>,
+    #[serde(default)]
+    pub considered_by_builders_at: Vec<BuilderTimestamp>,
+    #[serde(default)]
+    pub sealed_by_builders_at: Vec<BuilderTimestamp>,
+}
+
+impl BundleStats {
+    /// Builders whose sealed blocks contained the bundle.
+    pub fn sealed_by(&self) -> Vec<String> {
+        self.sealed_by_builders_at.iter().map(|b| b.pubkey.clone()).collect()
+    }
+
+    /// Why the bundle did not land, given that it was not included on chain.
+    pub fn miss_reason(&self) -> MissReason {
+        if self.received_at.is_none() && !self.is_simulated {
+            MissReason::NotReceived
+        } else if !self.is_simulated {
+            MissReason::NotSimulated
+        } else if self.considered_by_builders_at.is_empty() {
+            MissReason::NotConsidered
+        } else if self.sealed_by_builders_at.is_empty() {
+            MissReason::NotSealed { considered_by: self.considered_by_builders_at.iter().map(|b| b.pubkey.clone()).collect() }
+        } else {
+            MissReason::LostAuction { sealed_by: self.sealed_by() }
+        }
+    }
+}
+
+/// Why a submitted bundle was not included in its target block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MissReason {
+    /// `eth_sendBundle` itself failed for this block
+    SubmissionFailed(String),
+    /// The stats query failed at the transport level; the bundle may or may not have arrived
+    Unreachable(String),
+    /// The relay answered the stats query with an error (commonly: unknown bundle)
+    StatsUnavailable(String),
+    /// The relay has no record of receiving the bundle
+    NotReceived,
+    /// Received but never simulated (late, invalid or deprioritised)
+    NotSimulated,
+    /// Simulated but no builder considered it, usually because it paid too little
+    NotConsidered,
+    /// Considered by builders but left out of every sealed block
+    NotSealed { considered_by: Vec<String> },
+    /// Sealed into blocks by these builders, but another block won the slot
+    LostAuction { sealed_by: Vec<String> },
+}
+
+impl MissReason {
+    /// Short stable name, used as a metrics label.
+    pub fn label(&self) -> &'static str {
+        match self {
+            MissReason::SubmissionFailed(_) => "submission_failed",
+            MissReason::Unreachable(_) => "unreachable",
+            MissReason::StatsUnavailable(_) => "stats_unavailable",
+            MissReason::NotReceived => "not_received",
+            MissReason::NotSimulated => "not_simulated",
+            MissReason::NotConsidered => "not_considered",
+            MissReason::NotSealed { .. } => "not_sealed",
+            MissReason::LostAuction { .. } => "lost_auction",
+        }
+    }
+
+    /// Classify a stats lookup for a bundle that missed its block.
+    pub fn from_stats(stats: &Result<BundleStats, RelayError>) -> Self {
+        match stats {
+            Ok(s) => s.miss_reason(),
+            Err(e @ (RelayError::JsonRpc { .. } | RelayError::InvalidResponse(_))) => MissReason::StatsUnavailable(e.to_string()),
+            Err(e) => MissReason::Unreachable(e.to_string()),
+        }
+    }
+}
+
+/// Result of `flashbots_getUserStatsV2`: payments and simulated gas for the auth key.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserStats {
+    #[serde(default)]
+    pub is_high_priority: bool,
+    #[serde(default, deserialize_with = "de_u256")]
+    pub all_time_validator_payments: U256,
+    #[serde(default, deserialize_with = "de_u256")]
+    pub all_time_gas_simulated: U256,
+    #[serde(default, rename = "last7dValidatorPayments", deserialize_with = "de_u256")]
+    pub last_7d_validator_payments: U256,
+    #[serde(default, rename = "last7dGasSimulated", deserialize_with = "de_u256")]
+    pub last_7d_gas_simulated: U256,
+    #[serde(default, rename = "last1dValidatorPayments", deserialize_with = "de_u256")]
+    pub last_1d_validator_payments: U256,
+    #[serde(default, rename = "last1dGasSimulated", deserialize_with = "de_u256")]
+    pub last_1d_gas_simulated: U256,
+}
+
+/// Relays encode amounts as decimal strings, hex strings or bare numbers depending on the field.
+fn parse_u256_value(v: &serde_json::Value) -> Result<U256, String> {
+    match v {
+        serde_json::Value::Null => Ok(U256::zero()),
+        serde_json::Value::Number(n) => n.as_u64().map(U256::from).ok_or_else(|| format!("not an unsigned integer: {}", n)),
+        serde_json::Value::String(s) => {
+            if let Some(hex) = s.strip_prefix("0x") {
+                U256::from_str_radix(hex, 16).map_err(|e| e.to_string())
+            } else {
+                U256::from_dec_str(s).map_err(|e| e.to_string())
+            }
+        }
+        other => Err(format!("unexpected amount encoding: {}", other)),
+    }
+}
+
+fn de_u256<'de, D: Deserializer<'de>>(d: D) -> Result<U256, D::Error> {
+    let v = serde_json::Value::deserialize(d)?;
+    parse_u256_value(&v).map_err(serde::de::Error::custom)
+}
+
+fn de_u64<'de, D: Deserializer<'de>>(d: D) -> Result<u64, D::Error> {
+    let v = de_u256(d)?;
+    if v > U256::from(u64::MAX) {
+        return Err(serde::de::Error::custom("value does not fit in u64"));
+    }
+    Ok(v.as_u64())
+}
+
+#[derive(Deserialize)]
+struct JsonRpcErrorObject {
+    code: i64,
+    message: String,
+    #[serde(default)]
+    data: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct JsonRpcEnvelope {
+    #[serde(default)]
+    result: Option<serde_json::Value>,
+    #[serde(default)]
+    error: Option<JsonRpcErrorObject>,
+}
 
 /// Relay client that can submit bundles to a configured relay endpoint.
+/// JSON-RPC calls are signed with `X-Flashbots-Signature` when an auth key is set.
 pub struct RelayClient {
     client: Client,
     relay_url: Option<String>,
+    auth: Option<Arc<FlashbotsAuth>>,
 }
 
 impl RelayClient {
     pub async fn new() -> Result<Self> {
         let client = Client::builder().timeout(Duration::from_secs(10)).build()?;
         let relay_url = std::env::var("FLASHBOTS_RELAY_URL").ok();
-        Ok(Self { client, relay_url })
+        Ok(Self { client, relay_url, auth: None })
     }
 
     /// Create a client with an explicit relay URL (useful for tests to avoid
     /// modifying the global environment and causing test interference).
     pub fn with_url(relay_url: String) -> Result<Self> {
         let client = Client::builder().timeout(Duration::from_secs(10)).build()?;
-        Ok(Self { client, relay_url: Some(relay_url) })
+        Ok(Self { client, relay_url: Some(relay_url), auth: None })
+    }
+
+    /// Create a client for `relay_url` with a per-request timeout.
+    pub fn with_url_and_timeout(relay_url: String, timeout: Duration) -> Result<Self> {
+        let client = Client::builder().timeout(timeout).build()?;
+        Ok(Self { client, relay_url: Some(relay_url), auth: None })
+    }
+
+    /// Configured relay URL, if any.
+    pub fn relay_url(&self) -> Option<&str> {
+        self.relay_url.as_deref()
     }
 
     /// Create a client with no relay configured (useful for testing fallback behaviour).
     pub fn without_relay() -> Result<Self> {
         let client = Client::builder().timeout(Duration::from_secs(10)).build()?;
-        Ok(Self { client, relay_url: None })
+        Ok(Self { client, relay_url: None, auth: None })
+    }
+
+    /// Sign every JSON-RPC request with `auth` (required by public relays).
+    pub fn with_auth(mut self, auth: Arc<FlashbotsAuth>) -> Self {
+        self.auth = Some(auth);
+        self
     }
 
     /// Submit a generic bundle body (legacy compatibility).
-    pub async fn submit_bundle(&self, bundle: &[u8]) -> Result<String> {
+    pub async fn submit_bundle(&self, bundle: &[u8]) -> Result<String, RelayError> {
         if let Some(url) = &self.relay_url {
             let body = base64::encode(bundle);
             let resp = self.client.post(url)
                 .json(&serde_json::json!({"bundle": body}))
-                .send().await.context("relay post failed")?;
-            let txt = resp.text().await.unwrap_or_default();
+                .send().await.map_err(RelayError::Transport)?;
+            let status = resp.status();
+            let txt = resp.text().await.map_err(RelayError::Transport)?;
+            if !status.is_success() {
+                return Err(RelayError::Http { status: status.as_u16(), body: txt });
+            }
             Ok(txt)
         } else {
             tracing::info!("No relay configured; bundle size {} bytes", bundle.len());
@@ -46,73 +495,234 @@ impl RelayClient {
     /// Submit a Flashbots-style bundle (array of signed raw tx hex strings).
     /// `signed_txs` is a slice of raw signed tx bytes.
     /// `block_number` is optional target block number; if None, relay decides.
-    pub async fn submit_flashbots_bundle(&self, signed_txs: &[Vec<u8>], block_number: Option<u64>) -> Result<serde_json::Value> {
-        let url = match &self.relay_url {
-            Some(u) => u.clone(),
-            None => return Err(anyhow::anyhow!("FLASHBOTS_RELAY_URL not configured")),
-        };
+    pub async fn submit_flashbots_bundle(&self, signed_txs: &[Vec<u8>], block_number: Option<u64>) -> Result<SendBundleResponse, RelayError> {
+        self.submit_bundle_with_options(signed_txs, block_number, RelayDialect::Flashbots, &BundleOptions::default()).await
+    }
 
-        let txs: Vec<String> = signed_txs.iter().map(|s| format!("0x{}", hex::encode(s))).collect();
-        let mut params = serde_json::Map::new();
-        params.insert("txs".to_string(), serde_json::Value::Array(txs.into_iter().map(serde_json::Value::String).collect()));
-        if let Some(bn) = block_number {
-            params.insert("blockNumber".to_string(), serde_json::Value::String(format!("0x{:x}", bn)));
+    /// Submit a bundle using the response conventions of `dialect`.
+    pub async fn submit_bundle_with_dialect(&self, signed_txs: &[Vec<u8>], block_number: Option<u64>, dialect: RelayDialect) -> Result<SendBundleResponse, RelayError> {
+        self.submit_bundle_with_options(signed_txs, block_number, dialect, &BundleOptions::default()).await
+    }
+
+    /// Submit a bundle with timestamp bounds and allowed reverts.
+    pub async fn submit_bundle_with_options(&self, signed_txs: &[Vec<u8>], block_number: Option<u64>, dialect: RelayDialect, options: &BundleOptions) -> Result<SendBundleResponse, RelayError> {
+        let mut params = bundle_params(signed_txs, block_number);
+        if let Some(ts) = options.min_timestamp {
+            params.insert("minTimestamp".to_string(), serde_json::Value::from(ts));
         }
+        if let Some(ts) = options.max_timestamp {
+            params.insert("maxTimestamp".to_string(), serde_json::Value::from(ts));
+        }
+        if !options.reverting_tx_hashes.is_empty() {
+            let hashes = options.reverting_tx_hashes.iter().map(|h| serde_json::Value::String(format!("{:?}", h))).collect();
+            params.insert("revertingTxHashes".to_string(), serde_json::Value::Array(hashes));
+        }
+        if let Some(uuid) = &options.replacement_uuid {
+            params.insert("replacementUuid".to_string(), serde_json::Value::String(uuid.clone()));
+        }
+        match dialect {
+            RelayDialect::Flashbots => self.rpc_call("eth_sendBundle", serde_json::Value::Object(params)).await,
+            RelayDialect::Builder => {
+                let result: serde_json::Value = self.rpc_call_allow_null("eth_sendBundle", serde_json::Value::Object(params)).await?;
+                let bundle_hash = result.get("bundleHash").and_then(|v| v.as_str())
+                    .or_else(|| result.as_str())
+                    .and_then(|h| h.parse::<H256>().ok())
+                    .unwrap_or_else(|| compute_bundle_hash(signed_txs));
+                Ok(SendBundleResponse { bundle_hash })
+            }
+        }
+    }
 
-        let req = serde_json::json!({
-            "jsonrpc": "2.0",
-            "id": 1,
-            "method": "eth_sendBundle",
-            "params": [params]
+    /// Cancel bundles submitted with `replacement_uuid` via `eth_cancelBundle`.
+    /// Relays answer with `null` or an empty result, so only errors are reported.
+    pub async fn cancel_bundle(&self, replacement_uuid: &str) -> Result<(), RelayError> {
+        let params = serde_json::json!({ "replacementUuid": replacement_uuid });
+        self.rpc_call_allow_null("eth_cancelBundle", params).await.map(|_| ())
+    }
+
+    /// Query `flashbots_getBundleStatsV2` for a bundle submitted for `block_number`.
+    pub async fn get_bundle_stats(&self, bundle_hash: H256, block_number: u64) -> Result<BundleStats, RelayError> {
+        let params = serde_json::json!({
+            "bundleHash": format!("{:?}", bundle_hash),
+            "blockNumber": format!("0x{:x}", block_number),
         });
+        self.rpc_call("flashbots_getBundleStatsV2", params).await
+    }
 
-        let resp = self.client.post(&url)
-            .json(&req)
-            .send().await.context("flashbots post failed")?;
-        let v = resp.json::<serde_json::Value>().await.context("invalid json response from relay")?;
-        Ok(v)
+    /// Query `flashbots_getUserStatsV2` for the auth key as of `block_number`.
+    pub async fn get_user_stats(&self, block_number: u64) -> Result<UserStats, RelayError> {
+        let params = serde_json::json!({ "blockNumber": format!("0x{:x}", block_number) });
+        self.rpc_call("flashbots_getUserStatsV2", params).await
     }
 
-    /// Call the relay's simulate endpoint to get relay-side simulation semantics.
-    pub async fn simulate_flashbots_bundle(&self, signed_txs: &[Vec<u8>], block_number: Option<u64>) -> Result<serde_json::Value> {
-        let url = match &self.relay_url {
-            Some(u) => u.clone(),
-            None => return Err(anyhow::anyhow!("FLASHBOTS_RELAY_URL not configured")),
-        };
-        let txs: Vec<String> = signed_txs.iter().map(|s| format!("0x{}", hex::encode(s))).collect();
+    /// Send a signed transaction via `eth_sendPrivateTransaction`. The relay drops it
+    /// after `max_block_number` (its default is 25 blocks); `fast` shares it with
+    /// every registered builder instead of Flashbots only.
+    pub async fn send_private_transaction(&self, raw_tx: &[u8], max_block_number: Option<u64>, fast: bool) -> Result<H256, RelayError> {
         let mut params = serde_json::Map::new();
-        params.insert("txs".to_string(), serde_json::Value::Array(txs.into_iter().map(serde_json::Value::String).collect()));
-        if let Some(bn) = block_number {
-            params.insert("blockNumber".to_string(), serde_json::Value::String(format!("0x{:x}", bn)));
+        params.insert("tx".to_string(), serde_json::Value::String(format!("0x{}", hex::encode(raw_tx))));
+        if let Some(bn) = max_block_number {
+            params.insert("maxBlockNumber".to_string(), serde_json::Value::String(format!("0x{:x}", bn)));
         }
+        params.insert("preferences".to_string(), serde_json::json!({ "fast": fast }));
+        self.rpc_call("eth_sendPrivateTransaction", serde_json::Value::Object(params)).await
+    }
+
+    /// Submit a MEV-Share bundle via `mev_sendBundle`.
+    pub async fn send_mev_share_bundle(&self, bundle: &crate::mevshare::MevShareBundle) -> Result<SendBundleResponse, RelayError> {
+        let params = serde_json::to_value(bundle).map_err(|e| RelayError::InvalidResponse(e.to_string()))?;
+        self.rpc_call("mev_sendBundle", params).await
+    }
+
+    /// Simulate a bundle for inclusion in `block_number` via `eth_callBundle` against the
+    /// latest state. Use `call_bundle` to pin the state block, timestamp or base fee.
+    pub async fn simulate_flashbots_bundle(&self, signed_txs: &[Vec<u8>], block_number: u64) -> Result<CallBundleResponse, RelayError> {
+        self.call_bundle(signed_txs, &CallBundleParams::for_block(block_number)).await
+    }
+
+    /// Simulate a bundle on the relay via `eth_callBundle` with explicit parameters.
+    pub async fn call_bundle(&self, signed_txs: &[Vec<u8>], call: &CallBundleParams) -> Result<CallBundleResponse, RelayError> {
+        let mut params = bundle_params(signed_txs, Some(call.block_number));
+        let state_block = match call.state_block_number {
+            Some(n) => format!("0x{:x}", n),
+            None => "latest".to_string(),
+        };
+        params.insert("stateBlockNumber".to_string(), serde_json::Value::String(state_block));
+        if let Some(ts) = call.timestamp {
+            params.insert("timestamp".to_string(), serde_json::Value::from(ts));
+        }
+        if let Some(bf) = call.base_fee {
+            params.insert("baseFee".to_string(), serde_json::Value::String(format!("0x{:x}", bf)));
+        }
+        self.rpc_call("eth_callBundle", serde_json::Value::Object(params)).await
+    }
+
+    /// POST a JSON-RPC request to the relay and decode its `result`.
+    async fn rpc_call<T: DeserializeOwned>(&self, method: &str, params: serde_json::Value) -> Result<T, RelayError> {
+        let (status, body) = self.post_rpc(method, params).await?;
+        decode_rpc_response(status, &body)
+    }
+
+    /// Like `rpc_call`, but a missing or `null` result is returned as `Value::Null`.
+    async fn rpc_call_allow_null(&self, method: &str, params: serde_json::Value) -> Result<serde_json::Value, RelayError> {
+        let (status, body) = self.post_rpc(method, params).await?;
+        Ok(decode_rpc_result(status, &body)?.unwrap_or(serde_json::Value::Null))
+    }
+
+    /// POST a (signed, if configured) JSON-RPC request and return the HTTP status and body.
+    async fn post_rpc(&self, method: &str, params: serde_json::Value) -> Result<(u16, String), RelayError> {
+        let url = self.relay_url.as_ref().ok_or(RelayError::NotConfigured)?;
         let req = serde_json::json!({
             "jsonrpc": "2.0",
             "id": 1,
-            "method": "eth_simulateBundle",
+            "method": method,
             "params": [params]
         });
-        let resp = self.client.post(&url).json(&req).send().await.context("flashbots simulate failed")?;
-        let v = resp.json::<serde_json::Value>().await.context("invalid json response from relay simulate")?;
-        Ok(v)
+
+        let body = serde_json::to_vec(&req).map_err(|e| RelayError::InvalidResponse(e.to_string()))?;
+        let mut builder = self.client.post(url).header(reqwest::header::CONTENT_TYPE, "application/json");
+        if let Some(auth) = &self.auth {
+            let signature = auth.sign_body(&body).await.map_err(|e| RelayError::Auth(e.to_string()))?;
+            builder = builder.header(FLASHBOTS_SIGNATURE_HEADER, signature);
+        }
+        let resp = builder.body(body).send().await.map_err(RelayError::Transport)?;
+        let status = resp.status();
+        let body = resp.text().await.map_err(RelayError::Transport)?;
+        Ok((status.as_u16(), body))
+    }
+}
+
+fn bundle_params(signed_txs: &[Vec<u8>], block_number: Option<u64>) -> serde_json::Map<String, serde_json::Value> {
+    let txs: Vec<String> = signed_txs.iter().map(|s| format!("0x{}", hex::encode(s))).collect();
+    let mut params = serde_json::Map::new();
+    params.insert("txs".to_string(), serde_json::Value::Array(txs.into_iter().map(serde_json::Value::String).collect()));
+    if let Some(bn) = block_number {
+        params.insert("blockNumber".to_string(), serde_json::Value::String(format!("0x{:x}", bn)));
+    }
+    params
+}
+
+/// Map an HTTP status and body to a decoded JSON-RPC result or a `RelayError`.
+/// Relays often report JSON-RPC errors with a 4xx status, so the body is checked first.
+fn decode_rpc_response<T: DeserializeOwned>(status: u16, body: &str) -> Result<T, RelayError> {
+    let result = decode_rpc_result(status, body)?
+        .ok_or_else(|| RelayError::InvalidResponse(format!("missing result: {}", body)))?;
+    serde_json::from_value(result).map_err(|e| RelayError::InvalidResponse(format!("{}: {}", e, body)))
+}
+
+/// Extract the raw `result` of a JSON-RPC response; `None` when absent or `null`.
+fn decode_rpc_result(status: u16, body: &str) -> Result<Option<serde_json::Value>, RelayError> {
+    let envelope = serde_json::from_str::<JsonRpcEnvelope>(body);
+    if let Ok(JsonRpcEnvelope { error: Some(err), .. }) = envelope {
+        return Err(RelayError::JsonRpc { code: err.code, message: err.message, data: err.data });
     }
+    if !(200..300).contains(&status) {
+        return Err(RelayError::Http { status, body: body.to_string() });
+    }
+    let envelope = envelope.map_err(|e| RelayError::InvalidResponse(format!("{}: {}", e, body)))?;
+    Ok(envelope.result.filter(|v| !v.is_null()))
 }
 
 #[cfg(test)]
 mod simulate_tests {
     use super::*;
 
+    #[tokio::test]
+    async fn call_bundle_sends_standard_params() {
+        let server = httpmock::MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/")
+                .body_contains(r#""method":"eth_callBundle""#)
+                .body_contains(r#""blockNumber":"0x65""#)
+                .body_contains(r#""stateBlockNumber":"0x64""#)
+                .body_contains(r#""timestamp":1700000000"#)
+                .body_contains(r#""baseFee":"0x3b9aca00""#);
+            then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"result":{"results":[],"stateBlockNumber":100}}"#);
+        });
+
+        let rc = RelayClient::with_url(server.url("/")).unwrap();
+        let params = CallBundleParams {
+            block_number: 101,
+            state_block_number: Some(100),
+            timestamp: Some(1_700_000_000),
+            base_fee: Some(U256::from(1_000_000_000u64)),
+        };
+        let v = rc.call_bundle(&[vec![0x01]], &params).await.unwrap();
+        assert_eq!(v.state_block_number, 100);
+        m.assert();
+    }
+
     #[tokio::test]
     async fn simulate_flashbots_bundle_posts_to_relay() {
         let server = httpmock::MockServer::start();
         let m = server.mock(|when, then| {
-            when.method(httpmock::Method::POST).path("/");
-            then.status(200).body(r#"{"result":"sim_ok"}"#);
+            when.method(httpmock::Method::POST).path("/")
+                .body_contains("eth_callBundle")
+                .body_contains(r#""blockNumber":"0x3039""#)
+                .body_contains(r#""stateBlockNumber":"latest""#);
+            then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"result":{
+                "bundleGasPrice":"476190476193","bundleHash":"0x73b1e258c7a42fd0230b2fd05529c5d4b6fcb66c227783f8bece8aeacdd1db2e",
+                "coinbaseDiff":"20000000000126000","ethSentToCoinbase":"20000000000000000","gasFees":"126000",
+                "results":[
+                    {"coinbaseDiff":"10000000000063000","ethSentToCoinbase":"10000000000000000","fromAddress":"0x02a727155aef8609c9f7f2179b2a1f560b39f5a0",
+                     "gasFees":"63000","gasPrice":"476190476193","gasUsed":21000,"toAddress":"0x73625f59cadc5009cb458b751b3e7b6b48c06f2c",
+                     "txHash":"0x669b4704a7d993a946cdd6e2f95233f308ce0c4649d2e04944e8299efcaa098a","value":"0x"},
+                    {"coinbaseDiff":"0","fromAddress":"0x02a727155aef8609c9f7f2179b2a1f560b39f5a0","gasUsed":30000,
+                     "txHash":"0xa839ee83465657cac01adc1d50d96c1b586ed498120a84a64749c0034b4f19fa","error":"execution reverted","revert":"too late"}
+                ],
+                "stateBlockNumber":5221585,"totalGasUsed":51000}}"#);
         });
 
         let rc = RelayClient::with_url(server.url("/")).unwrap();
         let signed = vec![vec![0x01,0x02,0x03]];
-        let v = rc.simulate_flashbots_bundle(&signed, Some(12345)).await.unwrap();
-        assert_eq!(v.get("result").unwrap().as_str().unwrap(), "sim_ok");
+        let v = rc.simulate_flashbots_bundle(&signed, 12345).await.unwrap();
+        assert_eq!(v.coinbase_diff, U256::from(20_000_000_000_126_000u64));
+        assert_eq!(v.gas_fees, U256::from(126_000u64));
+        assert_eq!(v.state_block_number, 5_221_585);
+        assert_eq!(v.results.len(), 2);
+        assert_eq!(v.results[0].gas_used, 21_000);
+        assert!(!v.results[0].is_revert());
+        assert_eq!(v.first_revert().unwrap().revert.as_deref(), Some("too late"));
         m.assert();
     }
 }
@@ -120,6 +730,8 @@ mod simulate_tests {
 mod tests {
     use super::*;
 
+    const BUNDLE_HASH: &str = "0x2228f5d8954ce31dc1601a8ba264dbd401bf1428388ce88238932815c5d6f23f";
+
     #[tokio::test]
     async fn relay_submit_bundle_falls_back_when_no_relay() {
         let rc = RelayClient::without_relay().unwrap();
@@ -127,19 +739,173 @@ mod tests {
         assert_eq!(res, "stub");
     }
 
+    #[tokio::test]
+    async fn submit_bundle_reports_http_failure() {
+        let server = httpmock::MockServer::start();
+        server.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/");
+            then.status(503).body("unavailable");
+        });
+        let rc = RelayClient::with_url(server.url("/")).unwrap();
+        match rc.submit_bundle(&[1u8, 2, 3]).await {
+            Err(e @ RelayError::Http { status: 503, .. }) => assert!(e.is_retryable()),
+            other => panic!("expected HTTP error, got {:?}", other),
+        }
+    }
+
     #[tokio::test]
     async fn submit_flashbots_bundle_posts_to_relay() {
         // start a mock HTTP server
         let server = httpmock::MockServer::start();
         let m = server.mock(|when, then| {
-            when.method(httpmock::Method::POST).path("/");
-            then.status(200).body(r#"{"result":"ok"}"#);
+            when.method(httpmock::Method::POST).path("/").body_contains("eth_sendBundle");
+            then.status(200).body(format!(r#"{{"jsonrpc":"2.0","id":1,"result":{{"bundleHash":"{}"}}}}"#, BUNDLE_HASH));
         });
 
         let rc = RelayClient::with_url(server.url("/")).unwrap();
         let signed = vec![vec![0x01,0x02,0x03]];
         let v = rc.submit_flashbots_bundle(&signed, Some(12345)).await.unwrap();
-        assert_eq!(v.get("result").unwrap().as_str().unwrap(), "ok");
+        assert_eq!(v.bundle_hash, BUNDLE_HASH.parse::<H256>().unwrap());
+        m.assert();
+    }
+
+    #[tokio::test]
+    async fn send_bundle_for_blocks_sends_options_per_block() {
+        let server = httpmock::MockServer::start();
+        let reverting: H256 = BUNDLE_HASH.parse().unwrap();
+        let m = server.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/")
+                .body_contains(r#""minTimestamp":1000"#)
+                .body_contains(r#""maxTimestamp":1100"#)
+                .body_contains(format!(r#""revertingTxHashes":["{:?}"]"#, reverting));
+            then.status(200).body(format!(r#"{{"jsonrpc":"2.0","id":1,"result":{{"bundleHash":"{}"}}}}"#, BUNDLE_HASH));
+        });
+
+        let rc = RelayClient::with_url(server.url("/")).unwrap();
+        let options = BundleOptions { min_timestamp: Some(1000), max_timestamp: Some(1100), reverting_tx_hashes: vec![reverting], ..Default::default() };
+        let results = rc.send_bundle_for_blocks(&[vec![0x01]], 10, 3, &options).await;
+        assert_eq!(results.iter().map(|(b, _)| *b).collect::<Vec<_>>(), vec![10, 11, 12]);
+        assert!(results.iter().all(|(_, r)| r.is_ok()));
+        m.assert_hits(3);
+    }
+
+    #[tokio::test]
+    async fn replacement_uuid_is_sent_and_cancelled() {
+        let server = httpmock::MockServer::start();
+        let uuid = new_replacement_uuid();
+        let send = server.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/")
+                .body_contains("eth_sendBundle")
+                .body_contains(format!(r#""replacementUuid":"{}""#, uuid));
+            then.status(200).body(format!(r#"{{"jsonrpc":"2.0","id":1,"result":{{"bundleHash":"{}"}}}}"#, BUNDLE_HASH));
+        });
+        let cancel = server.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/")
+                .body_contains("eth_cancelBundle")
+                .body_contains(format!(r#""replacementUuid":"{}""#, uuid));
+            then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"result":null}"#);
+        });
+
+        let rc = RelayClient::with_url(server.url("/")).unwrap();
+        let options = BundleOptions { replacement_uuid: Some(uuid.clone()), ..Default::default() };
+        rc.send_bundle_with_options(&[vec![0x01]], Some(5), &options).await.unwrap();
+        rc.cancel_bundle(&uuid).await.unwrap();
+        send.assert();
+        cancel.assert();
+        assert_eq!(uuid.len(), 36);
+        assert_eq!(&uuid[14..15], "4");
+    }
+
+    #[tokio::test]
+    async fn bundle_stats_decode_and_explain_misses() {
+        let server = httpmock::MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/")
+                .body_contains("flashbots_getBundleStatsV2")
+                .body_contains(r#""blockNumber":"0xa""#);
+            then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"result":{
+                "isHighPriority":true,"isSimulated":true,
+                "simulatedAt":"2024-01-01T00:00:01Z","receivedAt":"2024-01-01T00:00:00Z",
+                "consideredByBuildersAt":[{"pubkey":"0xaa","timestamp":"2024-01-01T00:00:02Z"}],
+                "sealedByBuildersAt":[{"pubkey":"0xbb","timestamp":"2024-01-01T00:00:03Z"}]}}"#);
+        });
+        let users = server.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/").body_contains("flashbots_getUserStatsV2");
+            then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"result":{"isHighPriority":false,
+                "allTimeValidatorPayments":"1280749594841588639","allTimeGasSimulated":"30049470846",
+                "last7dValidatorPayments":"1280749594841588639","last7dGasSimulated":"30049470846",
+                "last1dValidatorPayments":"142305510537954293","last1dGasSimulated":"2731770076"}}"#);
+        });
+
+        let rc = RelayClient::with_url(server.url("/")).unwrap();
+        let stats = rc.get_bundle_stats(BUNDLE_HASH.parse().unwrap(), 10).await.unwrap();
+        assert_eq!(stats.miss_reason(), MissReason::LostAuction { sealed_by: vec!["0xbb".to_string()] });
+        let user = rc.get_user_stats(10).await.unwrap();
+        assert_eq!(user.last_1d_gas_simulated, U256::from(2_731_770_076u64));
+        m.assert();
+        users.assert();
+
+        let simulated_only = BundleStats { received_at: Some("t".into()), is_simulated: true, ..Default::default() };
+        assert_eq!(simulated_only.miss_reason(), MissReason::NotConsidered);
+        assert_eq!(MissReason::from_stats(&Err(RelayError::Timeout(Duration::from_secs(1)))), MissReason::Unreachable("relay did not answer within 1s".into()));
+        let unknown = Err(RelayError::JsonRpc { code: -32000, message: "bundle not found".into(), data: None });
+        assert!(matches!(MissReason::from_stats(&unknown), MissReason::StatsUnavailable(_)));
+    }
+
+    #[tokio::test]
+    async fn submit_flashbots_bundle_maps_json_rpc_error() {
+        let server = httpmock::MockServer::start();
+        server.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/");
+            then.status(400).body(r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"bundle block number is in the past"}}"#);
+        });
+
+        let rc = RelayClient::with_url(server.url("/")).unwrap();
+        match rc.submit_flashbots_bundle(&[vec![0x01]], Some(1)).await {
+            Err(RelayError::JsonRpc { code, message, .. }) => {
+                assert_eq!(code, -32000);
+                assert!(message.contains("in the past"));
+            }
+            other => panic!("expected JSON-RPC error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn submit_flashbots_bundle_without_relay_is_not_configured() {
+        let rc = RelayClient::without_relay().unwrap();
+        assert!(matches!(rc.submit_flashbots_bundle(&[], None).await, Err(RelayError::NotConfigured)));
+    }
+
+    #[test]
+    fn decode_rejects_unexpected_result_shape() {
+        let r: Result<SendBundleResponse, _> = decode_rpc_response(200, r#"{"jsonrpc":"2.0","id":1,"result":"ok"}"#);
+        assert!(matches!(r, Err(RelayError::InvalidResponse(_))));
+    }
+
+    fn has_valid_flashbots_signature(req: &httpmock::prelude::HttpMockRequest) -> bool {
+        let header = req.headers.as_ref()
+            .and_then(|hs| hs.iter().find(|(k, _)| k.eq_ignore_ascii_case(FLASHBOTS_SIGNATURE_HEADER)))
+            .map(|(_, v)| v.clone());
+        match (header, req.body.as_ref()) {
+            (Some(h), Some(body)) => crate::auth::verify_signature_header(&h, body).is_ok(),
+            _ => false,
+        }
+    }
+
+    #[tokio::test]
+    async fn authenticated_requests_carry_flashbots_signature() {
+        let server = httpmock::MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/")
+                .header_exists(FLASHBOTS_SIGNATURE_HEADER)
+                .matches(has_valid_flashbots_signature);
+            then.status(200).body(format!(r#"{{"jsonrpc":"2.0","id":1,"result":{{"bundleHash":"{}"}}}}"#, BUNDLE_HASH));
+        });
+
+        let kms = crate::kms::MockKms::new("0123456789012345678901234567890123456789012345678901234567890123".to_string());
+        let auth = Arc::new(FlashbotsAuth::from_kms(Arc::new(kms)));
+        let rc = RelayClient::with_url(server.url("/")).unwrap().with_auth(auth);
+        rc.submit_flashbots_bundle(&[vec![0x01, 0x02]], Some(7)).await.unwrap();
         m.assert();
     }
 }
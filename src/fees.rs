@@ -0,0 +1,332 @@
+use anyhow::{Result, Context};
+use ethers_core::types::{BlockNumber, U256, transaction::eip2718::TypedTransaction};
+use ethers_providers::{Provider, Http, Middleware};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// EIP-1559 base fee max change denominator (12.5% per block).
+pub const BASE_FEE_MAX_CHANGE_DENOMINATOR: u64 = 8;
+/// EIP-1559 elasticity multiplier (gas target = gas limit / 2).
+pub const ELASTICITY_MULTIPLIER: u64 = 2;
+/// Tip percentiles requested from `eth_feeHistory`, one per `Urgency` level.
+pub const REWARD_PERCENTILES: [f64; 4] = [10.0, 50.0, 75.0, 95.0];
+
+/// How quickly a transaction needs to land. Higher urgency pays a higher tip
+/// percentile and leaves more headroom in the fee cap for base fee growth.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Urgency {
+    Low,
+    Normal,
+    High,
+    Immediate,
+}
+
+impl Urgency {
+    fn percentile_index(self) -> usize {
+        match self {
+            Urgency::Low => 0,
+            Urgency::Normal => 1,
+            Urgency::High => 2,
+            Urgency::Immediate => 3,
+        }
+    }
+
+    /// Number of consecutive full blocks the fee cap must survive.
+    pub fn headroom_blocks(self) -> u32 {
+        match self {
+            Urgency::Low => 1,
+            Urgency::Normal => 2,
+            Urgency::High => 4,
+            Urgency::Immediate => 6,
+        }
+    }
+
+    /// Urgency to use for the n-th gas bump (0-based): escalate quickly since a
+    /// bump means the previous bid already missed.
+    pub fn for_bump(bump_idx: usize) -> Self {
+        if bump_idx == 0 { Urgency::High } else { Urgency::Immediate }
+    }
+}
+
+/// Gas usage and base fee of a mined block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockSample {
+    pub number: u64,
+    pub base_fee: U256,
+    pub gas_used: U256,
+    pub gas_limit: U256,
+}
+
+/// Suggested EIP-1559 fee fields for a given urgency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeRecommendation {
+    pub predicted_base_fee: U256,
+    pub max_fee_per_gas: U256,
+    pub max_priority_fee_per_gas: U256,
+}
+
+impl FeeRecommendation {
+    /// Return a copy of `tx` with its fee fields replaced by this recommendation.
+    /// Legacy transactions get `max_fee_per_gas` as their gas price.
+    pub fn apply_to(&self, tx: &TypedTransaction) -> TypedTransaction {
+        match tx.clone() {
+            TypedTransaction::Eip1559(req) => TypedTransaction::Eip1559(
+                req.max_fee_per_gas(self.max_fee_per_gas).max_priority_fee_per_gas(self.max_priority_fee_per_gas),
+            ),
+            TypedTransaction::Legacy(req) => TypedTransaction::Legacy(req.gas_price(self.max_fee_per_gas)),
+            other => other,
+        }
+    }
+}
+
+/// Base fee of the block after `parent`, per the EIP-1559 update rule.
+pub fn next_base_fee(parent_base_fee: U256, parent_gas_used: U256, parent_gas_limit: U256) -> U256 {
+    let gas_target = parent_gas_limit / U256::from(ELASTICITY_MULTIPLIER);
+    if gas_target.is_zero() || parent_gas_used == gas_target {
+        return parent_base_fee;
+    }
+    let denominator = U256::from(BASE_FEE_MAX_CHANGE_DENOMINATOR);
+    if parent_gas_used > gas_target {
+        let delta = parent_base_fee.saturating_mul(parent_gas_used - gas_target) / gas_target / denominator;
+        parent_base_fee.saturating_add(delta.max(U256::one()))
+    } else {
+        let delta = parent_base_fee.saturating_mul(gas_target - parent_gas_used) / gas_target / denominator;
+        parent_base_fee.saturating_sub(delta)
+    }
+}
+
+/// Highest base fee reachable after `blocks` consecutive full blocks.
+pub fn max_base_fee_after(base_fee: U256, blocks: u32) -> U256 {
+    let mut fee = base_fee;
+    for _ in 0..blocks {
+        fee = fee.saturating_add(fee / U256::from(BASE_FEE_MAX_CHANGE_DENOMINATOR));
+    }
+    fee
+}
+
+/// Predicted base fee `blocks` blocks after a block with `base_fee`, assuming each
+/// block in between uses `utilization_bps` of its gas limit (5_000 = at target).
+pub fn base_fee_after(base_fee: U256, blocks: u32, utilization_bps: u32) -> U256 {
+    let gas_limit = U256::from(30_000_000u64);
+    let gas_used = gas_limit * U256::from(utilization_bps.min(10_000)) / U256::from(10_000u64);
+    let mut fee = base_fee;
+    for _ in 0..blocks {
+        fee = next_base_fee(fee, gas_used, gas_limit);
+    }
+    fee
+}
+
+#[derive(Default)]
+struct FeeState {
+    blocks: VecDeque<BlockSample>,
+    /// (block number, tip per `REWARD_PERCENTILES` entry)
+    rewards: VecDeque<(u64, Vec<U256>)>,
+}
+
+/// Tracks recent blocks and recommends EIP-1559 fees. Feed it either by calling
+/// `refresh` (RPC polling) or `observe_block`/`observe_rewards` from a head stream.
+/// Shareable across tasks behind an `Arc`.
+pub struct FeeOracle {
+    rpc: String,
+    window: usize,
+    min_priority_fee: U256,
+    state: Mutex<FeeState>,
+}
+
+impl FeeOracle {
+    pub fn new(rpc: String) -> Self {
+        Self::with_window(rpc, 20)
+    }
+
+    /// Create an oracle that keeps the last `window` blocks of history.
+    pub fn with_window(rpc: String, window: usize) -> Self {
+        Self {
+            rpc,
+            window: window.max(1),
+            min_priority_fee: U256::from(100_000_000u64), // 0.1 gwei floor
+            state: Mutex::new(FeeState::default()),
+        }
+    }
+
+    /// Never recommend a tip below `min` (wei).
+    pub fn with_min_priority_fee(mut self, min: U256) -> Self {
+        self.min_priority_fee = min;
+        self
+    }
+
+    /// Record a mined block. Older blocks beyond the window are dropped.
+    pub fn observe_block(&self, sample: BlockSample) {
+        let mut st = self.state.lock().unwrap();
+        if st.blocks.iter().any(|b| b.number == sample.number) {
+            return;
+        }
+        st.blocks.push_back(sample);
+        st.blocks.make_contiguous().sort_by_key(|b| b.number);
+        while st.blocks.len() > self.window {
+            st.blocks.pop_front();
+        }
+    }
+
+    /// Record the tip percentiles (ordered as `REWARD_PERCENTILES`) paid in a block.
+    pub fn observe_rewards(&self, number: u64, tips: Vec<U256>) {
+        let mut st = self.state.lock().unwrap();
+        if st.rewards.iter().any(|(n, _)| *n == number) {
+            return;
+        }
+        st.rewards.push_back((number, tips));
+        st.rewards.make_contiguous().sort_by_key(|(n, _)| *n);
+        while st.rewards.len() > self.window {
+            st.rewards.pop_front();
+        }
+    }
+
+    /// Poll the latest block and `eth_feeHistory` over the window.
+    pub async fn refresh(&self) -> Result<()> {
+        let provider = Provider::<Http>::try_from(self.rpc.as_str()).context("invalid rpc url")?;
+        let head = provider.get_block(BlockNumber::Latest).await.context("get latest block failed")?
+            .ok_or_else(|| anyhow::anyhow!("latest block not found"))?;
+        let number = head.number.ok_or_else(|| anyhow::anyhow!("latest block has no number"))?.as_u64();
+        let base_fee = head.base_fee_per_gas.ok_or_else(|| anyhow::anyhow!("chain does not report base fees (pre-London?)"))?;
+        self.observe_block(BlockSample { number, base_fee, gas_used: head.gas_used, gas_limit: head.gas_limit });
+
+        let history = provider
+            .fee_history(U256::from(self.window as u64), BlockNumber::Number(number.into()), &REWARD_PERCENTILES)
+            .await
+            .context("eth_feeHistory failed")?;
+        let oldest = history.oldest_block.as_u64();
+        for (i, tips) in history.reward.into_iter().enumerate() {
+            if tips.len() == REWARD_PERCENTILES.len() {
+                self.observe_rewards(oldest + i as u64, tips);
+            }
+        }
+        Ok(())
+    }
+
+    /// Most recent block observed, if any.
+    pub fn latest_block(&self) -> Option<BlockSample> {
+        self.state.lock().unwrap().blocks.back().copied()
+    }
+
+    /// Predicted base fee of the next block, from the latest observed block.
+    pub fn predict_next_base_fee(&self) -> Option<U256> {
+        self.latest_block().map(|b| next_base_fee(b.base_fee, b.gas_used, b.gas_limit))
+    }
+
+    /// Median tip paid at the urgency's percentile over the window, floored at
+    /// `min_priority_fee`. Empty blocks (all-zero tips) are ignored.
+    pub fn suggest_priority_fee(&self, urgency: Urgency) -> U256 {
+        let st = self.state.lock().unwrap();
+        let idx = urgency.percentile_index();
+        let mut tips: Vec<U256> = st.rewards.iter()
+            .filter(|(_, t)| t.iter().any(|v| !v.is_zero()))
+            .filter_map(|(_, t)| t.get(idx).copied())
+            .collect();
+        if tips.is_empty() {
+            return self.min_priority_fee;
+        }
+        tips.sort();
+        tips[tips.len() / 2].max(self.min_priority_fee)
+    }
+
+    /// Recommend fee cap and tip for `urgency`. Fails if no block has been observed.
+    pub fn recommend(&self, urgency: Urgency) -> Result<FeeRecommendation> {
+        let predicted_base_fee = self.predict_next_base_fee()
+            .ok_or_else(|| anyhow::anyhow!("fee oracle has no block history; call refresh first"))?;
+        let tip = self.suggest_priority_fee(urgency);
+        let max_fee = max_base_fee_after(predicted_base_fee, urgency.headroom_blocks()).saturating_add(tip);
+        Ok(FeeRecommendation {
+            predicted_base_fee,
+            max_fee_per_gas: max_fee,
+            max_priority_fee_per_gas: tip,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gwei(v: u64) -> U256 {
+        U256::from(v) * U256::exp10(9)
+    }
+
+    #[test]
+    fn next_base_fee_follows_eip1559() {
+        let limit = U256::from(30_000_000u64);
+        // at target: unchanged
+        assert_eq!(next_base_fee(gwei(10), U256::from(15_000_000u64), limit), gwei(10));
+        // full block: +12.5%
+        assert_eq!(next_base_fee(gwei(8), limit, limit), gwei(9));
+        // empty block: -12.5%
+        assert_eq!(next_base_fee(gwei(8), U256::zero(), limit), gwei(7));
+        // tiny base fee still increases by at least 1 wei
+        assert_eq!(next_base_fee(U256::from(1u64), U256::from(15_000_001u64), limit), U256::from(2u64));
+
+        assert_eq!(base_fee_after(gwei(8), 2, 10_000), max_base_fee_after(gwei(8), 2));
+        assert_eq!(base_fee_after(gwei(8), 3, 5_000), gwei(8));
+        assert_eq!(base_fee_after(gwei(8), 1, 0), gwei(7));
+    }
+
+    #[test]
+    fn recommend_scales_with_urgency() {
+        let oracle = FeeOracle::new("http://127.0.0.1:8545".to_string()).with_min_priority_fee(U256::from(1u64));
+        assert!(oracle.recommend(Urgency::Normal).is_err());
+
+        oracle.observe_block(BlockSample { number: 10, base_fee: gwei(8), gas_used: U256::from(30_000_000u64), gas_limit: U256::from(30_000_000u64) });
+        oracle.observe_rewards(9, vec![gwei(1), gwei(2), gwei(3), gwei(4)]);
+        oracle.observe_rewards(10, vec![gwei(1), gwei(2), gwei(5), gwei(6)]);
+        oracle.observe_rewards(11, vec![U256::zero(); 4]);
+
+        let low = oracle.recommend(Urgency::Low).unwrap();
+        let high = oracle.recommend(Urgency::Immediate).unwrap();
+        assert_eq!(low.predicted_base_fee, gwei(9));
+        assert_eq!(low.max_priority_fee_per_gas, gwei(1));
+        assert_eq!(high.max_priority_fee_per_gas, gwei(6));
+        assert!(high.max_fee_per_gas > low.max_fee_per_gas);
+        assert_eq!(low.max_fee_per_gas, max_base_fee_after(gwei(9), 1) + gwei(1));
+    }
+
+    #[test]
+    fn observe_block_keeps_window() {
+        let oracle = FeeOracle::with_window("http://127.0.0.1:8545".to_string(), 2);
+        for n in 0..5u64 {
+            oracle.observe_block(BlockSample { number: n, base_fee: gwei(n + 1), gas_used: U256::zero(), gas_limit: U256::from(2u64) });
+        }
+        assert_eq!(oracle.latest_block().unwrap().number, 4);
+        assert_eq!(oracle.state.lock().unwrap().blocks.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn refresh_reads_head_and_fee_history() {
+        let server = httpmock::MockServer::start();
+        let block = server.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/").body_contains("eth_getBlockByNumber");
+            then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"result":{
+                "hash":"0x0000000000000000000000000000000000000000000000000000000000000001",
+                "parentHash":"0x0000000000000000000000000000000000000000000000000000000000000000",
+                "sha3Uncles":"0x0000000000000000000000000000000000000000000000000000000000000000",
+                "miner":"0x0000000000000000000000000000000000000000",
+                "stateRoot":"0x0000000000000000000000000000000000000000000000000000000000000000",
+                "transactionsRoot":"0x0000000000000000000000000000000000000000000000000000000000000000",
+                "receiptsRoot":"0x0000000000000000000000000000000000000000000000000000000000000000",
+                "number":"0x64","gasUsed":"0x1c9c380","gasLimit":"0x1c9c380","extraData":"0x",
+                "logsBloom":null,"timestamp":"0x1","difficulty":"0x0","totalDifficulty":"0x0",
+                "uncles":[],"transactions":[],"size":"0x1","baseFeePerGas":"0x1dcd65000"}}"#);
+        });
+        let history = server.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/").body_contains("eth_feeHistory");
+            then.status(200).body(r#"{"jsonrpc":"2.0","id":2,"result":{
+                "oldestBlock":"0x63","baseFeePerGas":["0x1","0x1","0x1"],"gasUsedRatio":[0.5,1.0],
+                "reward":[["0x1","0x2","0x3","0x4"],["0x5","0x6","0x7","0x8"]]}}"#);
+        });
+
+        let oracle = FeeOracle::new(server.url("/")).with_min_priority_fee(U256::zero());
+        oracle.refresh().await.unwrap();
+        block.assert();
+        history.assert();
+
+        // full block at 8 gwei -> 9 gwei
+        assert_eq!(oracle.predict_next_base_fee(), Some(gwei(9)));
+        assert_eq!(oracle.suggest_priority_fee(Urgency::Immediate), U256::from(8u64));
+    }
+}
@@ -0,0 +1,504 @@
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use anyhow::{anyhow, Context, Result};
+use ethers_core::types::{Address, BlockId, BlockNumber, Filter, H256, U256 as EU256};
+use ethers_providers::{Http, Middleware, Provider};
+use revm::db::CacheDB;
+use revm::primitives::{AccountInfo, Address as RAddress, Bytecode, Bytes as RBytes, B256, KECCAK_EMPTY, U256};
+use revm::DatabaseRef;
+use crate::revm_backend::{to_raddress, to_ru256};
+
+/// Blocks to replay diffs for when advancing; larger gaps drop the whole cache instead.
+pub const MAX_DIFF_BLOCKS: u64 = 16;
+
+/// Accounts and storage slots changed by one or more blocks.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateDiff {
+    /// Balance, nonce or code may have changed
+    pub accounts: HashSet<Address>,
+    /// Individual storage slots that may have changed
+    pub slots: HashSet<(Address, H256)>,
+    /// Contracts whose storage changed in unknown slots (log-based diffs)
+    pub storage: HashSet<Address>,
+}
+
+impl StateDiff {
+    /// Collect touched accounts and slots from a `debug_traceBlockByNumber` result using
+    /// `prestateTracer` in diff mode: `[{ "result": { "pre": {..}, "post": {..} } }, ..]`.
+    pub fn from_prestate_trace(trace: &serde_json::Value) -> Result<Self> {
+        let txs = trace.as_array().ok_or_else(|| anyhow!("prestate trace is not an array"))?;
+        let mut diff = StateDiff::default();
+        for tx in txs {
+            let result = tx.get("result").unwrap_or(tx);
+            for side in ["pre", "post"] {
+                let Some(accounts) = result.get(side).and_then(|v| v.as_object()) else { continue };
+                for (addr, state) in accounts {
+                    let addr: Address = addr.parse().with_context(|| format!("bad address {}", addr))?;
+                    diff.accounts.insert(addr);
+                    if let Some(storage) = state.get("storage").and_then(|s| s.as_object()) {
+                        for slot in storage.keys() {
+                            let slot: H256 = slot.parse().with_context(|| format!("bad slot {}", slot))?;
+                            diff.slots.insert((addr, slot));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(diff)
+    }
+
+    pub fn extend(&mut self, other: StateDiff) {
+        self.accounts.extend(other.accounts);
+        self.slots.extend(other.slots);
+        self.storage.extend(other.storage);
+    }
+}
+
+#[derive(Default)]
+struct Cache {
+    accounts: HashMap<RAddress, AccountInfo>,
+    storage: HashMap<RAddress, HashMap<U256, U256>>,
+    contracts: HashMap<B256, Bytecode>,
+    block_hashes: HashMap<u64, B256>,
+}
+
+struct Inner {
+    provider: Provider<Http>,
+    /// Separate client for `rt`: pooled connections are bound to the runtime that opened them
+    fetch_provider: Provider<Http>,
+    block: RwLock<u64>,
+    /// Hash of the pinned block, recorded when re-pinned; `None` until the first `advance_to`
+    pinned_hash: RwLock<Option<H256>>,
+    cache: RwLock<Cache>,
+    rt: FetchRuntime,
+}
+
+/// Read-only view of chain state at a pinned block. Accounts, code and storage are
+/// fetched from RPC on first access and cached; clones share the cache.
+#[derive(Clone)]
+pub struct ForkDb {
+    inner: Arc<Inner>,
+}
+
+impl ForkDb {
+    pub fn new(rpc_url: &str, block: u64) -> Result<Self> {
+        let provider = Provider::<Http>::try_from(rpc_url).context("invalid rpc url")?;
+        let fetch_provider = Provider::<Http>::try_from(rpc_url).context("invalid rpc url")?;
+        let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().context("fork db runtime")?;
+        Ok(Self {
+            inner: Arc::new(Inner {
+                provider,
+                fetch_provider,
+                block: RwLock::new(block),
+                pinned_hash: RwLock::new(None),
+                cache: RwLock::new(Cache::default()),
+                rt: FetchRuntime(Some(rt)),
+            }),
+        })
+    }
+
+    pub fn pinned_block(&self) -> u64 {
+        *self.inner.block.read().unwrap()
+    }
+
+    /// Number of cached accounts and storage slots.
+    pub fn cached(&self) -> (usize, usize) {
+        let cache = self.inner.cache.read().unwrap();
+        (cache.accounts.len(), cache.storage.values().map(|s| s.len()).sum())
+    }
+
+    /// Drop cached entries touched by `diff`. Code is keyed by hash and stays valid.
+    pub fn invalidate(&self, diff: &StateDiff) {
+        let mut cache = self.inner.cache.write().unwrap();
+        for addr in &diff.accounts {
+            cache.accounts.remove(&to_raddress(*addr));
+        }
+        for addr in &diff.storage {
+            cache.storage.remove(&to_raddress(*addr));
+        }
+        for (addr, slot) in &diff.slots {
+            if let Some(slots) = cache.storage.get_mut(&to_raddress(*addr)) {
+                slots.remove(&U256::from_be_bytes(slot.0));
+            }
+        }
+    }
+
+    /// State touched by `block`, from a prestate trace or, if the node has no debug API,
+    /// from the block's logs, senders, recipients and coinbase.
+    pub async fn fetch_state_diff(&self, block: u64) -> Result<StateDiff> {
+        let params = serde_json::json!([format!("0x{:x}", block), {"tracer": "prestateTracer", "tracerConfig": {"diffMode": true}}]);
+        match self.inner.provider.request::<_, serde_json::Value>("debug_traceBlockByNumber", params).await {
+            Ok(trace) => StateDiff::from_prestate_trace(&trace),
+            Err(e) => {
+                tracing::debug!("prestate trace for block {} unavailable ({}), using logs", block, e);
+                self.fetch_log_diff(block).await
+            }
+        }
+    }
+
+    /// Log-based fallback. Contracts reached by internal calls that emit no log are
+    /// missed, so after such a block their cached storage can be stale until the next
+    /// full cache drop.
+    async fn fetch_log_diff(&self, block: u64) -> Result<StateDiff> {
+        let provider = &self.inner.provider;
+        let logs = provider.get_logs(&Filter::new().from_block(block).to_block(block)).await.context("get_logs")?;
+        let full = provider.get_block_with_txs(block).await.context("get_block")?.ok_or_else(|| anyhow!("block {} not found", block))?;
+        let mut diff = StateDiff::default();
+        diff.storage.extend(logs.iter().map(|l| l.address));
+        diff.accounts.extend(full.author);
+        for tx in &full.transactions {
+            diff.accounts.insert(tx.from);
+            if let Some(to) = tx.to {
+                // storage of a called contract may change without a log
+                diff.accounts.insert(to);
+                diff.storage.insert(to);
+            }
+        }
+        Ok(diff)
+    }
+
+    /// Re-pin to `block`, invalidating only what the blocks in between touched.
+    /// Falls back to clearing the cache when the gap exceeds `MAX_DIFF_BLOCKS`, or when
+    /// the pinned block is no longer canonical (a reorg), whatever `block` is.
+    pub async fn advance_to(&self, block: u64) -> Result<StateDiff> {
+        let from = self.pinned_block();
+        let known = *self.inner.pinned_hash.read().unwrap();
+        if let Some(known) = known
+            && self.canonical_hash(from).await? != Some(known)
+        {
+            tracing::warn!(from, block, "pinned block was reorged out; dropping the fork cache");
+            return self.repin_cleared(block).await;
+        }
+        if block <= from {
+            return Ok(StateDiff::default());
+        }
+        if block - from > MAX_DIFF_BLOCKS {
+            return self.repin_cleared(block).await;
+        }
+        let mut diff = StateDiff::default();
+        for n in from + 1..=block {
+            diff.extend(self.fetch_state_diff(n).await?);
+        }
+        let hash = self.canonical_hash(block).await?;
+        // invalidate and re-pin together so no fetch at the old block lands in between
+        let mut pinned = self.inner.block.write().unwrap();
+        self.invalidate(&diff);
+        *pinned = block;
+        *self.inner.pinned_hash.write().unwrap() = hash;
+        Ok(diff)
+    }
+
+    async fn repin_cleared(&self, block: u64) -> Result<StateDiff> {
+        let hash = self.canonical_hash(block).await?;
+        let mut pinned = self.inner.block.write().unwrap();
+        let mut cache = self.inner.cache.write().unwrap();
+        cache.accounts.clear();
+        cache.storage.clear();
+        cache.block_hashes.clear();
+        *pinned = block;
+        *self.inner.pinned_hash.write().unwrap() = hash;
+        Ok(StateDiff::default())
+    }
+
+    async fn canonical_hash(&self, block: u64) -> Result<Option<H256>> {
+        Ok(self.inner.provider.get_block(block).await.context("get_block")?.and_then(|b| b.hash))
+    }
+
+    fn fetch<F, T>(&self, f: impl FnOnce(Provider<Http>, BlockId) -> F) -> Result<(T, u64)>
+    where
+        F: Future<Output = Result<T>> + Send,
+        T: Send,
+    {
+        let pinned = self.pinned_block();
+        let fut = f(self.inner.fetch_provider.clone(), BlockId::Number(BlockNumber::Number(pinned.into())));
+        let value = self.inner.rt.block_on(fut)?;
+        Ok((value, pinned))
+    }
+
+    /// Insert into the cache unless the db was re-pinned while the value was being fetched.
+    fn cache_if_current(&self, fetched_at: u64, f: impl FnOnce(&mut Cache)) {
+        let pinned = self.inner.block.read().unwrap();
+        if *pinned == fetched_at {
+            f(&mut self.inner.cache.write().unwrap());
+        }
+    }
+}
+
+impl DatabaseRef for ForkDb {
+    type Error = anyhow::Error;
+
+    fn basic_ref(&self, address: RAddress) -> Result<Option<AccountInfo>> {
+        if let Some(info) = self.inner.cache.read().unwrap().accounts.get(&address) {
+            return Ok(Some(info.clone()));
+        }
+        let addr = Address::from(address.0 .0);
+        let ((balance, nonce, code), at) = self.fetch(|p, block| async move {
+            let (balance, nonce, code) = tokio::join!(
+                p.get_balance(addr, Some(block)),
+                p.get_transaction_count(addr, Some(block)),
+                p.get_code(addr, Some(block)),
+            );
+            Ok((balance?, nonce?, code?))
+        })?;
+        let code = Bytecode::new_raw(RBytes::from(code.to_vec()));
+        let code_hash = if code.is_empty() { KECCAK_EMPTY } else { code.hash_slow() };
+        let info = AccountInfo { balance: to_ru256(balance), nonce: nonce.as_u64(), code_hash, code: Some(code.clone()) };
+        self.cache_if_current(at, |cache| {
+            cache.contracts.entry(code_hash).or_insert(code);
+            cache.accounts.insert(address, info.clone());
+        });
+        Ok(Some(info))
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode> {
+        if code_hash == KECCAK_EMPTY {
+            return Ok(Bytecode::default());
+        }
+        self.inner.cache.read().unwrap().contracts.get(&code_hash).cloned().ok_or_else(|| anyhow!("code {} not loaded", code_hash))
+    }
+
+    fn storage_ref(&self, address: RAddress, index: U256) -> Result<U256> {
+        if let Some(value) = self.inner.cache.read().unwrap().storage.get(&address).and_then(|s| s.get(&index)) {
+            return Ok(*value);
+        }
+        let addr = Address::from(address.0 .0);
+        let slot = H256::from(index.to_be_bytes::<32>());
+        let (value, at) = self.fetch(|p, block| async move { Ok(p.get_storage_at(addr, slot, Some(block)).await?) })?;
+        let value = U256::from_be_bytes(value.0);
+        self.cache_if_current(at, |cache| {
+            cache.storage.entry(address).or_default().insert(index, value);
+        });
+        Ok(value)
+    }
+
+    fn block_hash_ref(&self, number: U256) -> Result<B256> {
+        let number: u64 = number.try_into().map_err(|_| anyhow!("block number out of range"))?;
+        if let Some(hash) = self.inner.cache.read().unwrap().block_hashes.get(&number) {
+            return Ok(*hash);
+        }
+        let (block, at) = self.fetch(|p, _| async move { Ok(p.get_block(number).await?) })?;
+        let hash = block.and_then(|b| b.hash).map(|h| B256::from(h.0)).unwrap_or_default();
+        self.cache_if_current(at, |cache| {
+            cache.block_hashes.insert(number, hash);
+        });
+        Ok(hash)
+    }
+}
+
+/// Dedicated runtime for the blocking RPC fetches `DatabaseRef` requires.
+struct FetchRuntime(Option<tokio::runtime::Runtime>);
+
+impl FetchRuntime {
+    fn block_on<F: Future + Send>(&self, fut: F) -> F::Output
+    where
+        F::Output: Send,
+    {
+        let rt = self.0.as_ref().expect("runtime is only taken on drop");
+        if tokio::runtime::Handle::try_current().is_ok() {
+            // a runtime cannot be blocked on from inside another one
+            std::thread::scope(|s| s.spawn(|| rt.block_on(fut)).join().expect("fork db fetch panicked"))
+        } else {
+            rt.block_on(fut)
+        }
+    }
+}
+
+impl Drop for FetchRuntime {
+    fn drop(&mut self) {
+        // may be dropped from async code, where a blocking shutdown would panic
+        if let Some(rt) = self.0.take() {
+            rt.shutdown_background();
+        }
+    }
+}
+
+/// Mutable overlay over a `ForkDb` with snapshot/rollback. Branches share the
+/// underlying RPC cache, so parallel candidates only fetch each slot once.
+#[derive(Clone)]
+pub struct ForkState {
+    db: CacheDB<ForkDb>,
+    snapshots: Vec<CacheDB<ForkDb>>,
+}
+
+impl ForkState {
+    pub fn new(fork: ForkDb) -> Self {
+        Self { db: CacheDB::new(fork), snapshots: Vec::new() }
+    }
+
+    pub fn db(&self) -> &CacheDB<ForkDb> {
+        &self.db
+    }
+
+    /// Local edits (fake balances, committed txs) go through the overlay.
+    pub fn db_mut(&mut self) -> &mut CacheDB<ForkDb> {
+        &mut self.db
+    }
+
+    /// Independent copy of the current overlay for evaluating another candidate.
+    pub fn branch(&self) -> Self {
+        Self { db: self.db.clone(), snapshots: Vec::new() }
+    }
+
+    /// Save the overlay and return an id for `rollback`.
+    pub fn snapshot(&mut self) -> usize {
+        self.snapshots.push(self.db.clone());
+        self.snapshots.len() - 1
+    }
+
+    /// Restore the overlay saved by `snapshot(id)`, dropping it and any later snapshots.
+    pub fn rollback(&mut self, id: usize) -> bool {
+        if id >= self.snapshots.len() {
+            return false;
+        }
+        self.snapshots.truncate(id + 1);
+        self.db = self.snapshots.pop().expect("checked above");
+        true
+    }
+}
+
+impl DatabaseRef for ForkState {
+    type Error = anyhow::Error;
+
+    fn basic_ref(&self, address: RAddress) -> Result<Option<AccountInfo>> {
+        self.db.basic_ref(address)
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode> {
+        self.db.code_by_hash_ref(code_hash)
+    }
+
+    fn storage_ref(&self, address: RAddress, index: U256) -> Result<U256> {
+        self.db.storage_ref(address, index)
+    }
+
+    fn block_hash_ref(&self, number: U256) -> Result<B256> {
+        self.db.block_hash_ref(number)
+    }
+}
+
+/// Balance as seen by `db`, for callers that only have ethers types.
+pub fn balance_of<DB: DatabaseRef>(db: &DB, address: Address) -> Result<EU256>
+where
+    DB::Error: std::fmt::Debug,
+{
+    let info = db.basic_ref(to_raddress(address)).map_err(|e| anyhow!("{:?}", e))?;
+    Ok(EU256(info.map(|i| i.balance).unwrap_or_default().into_limbs()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::{Method::POST, MockServer};
+
+    fn rpc_result(v: &str) -> String {
+        format!(r#"{{"jsonrpc":"2.0","id":1,"result":{}}}"#, v)
+    }
+
+    fn block_result(number: u64, hash: u64) -> String {
+        rpc_result(&serde_json::json!({
+            "hash": H256::from_low_u64_be(hash), "parentHash": H256::zero(), "sha3Uncles": H256::zero(),
+            "miner": Address::zero(), "stateRoot": H256::zero(), "transactionsRoot": H256::zero(), "receiptsRoot": H256::zero(),
+            "number": format!("0x{:x}", number), "gasUsed": "0x0", "gasLimit": "0x1c9c380", "extraData": "0x",
+            "logsBloom": null, "timestamp": "0x1", "difficulty": "0x0", "totalDifficulty": "0x0",
+            "uncles": [], "transactions": [], "size": "0x1",
+        }).to_string())
+    }
+
+    #[test]
+    fn fetches_lazily_caches_and_invalidates_touched_slots() {
+        let server = MockServer::start();
+        let balance = server.mock(|when, then| {
+            when.method(POST).body_contains("eth_getBalance");
+            then.status(200).body(rpc_result(r#""0x64""#));
+        });
+        server.mock(|when, then| {
+            when.method(POST).body_contains("eth_getTransactionCount");
+            then.status(200).body(rpc_result(r#""0x2""#));
+        });
+        server.mock(|when, then| {
+            when.method(POST).body_contains("eth_getCode");
+            then.status(200).body(rpc_result(r#""0x""#));
+        });
+        let slot_at_100 = server.mock(|when, then| {
+            when.method(POST).body_contains("eth_getStorageAt").body_contains(r#""0x64""#);
+            then.status(200).body(rpc_result(&format!(r#""0x{:064x}""#, 7)));
+        });
+        let slot_at_101 = server.mock(|when, then| {
+            when.method(POST).body_contains("eth_getStorageAt").body_contains(r#""0x65""#);
+            then.status(200).body(rpc_result(&format!(r#""0x{:064x}""#, 8)));
+        });
+        let pool = Address::from_low_u64_be(0xabc);
+        let trace = serde_json::json!([{ "result": {
+            "pre": { format!("{:?}", pool): { "storage": { format!("0x{:064x}", 1): "0x07" } } },
+            "post": { format!("{:?}", pool): { "storage": { format!("0x{:064x}", 1): "0x08" } } },
+        }}]);
+        server.mock(|when, then| {
+            when.method(POST).body_contains("debug_traceBlockByNumber");
+            then.status(200).body(rpc_result(&trace.to_string()));
+        });
+
+        let mut head = server.mock(|when, then| {
+            when.method(POST).body_contains("eth_getBlockByNumber").body_contains(r#""0x65""#);
+            then.status(200).body(block_result(101, 0xa));
+        });
+
+        let fork = ForkDb::new(&server.url("/"), 100).unwrap();
+        let account = fork.basic_ref(to_raddress(Address::from_low_u64_be(1))).unwrap().unwrap();
+        assert_eq!((account.balance, account.nonce), (U256::from(100), 2));
+        fork.basic_ref(to_raddress(Address::from_low_u64_be(1))).unwrap();
+        balance.assert_hits(1);
+
+        let (addr, slot) = (to_raddress(pool), U256::from(1));
+        assert_eq!(fork.storage_ref(addr, slot).unwrap(), U256::from(7));
+        assert_eq!(fork.storage_ref(addr, slot).unwrap(), U256::from(7));
+        slot_at_100.assert_hits(1);
+
+        // block 101 touched the slot: it is refetched at the new pin, the account is not
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let diff = rt.block_on(fork.advance_to(101)).unwrap();
+        assert!(diff.slots.contains(&(pool, H256::from_low_u64_be(1))));
+        assert_eq!(fork.pinned_block(), 101);
+        assert_eq!(fork.storage_ref(addr, slot).unwrap(), U256::from(8));
+        slot_at_101.assert_hits(1);
+        fork.basic_ref(to_raddress(Address::from_low_u64_be(1))).unwrap();
+        balance.assert_hits(1);
+
+        // block 101 was replaced by a sibling: the whole cache is orphaned
+        rt.block_on(fork.advance_to(101)).unwrap();
+        fork.basic_ref(to_raddress(Address::from_low_u64_be(1))).unwrap();
+        balance.assert_hits(1);
+        head.delete();
+        server.mock(|when, then| {
+            when.method(POST).body_contains("eth_getBlockByNumber").body_contains(r#""0x65""#);
+            then.status(200).body(block_result(101, 0xb));
+        });
+        rt.block_on(fork.advance_to(101)).unwrap();
+        assert_eq!(fork.cached(), (0, 0));
+        fork.basic_ref(to_raddress(Address::from_low_u64_be(1))).unwrap();
+        balance.assert_hits(2);
+    }
+
+    #[test]
+    fn snapshots_roll_back_and_branches_are_isolated() {
+        let server = MockServer::start();
+        let fork = ForkDb::new(&server.url("/"), 1).unwrap();
+        let mut state = ForkState::new(fork);
+        let addr = Address::from_low_u64_be(0x1234);
+        let set_balance = |state: &mut ForkState, wei: u64| {
+            state.db_mut().insert_account_info(to_raddress(addr), AccountInfo { balance: U256::from(wei), ..Default::default() });
+        };
+
+        set_balance(&mut state, 1);
+        let snap = state.snapshot();
+        set_balance(&mut state, 2);
+        let mut branch = state.branch();
+        set_balance(&mut branch, 3);
+        assert_eq!(balance_of(&state, addr).unwrap(), EU256::from(2));
+        assert_eq!(balance_of(&branch, addr).unwrap(), EU256::from(3));
+
+        assert!(state.rollback(snap));
+        assert_eq!(balance_of(&state, addr).unwrap(), EU256::from(1));
+        assert!(!state.rollback(snap));
+    }
+}
@@ -0,0 +1,372 @@
+use anyhow::{Result, Context};
+use ethers_core::abi::{self, Token};
+use ethers_core::types::{Address, Bytes, TransactionRequest, U256, transaction::eip2718::TypedTransaction};
+use ethers_providers::{Provider, Http, HttpClientError, Middleware, ProviderError};
+
+/// Address of the OP-stack `GasPriceOracle` predeploy (Optimism, Base, ...).
+pub const GAS_PRICE_ORACLE: &str = "0x420000000000000000000000000000000000000F";
+
+/// Upper bound on the bytes a signature adds to an unsigned tx when it is serialized.
+/// The oracle's `getL1Fee` applies the same padding to unsigned input.
+pub const SIGNATURE_PADDING_BYTES: usize = 68;
+
+/// Parameters of the L1 data fee charged by OP-stack chains on top of L2 execution gas.
+/// Fetch them once per block with `GasPriceOracle::fetch_params` and reuse them for
+/// every candidate; the fee itself is then computed offline from the serialized tx.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum L1FeeParams {
+    /// Pre-Ecotone: `(calldata_gas + overhead) * l1_base_fee * scalar / 10^decimals`.
+    Bedrock {
+        l1_base_fee: U256,
+        overhead: U256,
+        scalar: U256,
+        decimals: U256,
+    },
+    /// Ecotone: `calldata_gas * (16 * l1_base_fee * base_fee_scalar + blob_base_fee * blob_base_fee_scalar) / 16e6`.
+    Ecotone {
+        l1_base_fee: U256,
+        base_fee_scalar: U256,
+        blob_base_fee: U256,
+        blob_base_fee_scalar: U256,
+    },
+    /// Fjord: the Ecotone fee scalars applied to a size estimated from the FastLZ-compressed
+    /// tx, `max(100e6, 836_500 * flz_size - 42_585_600) * (16 * l1_base_fee * base_fee_scalar + blob_base_fee * blob_base_fee_scalar) / 1e12`.
+    Fjord {
+        l1_base_fee: U256,
+        base_fee_scalar: U256,
+        blob_base_fee: U256,
+        blob_base_fee_scalar: U256,
+    },
+}
+
+impl L1FeeParams {
+    /// L1 data fee (wei) for a signed raw transaction, as the node charges it.
+    pub fn l1_fee(&self, raw_tx: &[u8]) -> U256 {
+        self.fee(raw_tx, 0)
+    }
+
+    /// L1 data fee (wei) for an unsigned serialized transaction. Pads the size by
+    /// `SIGNATURE_PADDING_BYTES` non-zero bytes so the estimate is an upper bound.
+    pub fn l1_fee_unsigned(&self, unsigned_rlp: &[u8]) -> U256 {
+        self.fee(unsigned_rlp, SIGNATURE_PADDING_BYTES)
+    }
+
+    fn fee(&self, data: &[u8], padding: usize) -> U256 {
+        let gas = || calldata_gas(data).saturating_add(U256::from(padding as u64 * 16));
+        match *self {
+            L1FeeParams::Bedrock { l1_base_fee, overhead, scalar, decimals } => {
+                let divisor = U256::from(10u64).overflowing_pow(decimals.min(U256::from(77u64))).0;
+                gas().saturating_add(overhead)
+                    .saturating_mul(l1_base_fee)
+                    .saturating_mul(scalar)
+                    / divisor.max(U256::one())
+            }
+            L1FeeParams::Ecotone { l1_base_fee, base_fee_scalar, blob_base_fee, blob_base_fee_scalar } => {
+                gas().saturating_mul(weighted_l1_fee(l1_base_fee, base_fee_scalar, blob_base_fee, blob_base_fee_scalar)) / U256::from(16_000_000u64)
+            }
+            L1FeeParams::Fjord { l1_base_fee, base_fee_scalar, blob_base_fee, blob_base_fee_scalar } => {
+                let flz_size = U256::from(flz_compress_len(data) as u64 + padding as u64);
+                let estimated_size = (flz_size * U256::from(836_500u64)).saturating_sub(U256::from(42_585_600u64)).max(U256::from(100_000_000u64));
+                estimated_size.saturating_mul(weighted_l1_fee(l1_base_fee, base_fee_scalar, blob_base_fee, blob_base_fee_scalar)) / U256::exp10(12)
+            }
+        }
+    }
+}
+
+fn weighted_l1_fee(l1_base_fee: U256, base_fee_scalar: U256, blob_base_fee: U256, blob_base_fee_scalar: U256) -> U256 {
+    U256::from(16u64)
+        .saturating_mul(l1_base_fee)
+        .saturating_mul(base_fee_scalar)
+        .saturating_add(blob_base_fee.saturating_mul(blob_base_fee_scalar))
+}
+
+/// Length of `data` after FastLZ (level 1) compression, computed the way the Fjord
+/// `GasPriceOracle` and op-geth do without producing the compressed bytes.
+pub fn flz_compress_len(data: &[u8]) -> usize {
+    let u24 = |i: usize| data[i] as u32 | (data[i + 1] as u32) << 8 | (data[i + 2] as u32) << 16;
+    let hash = |v: u32| (2_654_435_769u32.wrapping_mul(v) >> 19) as usize & 0x1fff;
+    let literals = |r: usize| 0x21 * (r / 0x20) + if r.is_multiple_of(0x20) { 0 } else { r % 0x20 + 1 };
+    let matched = |l: usize| 3 * ((l - 1) / 262) + if (l - 1) % 262 >= 6 { 3 } else { 2 };
+
+    let mut table = vec![0usize; 8192];
+    let mut n = 0;
+    let mut anchor = 0;
+    let ip_limit = data.len().saturating_sub(13);
+    let mut ip = anchor + 2;
+    while ip < ip_limit {
+        let mut r;
+        loop {
+            let seq = u24(ip);
+            let h = hash(seq);
+            r = table[h];
+            table[h] = ip;
+            let distance = ip - r;
+            if ip >= ip_limit {
+                break;
+            }
+            ip += 1;
+            if distance <= 0x1fff && seq == u24(r) {
+                break;
+            }
+        }
+        if ip >= ip_limit {
+            break;
+        }
+        ip -= 1;
+        if ip > anchor {
+            n += literals(ip - anchor);
+        }
+        // match length; the first mismatching byte is counted, as in the reference
+        let (p, q, end) = (r + 3, ip + 3, ip_limit + 9);
+        let mut l = 0;
+        while l < end - q {
+            l += 1;
+            if data[p + l - 1] != data[q + l - 1] {
+                break;
+            }
+        }
+        n += matched(l);
+        ip += l;
+        table[hash(u24(ip))] = ip;
+        table[hash(u24(ip + 1))] = ip + 1;
+        ip += 2;
+        anchor = ip;
+    }
+    n + literals(data.len() - anchor)
+}
+
+/// Calldata gas of a serialized tx: 4 per zero byte, 16 per non-zero byte.
+pub fn calldata_gas(data: &[u8]) -> U256 {
+    let zeros = data.iter().filter(|b| **b == 0).count() as u64;
+    let non_zeros = data.len() as u64 - zeros;
+    U256::from(zeros * 4 + non_zeros * 16)
+}
+
+/// Thin client for the `GasPriceOracle` predeploy.
+#[derive(Clone)]
+pub struct GasPriceOracle {
+    rpc: String,
+    address: Address,
+}
+
+impl GasPriceOracle {
+    pub fn new(rpc: String) -> Self {
+        let address = GAS_PRICE_ORACLE.parse().expect("valid predeploy address");
+        Self { rpc, address }
+    }
+
+    /// Use a non-standard oracle address (e.g. a mock contract deployed on Anvil).
+    pub fn with_address(rpc: String, address: Address) -> Self {
+        Self { rpc, address }
+    }
+
+    /// Ask the oracle for the exact L1 fee of `data` via `getL1Fee(bytes)`.
+    /// The oracle treats `data` as unsigned and pads it for the signature.
+    pub async fn get_l1_fee(&self, data: &[u8]) -> Result<U256> {
+        let args = abi::encode(&[Token::Bytes(data.to_vec())]);
+        self.call_u256("getL1Fee(bytes)", &args).await
+    }
+
+    /// Read the current fee parameters so fees can be computed offline. Oracles that
+    /// predate a fork revert on its `isEcotone()` / `isFjord()` flag, which reads as
+    /// not activated; any other error is returned.
+    pub async fn fetch_params(&self) -> Result<L1FeeParams> {
+        let l1_base_fee = self.call_u256("l1BaseFee()", &[]).await?;
+        if !self.fork_flag("isEcotone()").await? {
+            return Ok(L1FeeParams::Bedrock {
+                l1_base_fee,
+                overhead: self.call_u256("overhead()", &[]).await?,
+                scalar: self.call_u256("scalar()", &[]).await?,
+                decimals: self.call_u256("decimals()", &[]).await?,
+            });
+        }
+        let is_fjord = self.fork_flag("isFjord()").await?;
+        let base_fee_scalar = self.call_u256("baseFeeScalar()", &[]).await?;
+        let blob_base_fee = self.call_u256("blobBaseFee()", &[]).await?;
+        let blob_base_fee_scalar = self.call_u256("blobBaseFeeScalar()", &[]).await?;
+        Ok(if is_fjord {
+            L1FeeParams::Fjord { l1_base_fee, base_fee_scalar, blob_base_fee, blob_base_fee_scalar }
+        } else {
+            L1FeeParams::Ecotone { l1_base_fee, base_fee_scalar, blob_base_fee, blob_base_fee_scalar }
+        })
+    }
+
+    async fn fork_flag(&self, signature: &str) -> Result<bool> {
+        match self.call_u256(signature, &[]).await {
+            Ok(v) => Ok(!v.is_zero()),
+            Err(e) if is_revert(&e) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn call_u256(&self, signature: &str, args: &[u8]) -> Result<U256> {
+        let provider = Provider::<Http>::try_from(self.rpc.as_str()).context("invalid rpc url")?;
+        let mut data = ethers_core::utils::id(signature).to_vec();
+        data.extend_from_slice(args);
+        let req = TransactionRequest::new().to(self.address).data(Bytes::from(data));
+        let out = provider.call(&TypedTransaction::Legacy(req), None).await
+            .with_context(|| format!("GasPriceOracle.{} failed", signature))?;
+        if out.len() < 32 {
+            return Err(anyhow::anyhow!("GasPriceOracle.{} returned {} bytes", signature, out.len()));
+        }
+        Ok(U256::from_big_endian(&out[..32]))
+    }
+}
+
+/// Whether `e` is an `eth_call` that reverted, as opposed to a transport or node failure.
+fn is_revert(e: &anyhow::Error) -> bool {
+    let Some(ProviderError::JsonRpcClientError(inner)) = e.downcast_ref::<ProviderError>() else { return false };
+    matches!(inner.downcast_ref::<HttpClientError>(), Some(HttpClientError::JsonRpcError(err)) if err.code == 3 || err.message.contains("revert"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rpc_result(v: u64) -> String {
+        format!(r#"{{"jsonrpc":"2.0","id":1,"result":"0x{:064x}"}}"#, v)
+    }
+
+    #[test]
+    fn calldata_gas_counts_zero_and_nonzero_bytes() {
+        assert_eq!(calldata_gas(&[0, 0, 1, 2]), U256::from(4 + 4 + 16 + 16));
+    }
+
+    #[test]
+    fn bedrock_fee_matches_formula() {
+        let p = L1FeeParams::Bedrock {
+            l1_base_fee: U256::from(30_000_000_000u64),
+            overhead: U256::from(188u64),
+            scalar: U256::from(684_000u64),
+            decimals: U256::from(6u64),
+        };
+        let raw = vec![1u8; 100];
+        // (1600 + 188) * 30 gwei * 0.684
+        let expected = U256::from(1788u64) * U256::from(30_000_000_000u64) * U256::from(684_000u64) / U256::from(1_000_000u64);
+        assert_eq!(p.l1_fee(&raw), expected);
+        assert!(p.l1_fee_unsigned(&raw) > p.l1_fee(&raw));
+    }
+
+    #[test]
+    fn ecotone_fee_matches_formula() {
+        let p = L1FeeParams::Ecotone {
+            l1_base_fee: U256::from(10_000_000_000u64),
+            base_fee_scalar: U256::from(1_368u64),
+            blob_base_fee: U256::from(1u64),
+            blob_base_fee_scalar: U256::from(810_949u64),
+        };
+        let raw = vec![0u8, 1u8];
+        let weighted = U256::from(16u64) * U256::from(10_000_000_000u64) * U256::from(1_368u64) + U256::from(810_949u64);
+        assert_eq!(p.l1_fee(&raw), U256::from(20u64) * weighted / U256::from(16_000_000u64));
+    }
+
+    #[test]
+    fn flz_compress_len_counts_literals_and_matches() {
+        assert_eq!(flz_compress_len(&[]), 0);
+        assert_eq!(flz_compress_len(&[1, 2, 3, 4, 5]), 6);
+        // 32 literals need one 33-byte run
+        assert_eq!(flz_compress_len(&(0u8..32).collect::<Vec<_>>()), 33);
+        // 2 literals, one long match, 5 trailing literals
+        assert_eq!(flz_compress_len(&[0u8; 200]), 12);
+    }
+
+    #[test]
+    fn fjord_fee_uses_compressed_size_with_a_floor() {
+        let p = L1FeeParams::Fjord {
+            l1_base_fee: U256::from(10_000_000_000u64),
+            base_fee_scalar: U256::from(1_368u64),
+            blob_base_fee: U256::from(1u64),
+            blob_base_fee_scalar: U256::from(810_949u64),
+        };
+        let weighted = U256::from(16u64) * U256::from(10_000_000_000u64) * U256::from(1_368u64) + U256::from(810_949u64);
+        // small txs are charged as 100 bytes
+        assert_eq!(p.l1_fee(&[0u8; 200]), U256::from(100_000_000u64) * weighted / U256::exp10(12));
+        let raw: Vec<u8> = (0u8..=255).collect();
+        let size = flz_compress_len(&raw) as u64;
+        assert_eq!(p.l1_fee(&raw), U256::from(836_500 * size - 42_585_600) * weighted / U256::exp10(12));
+        let padded = U256::from(836_500 * (size + SIGNATURE_PADDING_BYTES as u64) - 42_585_600) * weighted / U256::exp10(12);
+        assert_eq!(p.l1_fee_unsigned(&raw), padded);
+    }
+
+    #[tokio::test]
+    async fn oracle_get_l1_fee_calls_predeploy() {
+        let server = httpmock::MockServer::start();
+        let selector = hex::encode(ethers_core::utils::id("getL1Fee(bytes)"));
+        let m = server.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/")
+                .body_contains("eth_call")
+                .body_contains(&selector)
+                .body_contains(GAS_PRICE_ORACLE.to_lowercase());
+            then.status(200).body(rpc_result(42_000));
+        });
+
+        let oracle = GasPriceOracle::new(server.url("/"));
+        let fee = oracle.get_l1_fee(&[0x02, 0x01]).await.unwrap();
+        assert_eq!(fee, U256::from(42_000u64));
+        m.assert();
+    }
+
+    #[tokio::test]
+    async fn oracle_fetch_params_falls_back_to_bedrock() {
+        let server = httpmock::MockServer::start();
+        let mock_fn = |sig: &str, v: Option<u64>| {
+            let selector = hex::encode(ethers_core::utils::id(sig));
+            server.mock(move |when, then| {
+                when.method(httpmock::Method::POST).path("/").body_contains(&selector);
+                match v {
+                    Some(v) => { then.status(200).body(rpc_result(v)); }
+                    None => { then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"error":{"code":3,"message":"execution reverted"}}"#); }
+                }
+            })
+        };
+        let _a = mock_fn("l1BaseFee()", Some(7));
+        let _b = mock_fn("isEcotone()", None);
+        let _c = mock_fn("overhead()", Some(188));
+        let _d = mock_fn("scalar()", Some(684_000));
+        let _e = mock_fn("decimals()", Some(6));
+
+        let oracle = GasPriceOracle::new(server.url("/"));
+        let p = oracle.fetch_params().await.unwrap();
+        assert_eq!(p, L1FeeParams::Bedrock {
+            l1_base_fee: U256::from(7u64),
+            overhead: U256::from(188u64),
+            scalar: U256::from(684_000u64),
+            decimals: U256::from(6u64),
+        });
+    }
+
+    #[tokio::test]
+    async fn oracle_fetch_params_detects_fjord_and_propagates_failures() {
+        let server = httpmock::MockServer::start();
+        let mock_fn = |sig: &str, v: u64| {
+            let selector = hex::encode(ethers_core::utils::id(sig));
+            server.mock(move |when, then| {
+                when.method(httpmock::Method::POST).path("/").body_contains(&selector);
+                then.status(200).body(rpc_result(v));
+            })
+        };
+        let _a = mock_fn("l1BaseFee()", 7);
+        let mut ecotone = mock_fn("isEcotone()", 1);
+        let _c = mock_fn("isFjord()", 1);
+        let _d = mock_fn("baseFeeScalar()", 1_368);
+        let _e = mock_fn("blobBaseFee()", 1);
+        let _f = mock_fn("blobBaseFeeScalar()", 810_949);
+
+        let oracle = GasPriceOracle::new(server.url("/"));
+        assert_eq!(oracle.fetch_params().await.unwrap(), L1FeeParams::Fjord {
+            l1_base_fee: U256::from(7u64),
+            base_fee_scalar: U256::from(1_368u64),
+            blob_base_fee: U256::one(),
+            blob_base_fee_scalar: U256::from(810_949u64),
+        });
+
+        // an unavailable node is not an old oracle
+        ecotone.delete();
+        let selector = hex::encode(ethers_core::utils::id("isEcotone()"));
+        server.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/").body_contains(&selector);
+            then.status(503).body("unavailable");
+        });
+        assert!(oracle.fetch_params().await.unwrap_err().to_string().contains("isEcotone()"));
+    }
+}
@@ -8,6 +8,22 @@ pub mod tx;
 pub mod kms;
 pub mod crypto;
 pub mod autosubmit;
+pub mod l2fee;
+pub mod fees;
+pub mod nonce;
+pub mod auth;
+pub mod relay_set;
+pub mod relay_health;
+pub mod retarget;
+pub mod mevshare;
+pub mod mock_relay;
+pub mod revm_backend;
+pub mod fork_db;
+pub mod revert;
+pub mod sim_pool;
+pub mod bid_search;
+pub mod scoring;
+pub mod profit_decay;
 
 use anyhow::Result;
 use tracing::{info, warn};
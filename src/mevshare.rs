@@ -0,0 +1,336 @@
+use anyhow::{Result, Context};
+use ethers_core::types::{Address, Bytes, H256, U256, U64};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Public MEV-Share event stream on mainnet.
+pub const MEV_SHARE_STREAM_URL: &str = "https://mev-share.flashbots.net";
+
+/// A log revealed by the hint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HintLog {
+    pub address: Address,
+    #[serde(default, deserialize_with = "null_as_empty")]
+    pub topics: Vec<H256>,
+    #[serde(default)]
+    pub data: Option<Bytes>,
+}
+
+/// A transaction hint; every field is optional and depends on what the user shares.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HintTx {
+    #[serde(default)]
+    pub to: Option<Address>,
+    #[serde(default)]
+    pub function_selector: Option<Bytes>,
+    #[serde(default)]
+    pub call_data: Option<Bytes>,
+}
+
+/// One event from the MEV-Share stream: a pending transaction or bundle, identified
+/// only by `hash`, with whatever hints the sender chose to reveal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MevShareEvent {
+    pub hash: H256,
+    #[serde(default, deserialize_with = "null_as_empty")]
+    pub logs: Vec<HintLog>,
+    #[serde(default, deserialize_with = "null_as_empty")]
+    pub txs: Vec<HintTx>,
+    #[serde(default)]
+    pub mev_gas_price: Option<U256>,
+    #[serde(default)]
+    pub gas_used: Option<U256>,
+}
+
+/// The stream sends `null` rather than omitting hint lists it has nothing for.
+fn null_as_empty<'de, D, T>(d: D) -> std::result::Result<Vec<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Ok(Option::<Vec<T>>::deserialize(d)?.unwrap_or_default())
+}
+
+impl MevShareEvent {
+    /// 4-byte selectors revealed for the event's transactions.
+    pub fn selectors(&self) -> Vec<[u8; 4]> {
+        self.txs.iter()
+            .filter_map(|t| t.function_selector.as_ref().or(t.call_data.as_ref()))
+            .filter(|b| b.len() >= 4)
+            .map(|b| [b[0], b[1], b[2], b[3]])
+            .collect()
+    }
+}
+
+/// Incremental `text/event-stream` parser. Feed raw chunks; get back the `data`
+/// payload of each complete event. Comments (`: ping`) and other fields are skipped.
+#[derive(Default)]
+pub struct SseDecoder {
+    /// Bytes after the last newline; a UTF-8 character may be split across chunks
+    buf: Vec<u8>,
+    data: Vec<String>,
+}
+
+impl SseDecoder {
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buf.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(pos) = self.buf.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buf.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push(self.data.join("\n"));
+                    self.data.clear();
+                }
+            } else if let Some(data) = line.strip_prefix("data:") {
+                self.data.push(data.strip_prefix(' ').unwrap_or(data).to_string());
+            }
+        }
+        events
+    }
+}
+
+/// Client for a MEV-Share-style SSE hint stream.
+pub struct MevShareStream {
+    client: Client,
+    url: String,
+    reconnect_delay: Duration,
+}
+
+impl MevShareStream {
+    pub fn new(url: String) -> Result<Self> {
+        // No overall timeout: the stream is long-lived.
+        let client = Client::builder().connect_timeout(Duration::from_secs(10)).build()?;
+        Ok(Self { client, url, reconnect_delay: Duration::from_secs(1) })
+    }
+
+    pub fn with_reconnect_delay(mut self, delay: Duration) -> Self {
+        self.reconnect_delay = delay;
+        self
+    }
+
+    /// Connect and forward decoded events, reconnecting when the stream drops.
+    /// The task stops once the receiver is dropped.
+    pub fn subscribe(self) -> mpsc::Receiver<MevShareEvent> {
+        let (tx, rx) = mpsc::channel(1024);
+        tokio::spawn(async move {
+            loop {
+                match self.read_stream(&tx).await {
+                    Ok(()) => tracing::info!("mev-share stream closed; reconnecting"),
+                    Err(e) => tracing::warn!("mev-share stream error: {:?}", e),
+                }
+                if tx.is_closed() {
+                    return;
+                }
+                tokio::time::sleep(self.reconnect_delay).await;
+            }
+        });
+        rx
+    }
+
+    async fn read_stream(&self, tx: &mpsc::Sender<MevShareEvent>) -> Result<()> {
+        let mut resp = self.client.get(&self.url)
+            .header(reqwest::header::ACCEPT, "text/event-stream")
+            .send().await.context("mev-share connect failed")?
+            .error_for_status().context("mev-share stream rejected")?;
+        let mut decoder = SseDecoder::default();
+        while let Some(chunk) = resp.chunk().await.context("mev-share read failed")? {
+            for data in decoder.push(&chunk) {
+                match serde_json::from_str::<MevShareEvent>(&data) {
+                    Ok(event) => {
+                        if tx.send(event).await.is_err() {
+                            return Ok(());
+                        }
+                    }
+                    Err(e) => tracing::debug!("skipping undecodable mev-share event: {}", e),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Blocks a `mev_sendBundle` may land in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Inclusion {
+    pub block: U64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_block: Option<U64>,
+}
+
+/// A bundle element: a hint transaction referenced by hash, or one of our signed txs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BundleItem {
+    Hash { hash: H256 },
+    #[serde(rename_all = "camelCase")]
+    Tx { tx: Bytes, can_revert: bool },
+}
+
+/// Share of the bundle's MEV paid back to the sender of body item `body_idx`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Refund {
+    pub body_idx: u32,
+    pub percent: u32,
+}
+
+/// Split of our own refund between addresses.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RefundConfig {
+    pub address: Address,
+    pub percent: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Validity {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub refund: Vec<Refund>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub refund_config: Vec<RefundConfig>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Privacy {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hints: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub builders: Vec<String>,
+}
+
+/// `mev_sendBundle` request (protocol version v0.1).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MevShareBundle {
+    pub version: String,
+    pub inclusion: Inclusion,
+    pub body: Vec<BundleItem>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validity: Option<Validity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub privacy: Option<Privacy>,
+}
+
+impl MevShareBundle {
+    /// Backrun the hinted transaction `target` with our signed txs, valid for
+    /// `block..=block + extra_blocks`.
+    pub fn backrun(target: H256, backrun_txs: &[Vec<u8>], block: u64, extra_blocks: u64) -> Self {
+        let mut body = vec![BundleItem::Hash { hash: target }];
+        body.extend(backrun_txs.iter().map(|raw| BundleItem::Tx { tx: Bytes::from(raw.clone()), can_revert: false }));
+        Self {
+            version: "v0.1".to_string(),
+            inclusion: Inclusion {
+                block: U64::from(block),
+                max_block: (extra_blocks > 0).then(|| U64::from(block + extra_blocks)),
+            },
+            body,
+            validity: None,
+            privacy: None,
+        }
+    }
+
+    /// Pay `percent` of the MEV to the sender of body item `body_idx`.
+    pub fn with_refund(mut self, body_idx: u32, percent: u32) -> Self {
+        self.validity.get_or_insert_with(Validity::default).refund.push(Refund { body_idx, percent });
+        self
+    }
+
+    /// Route our share of refunds to `address`.
+    pub fn with_refund_config(mut self, address: Address, percent: u32) -> Self {
+        self.validity.get_or_insert_with(Validity::default).refund_config.push(RefundConfig { address, percent });
+        self
+    }
+
+    pub fn with_privacy(mut self, privacy: Privacy) -> Self {
+        self.privacy = Some(privacy);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::RelayClient;
+
+    const EVENT: &str = r#"{"hash":"0x2228f5d8954ce31dc1601a8ba264dbd401bf1428388ce88238932815c5d6f23f","logs":[{"address":"0x0000000000000000000000000000000000000001","topics":["0x0000000000000000000000000000000000000000000000000000000000000002"],"data":"0x"}],"txs":[{"to":"0x0000000000000000000000000000000000000003","functionSelector":"0x38ed1739"}],"mevGasPrice":"0x3b9aca00","gasUsed":"0x5208"}"#;
+
+    #[test]
+    fn sse_decoder_handles_split_chunks_and_comments() {
+        let mut d = SseDecoder::default();
+        assert!(d.push(b": ping\n\nda").is_empty());
+        assert!(d.push(b"ta: {\"a\":1}\r\n").is_empty());
+        assert_eq!(d.push(b"\r\ndata: x\ndata: y\n\n"), vec![r#"{"a":1}"#.to_string(), "x\ny".to_string()]);
+
+        // "é" is 0xc3 0xa9; the chunk boundary falls inside it
+        let event = "data: {\"name\":\"caf\u{e9}\"}\n\n".as_bytes();
+        let split = event.iter().position(|&b| b == 0xc3).unwrap() + 1;
+        assert!(d.push(&event[..split]).is_empty());
+        assert_eq!(d.push(&event[split..]), vec!["{\"name\":\"caf\u{e9}\"}".to_string()]);
+    }
+
+    #[test]
+    fn event_with_null_hint_lists_decodes() {
+        let raw = r#"{"hash":"0x2228f5d8954ce31dc1601a8ba264dbd401bf1428388ce88238932815c5d6f23f","logs":null,"txs":null,"mevGasPrice":"0x3b9aca00","gasUsed":null}"#;
+        let event: MevShareEvent = serde_json::from_str(raw).unwrap();
+        assert!(event.logs.is_empty() && event.txs.is_empty());
+        assert_eq!(event.gas_used, None);
+
+        let raw = r#"{"hash":"0x2228f5d8954ce31dc1601a8ba264dbd401bf1428388ce88238932815c5d6f23f","logs":[{"address":"0x0000000000000000000000000000000000000001","topics":null,"data":null}],"txs":[{"to":null,"functionSelector":"0x38ed1739","callData":null}]}"#;
+        let event: MevShareEvent = serde_json::from_str(raw).unwrap();
+        assert!(event.logs[0].topics.is_empty());
+        assert_eq!(event.selectors(), vec![[0x38, 0xed, 0x17, 0x39]]);
+    }
+
+    #[tokio::test]
+    async fn stream_decodes_hints_from_sse_server() {
+        let server = httpmock::MockServer::start();
+        server.mock(|when, then| {
+            when.method(httpmock::Method::GET).path("/");
+            then.status(200)
+                .header("content-type", "text/event-stream")
+                .body(format!(":ping\n\ndata: {}\n\ndata: not json\n\n", EVENT));
+        });
+
+        let mut rx = MevShareStream::new(server.url("/")).unwrap()
+            .with_reconnect_delay(Duration::from_millis(50))
+            .subscribe();
+        let event = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+        assert_eq!(event.logs.len(), 1);
+        assert_eq!(event.selectors(), vec![[0x38, 0xed, 0x17, 0x39]]);
+        assert_eq!(event.mev_gas_price, Some(U256::from(1_000_000_000u64)));
+        assert_eq!(event.gas_used, Some(U256::from(21_000u64)));
+    }
+
+    #[tokio::test]
+    async fn mev_send_bundle_references_hint_by_hash() {
+        let server = httpmock::MockServer::start();
+        let hash: H256 = "0x2228f5d8954ce31dc1601a8ba264dbd401bf1428388ce88238932815c5d6f23f".parse().unwrap();
+        let m = server.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/")
+                .body_contains("mev_sendBundle")
+                .body_contains(r#""version":"v0.1""#)
+                .body_contains(format!(r#"{{"hash":"{:?}"}}"#, hash))
+                .body_contains(r#""tx":"0x0102""#)
+                .body_contains(r#""canRevert":false"#)
+                .body_contains(r#""inclusion":{"block":"0xa","maxBlock":"0xc"}"#)
+                .body_contains(r#""refund":[{"bodyIdx":0,"percent":90}]"#)
+                .body_contains(r#""refundConfig":[{"address":"0x0000000000000000000000000000000000000005","percent":100}]"#);
+            then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"result":{"bundleHash":"0x2228f5d8954ce31dc1601a8ba264dbd401bf1428388ce88238932815c5d6f23f"}}"#);
+        });
+
+        let bundle = MevShareBundle::backrun(hash, &[vec![0x01, 0x02]], 10, 2)
+            .with_refund(0, 90)
+            .with_refund_config(Address::from_low_u64_be(5), 100);
+        let rc = RelayClient::with_url(server.url("/")).unwrap();
+        let resp = rc.send_mev_share_bundle(&bundle).await.unwrap();
+        assert_eq!(resp.bundle_hash, hash);
+        m.assert();
+    }
+}
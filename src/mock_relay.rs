@@ -0,0 +1,315 @@
+//! In-process mock relay for integration tests. Speaks `eth_sendBundle`,
+//! `eth_callBundle` and `eth_cancelBundle`, checks `X-Flashbots-Signature`, forwards
+//! simulations and inclusions to a local Anvil, and plays scripted scenarios.
+
+use anyhow::{Result, Context};
+use ethers_core::types::{Address, Bytes, H256};
+use ethers_providers::{Provider, Http, Middleware};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server, StatusCode};
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use crate::auth::{verify_signature_header, FLASHBOTS_SIGNATURE_HEADER};
+use crate::executor::compute_bundle_hash;
+
+/// How the mock answers the next `eth_sendBundle`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Scenario {
+    /// Accept and return the bundle hash; nothing lands on chain
+    Accept,
+    /// Accept and send the bundle's txs to Anvil, so they are mined
+    Include,
+    /// Answer with a JSON-RPC error
+    Reject { code: i64, message: String },
+    /// Answer with a bare HTTP status
+    HttpError(u16),
+    /// Wait, then behave like `Accept`
+    Delay(Duration),
+}
+
+/// A request the mock received, after auth checks.
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub params: serde_json::Value,
+    /// Address recovered from `X-Flashbots-Signature`, if the header was present and valid
+    pub signer: Option<Address>,
+}
+
+#[derive(Default)]
+struct MockState {
+    require_auth: bool,
+    anvil_url: Option<String>,
+    scenarios: Mutex<VecDeque<Scenario>>,
+    default_scenario: Mutex<Option<Scenario>>,
+    requests: Mutex<Vec<RecordedRequest>>,
+    cancelled: Mutex<Vec<String>>,
+}
+
+/// Handle to a running mock relay; the server stops when this is dropped.
+pub struct MockRelay {
+    addr: SocketAddr,
+    state: Arc<MockState>,
+    _shutdown: oneshot::Sender<()>,
+}
+
+impl MockRelay {
+    /// Start on an ephemeral localhost port. `anvil_url` enables `Include` and
+    /// `eth_callBundle`; without it they fail with a JSON-RPC error.
+    pub async fn start(anvil_url: Option<String>, require_auth: bool) -> Result<Self> {
+        let state = Arc::new(MockState { require_auth, anvil_url, ..Default::default() });
+        let svc_state = state.clone();
+        let make_svc = make_service_fn(move |_| {
+            let state = svc_state.clone();
+            async move { Ok::<_, Infallible>(service_fn(move |req| handle(state.clone(), req))) }
+        });
+        let server = Server::try_bind(&SocketAddr::from(([127, 0, 0, 1], 0))).context("bind mock relay")?.serve(make_svc);
+        let addr = server.local_addr();
+        let (tx, rx) = oneshot::channel::<()>();
+        tokio::spawn(server.with_graceful_shutdown(async { let _ = rx.await; }));
+        Ok(Self { addr, state, _shutdown: tx })
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}/", self.addr)
+    }
+
+    /// Queue a scenario for the next `eth_sendBundle`; queued scenarios run in order.
+    pub fn push_scenario(&self, scenario: Scenario) {
+        self.state.scenarios.lock().unwrap().push_back(scenario);
+    }
+
+    /// Scenario used once the queue is empty (default `Accept`).
+    pub fn set_default_scenario(&self, scenario: Scenario) {
+        *self.state.default_scenario.lock().unwrap() = Some(scenario);
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.requests.lock().unwrap().clone()
+    }
+
+    /// Requests for one JSON-RPC method.
+    pub fn requests_for(&self, method: &str) -> Vec<RecordedRequest> {
+        self.requests().into_iter().filter(|r| r.method == method).collect()
+    }
+
+    /// `replacementUuid`s passed to `eth_cancelBundle`.
+    pub fn cancelled(&self) -> Vec<String> {
+        self.state.cancelled.lock().unwrap().clone()
+    }
+}
+
+async fn handle(state: Arc<MockState>, req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let header = req.headers().get(FLASHBOTS_SIGNATURE_HEADER).and_then(|v| v.to_str().ok()).map(str::to_string);
+    let body = match hyper::body::to_bytes(req.into_body()).await {
+        Ok(b) => b,
+        Err(_) => return Ok(http_error(StatusCode::BAD_REQUEST, "unreadable body")),
+    };
+    let signer = header.as_deref().and_then(|h| verify_signature_header(h, &body).ok());
+    if state.require_auth && signer.is_none() {
+        let msg = if header.is_some() { "invalid flashbots signature" } else { "missing flashbots signature header" };
+        return Ok(rpc_error(StatusCode::FORBIDDEN, -32600, msg));
+    }
+
+    let request: serde_json::Value = match serde_json::from_slice(&body) {
+        Ok(v) => v,
+        Err(_) => return Ok(rpc_error(StatusCode::BAD_REQUEST, -32700, "parse error")),
+    };
+    let method = request["method"].as_str().unwrap_or_default().to_string();
+    let params = request["params"].get(0).cloned().unwrap_or(serde_json::Value::Null);
+    state.requests.lock().unwrap().push(RecordedRequest { method: method.clone(), params: params.clone(), signer });
+
+    let resp = match method.as_str() {
+        "eth_sendBundle" => send_bundle(&state, &params).await,
+        "eth_callBundle" => call_bundle(&state, &params).await,
+        "eth_cancelBundle" => {
+            if let Some(uuid) = params["replacementUuid"].as_str() {
+                state.cancelled.lock().unwrap().push(uuid.to_string());
+            }
+            rpc_result(serde_json::Value::Null)
+        }
+        _ => rpc_error(StatusCode::OK, -32601, &format!("method not found: {}", method)),
+    };
+    Ok(resp)
+}
+
+async fn send_bundle(state: &MockState, params: &serde_json::Value) -> Response<Body> {
+    let txs = match decode_txs(params) {
+        Ok(txs) => txs,
+        Err(e) => return rpc_error(StatusCode::OK, -32602, &e),
+    };
+    let scenario = state.scenarios.lock().unwrap().pop_front()
+        .or_else(|| state.default_scenario.lock().unwrap().clone())
+        .unwrap_or(Scenario::Accept);
+    let accepted = || rpc_result(serde_json::json!({ "bundleHash": format!("{:?}", compute_bundle_hash(&txs)) }));
+    match scenario {
+        Scenario::Accept => accepted(),
+        Scenario::Delay(d) => {
+            tokio::time::sleep(d).await;
+            accepted()
+        }
+        Scenario::Reject { code, message } => rpc_error(StatusCode::OK, code, &message),
+        Scenario::HttpError(status) => http_error(StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR), "scripted failure"),
+        Scenario::Include => {
+            let provider = match anvil(state) {
+                Ok(p) => p,
+                Err(e) => return rpc_error(StatusCode::OK, -32000, &e),
+            };
+            for raw in txs.iter() {
+                if let Err(e) = provider.send_raw_transaction(Bytes::from(raw.clone())).await {
+                    return rpc_error(StatusCode::OK, -32000, &format!("anvil rejected bundle tx: {}", e));
+                }
+            }
+            accepted()
+        }
+    }
+}
+
+/// Simulate on Anvil: snapshot, send each tx, collect receipts, revert.
+async fn call_bundle(state: &MockState, params: &serde_json::Value) -> Response<Body> {
+    let txs = match decode_txs(params) {
+        Ok(txs) => txs,
+        Err(e) => return rpc_error(StatusCode::OK, -32602, &e),
+    };
+    let provider = match anvil(state) {
+        Ok(p) => p,
+        Err(e) => return rpc_error(StatusCode::OK, -32000, &e),
+    };
+    match simulate_on_anvil(&provider, &txs).await {
+        Ok(result) => rpc_result(result),
+        Err(e) => rpc_error(StatusCode::OK, -32000, &format!("simulation failed: {:?}", e)),
+    }
+}
+
+async fn simulate_on_anvil(provider: &Provider<Http>, txs: &[Vec<u8>]) -> Result<serde_json::Value> {
+    let state_block = provider.get_block_number().await?.as_u64();
+    let snapshot: serde_json::Value = provider.request("evm_snapshot", ()).await?;
+    let mut results = Vec::new();
+    let mut total_gas = 0u64;
+    let mut outcome = Ok(());
+    for raw in txs.iter() {
+        let tx_hash = H256::from(ethers_core::utils::keccak256(raw));
+        let receipt = match provider.send_raw_transaction(Bytes::from(raw.clone())).await {
+            Ok(pending) => pending.await,
+            Err(e) => {
+                outcome = Err(anyhow::anyhow!("eth_sendRawTransaction: {}", e));
+                break;
+            }
+        };
+        let receipt = match receipt {
+            Ok(Some(r)) => r,
+            Ok(None) => {
+                outcome = Err(anyhow::anyhow!("tx {:?} dropped", tx_hash));
+                break;
+            }
+            Err(e) => {
+                outcome = Err(anyhow::anyhow!("receipt: {}", e));
+                break;
+            }
+        };
+        let gas_used = receipt.gas_used.unwrap_or_default().as_u64();
+        total_gas += gas_used;
+        let mut entry = serde_json::json!({
+            "txHash": format!("{:?}", tx_hash),
+            "fromAddress": format!("{:?}", receipt.from),
+            "gasUsed": gas_used,
+        });
+        if receipt.status.map(|s| s.as_u64()) == Some(0) {
+            entry["error"] = serde_json::Value::String("execution reverted".to_string());
+        }
+        results.push(entry);
+    }
+    // Always restore state, even when a tx failed to send.
+    let _: bool = provider.request("evm_revert", [snapshot]).await?;
+    outcome?;
+    Ok(serde_json::json!({
+        "bundleHash": format!("{:?}", compute_bundle_hash(txs)),
+        "stateBlockNumber": state_block,
+        "totalGasUsed": total_gas,
+        "results": results,
+    }))
+}
+
+fn anvil(state: &MockState) -> Result<Provider<Http>, String> {
+    let url = state.anvil_url.as_deref().ok_or("mock relay has no anvil backend")?;
+    Provider::<Http>::try_from(url).map_err(|e| e.to_string())
+}
+
+fn decode_txs(params: &serde_json::Value) -> Result<Vec<Vec<u8>>, String> {
+    let txs = params["txs"].as_array().ok_or("missing txs")?;
+    txs.iter()
+        .map(|t| {
+            let s = t.as_str().ok_or("tx must be a hex string")?;
+            hex::decode(s.trim_start_matches("0x")).map_err(|e| format!("invalid tx hex: {}", e))
+        })
+        .collect()
+}
+
+fn json_response(status: StatusCode, body: serde_json::Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .expect("valid response")
+}
+
+fn rpc_result(result: serde_json::Value) -> Response<Body> {
+    json_response(StatusCode::OK, serde_json::json!({ "jsonrpc": "2.0", "id": 1, "result": result }))
+}
+
+fn rpc_error(status: StatusCode, code: i64, message: &str) -> Response<Body> {
+    json_response(status, serde_json::json!({ "jsonrpc": "2.0", "id": 1, "error": { "code": code, "message": message } }))
+}
+
+fn http_error(status: StatusCode, message: &str) -> Response<Body> {
+    Response::builder().status(status).body(Body::from(message.to_string())).expect("valid response")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::FlashbotsAuth;
+    use crate::executor::{BundleRelay, RelayClient, RelayError};
+    use crate::signer::BasicEnvSigner;
+
+    const SECRET: &str = "0123456789012345678901234567890123456789012345678901234567890123";
+
+    #[tokio::test]
+    async fn enforces_auth_and_plays_scenarios() {
+        let relay = MockRelay::start(None, true).await.unwrap();
+        let signed = vec![vec![0x01, 0x02]];
+
+        let unsigned = RelayClient::with_url(relay.url()).unwrap();
+        let err = unsigned.send_bundle(&signed, Some(1)).await.unwrap_err();
+        assert!(matches!(err, RelayError::JsonRpc { code: -32600, .. }));
+
+        let auth = Arc::new(FlashbotsAuth::from_signer(Arc::new(BasicEnvSigner::from_secret(SECRET.to_string()))));
+        let client = RelayClient::with_url(relay.url()).unwrap().with_auth(auth.clone());
+        relay.push_scenario(Scenario::Reject { code: -32000, message: "bundle underpriced".into() });
+        relay.push_scenario(Scenario::HttpError(503));
+        assert!(matches!(client.send_bundle(&signed, Some(1)).await, Err(RelayError::JsonRpc { code: -32000, .. })));
+        assert!(matches!(client.send_bundle(&signed, Some(1)).await, Err(RelayError::Http { status: 503, .. })));
+        let resp = client.send_bundle(&signed, Some(1)).await.unwrap();
+        assert_eq!(resp.bundle_hash, compute_bundle_hash(&signed));
+
+        client.cancel_bundle("3b3f7b8e-0000-4000-8000-000000000000").await.unwrap();
+        assert_eq!(relay.cancelled(), vec!["3b3f7b8e-0000-4000-8000-000000000000".to_string()]);
+
+        let sends = relay.requests_for("eth_sendBundle");
+        assert_eq!(sends.len(), 3);
+        assert_eq!(sends[0].signer, Some(auth.address().await.unwrap()));
+    }
+
+    #[tokio::test]
+    async fn simulation_without_anvil_is_a_json_rpc_error() {
+        let relay = MockRelay::start(None, false).await.unwrap();
+        let client = RelayClient::with_url(relay.url()).unwrap();
+        let err = client.simulate_flashbots_bundle(&[vec![0x01]], 1).await.unwrap_err();
+        assert!(matches!(err, RelayError::JsonRpc { code: -32000, .. }));
+        assert!(client.send_bundle(&[vec![0x01]], None).await.is_ok());
+    }
+}
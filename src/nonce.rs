@@ -0,0 +1,256 @@
+use anyhow::{Result, Context};
+use ethers_core::types::{Address, BlockId, BlockNumber};
+use ethers_providers::{Provider, Http, Middleware};
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+
+/// A contiguous range of nonces handed out for one bundle: `start..start + count`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NonceLease {
+    pub start: u64,
+    pub count: u64,
+}
+
+impl NonceLease {
+    pub fn nonces(&self) -> impl Iterator<Item = u64> {
+        self.start..self.start + self.count
+    }
+
+    /// One past the last nonce of the lease.
+    pub fn end(&self) -> u64 {
+        self.start + self.count
+    }
+}
+
+/// Outcome of reconciling local state with the chain's pending nonce.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncReport {
+    /// `eth_getTransactionCount(pending)` as seen by the node
+    pub chain_nonce: u64,
+    /// True when the chain moved past our local counter (transactions sent outside this manager)
+    pub external_advance: bool,
+    /// Nonces below the next free nonce that nothing is in flight for; higher nonces cannot be mined until these are filled
+    pub gaps: Vec<u64>,
+}
+
+#[derive(Default)]
+struct NonceState {
+    synced: bool,
+    /// Lowest nonce not yet used on chain (as of the last sync or confirmation)
+    chain_nonce: u64,
+    /// Next nonce that has never been handed out
+    next: u64,
+    in_flight: BTreeSet<u64>,
+    /// Nonces handed out then released (dropped bundles) and available for reuse
+    free: BTreeSet<u64>,
+}
+
+impl NonceState {
+    fn gaps(&self) -> Vec<u64> {
+        // Free nonces only matter while something above them is still in flight.
+        match self.in_flight.iter().next_back() {
+            Some(max_in_flight) => self.free.range(..*max_in_flight).copied().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Drop free nonces from the top of the range so `next` stays as low as possible.
+    fn compact(&mut self) {
+        while self.next > self.chain_nonce && self.free.remove(&(self.next - 1)) {
+            self.next -= 1;
+        }
+    }
+
+    fn apply_chain_nonce(&mut self, chain_nonce: u64) -> SyncReport {
+        let external_advance = self.synced && chain_nonce > self.next;
+        // Anything below the chain nonce has been consumed, by us or by someone else.
+        self.in_flight.retain(|n| *n >= chain_nonce);
+        self.free.retain(|n| *n >= chain_nonce);
+        if chain_nonce > self.next || !self.synced {
+            self.next = self.next.max(chain_nonce);
+        }
+        if chain_nonce < self.chain_nonce || chain_nonce < self.next {
+            // Reorg or dropped txs: nonces we thought were pending are free again unless still tracked in flight.
+            for n in chain_nonce..self.next {
+                if !self.in_flight.contains(&n) {
+                    self.free.insert(n);
+                }
+            }
+        }
+        self.chain_nonce = chain_nonce;
+        self.synced = true;
+        self.compact();
+        SyncReport { chain_nonce, external_advance, gaps: self.gaps() }
+    }
+
+    fn reserve(&mut self, count: u64) -> NonceLease {
+        // Reuse the lowest released run first so gaps get filled before new nonces are used.
+        if let Some(&lowest) = self.free.iter().next()
+            && (lowest..lowest + count).all(|n| self.free.contains(&n))
+        {
+            for n in lowest..lowest + count {
+                self.free.remove(&n);
+                self.in_flight.insert(n);
+            }
+            return NonceLease { start: lowest, count };
+        }
+        let lease = NonceLease { start: self.next, count };
+        for n in lease.nonces() {
+            self.in_flight.insert(n);
+        }
+        self.next += count;
+        lease
+    }
+}
+
+/// Per-account nonce allocator for concurrent bundle submission. Nonces are
+/// handed out atomically as contiguous leases; dropped bundles return their
+/// lease so it can be reused, and `sync` reconciles with the chain after
+/// inclusions, reorgs or transactions sent outside the manager.
+pub struct NonceManager {
+    rpc: String,
+    account: Address,
+    state: Mutex<NonceState>,
+}
+
+impl NonceManager {
+    pub fn new(rpc: String, account: Address) -> Self {
+        Self { rpc, account, state: Mutex::new(NonceState::default()) }
+    }
+
+    pub fn account(&self) -> Address {
+        self.account
+    }
+
+    /// Fetch `eth_getTransactionCount(account, pending)` and reconcile local state.
+    pub async fn sync(&self) -> Result<SyncReport> {
+        let provider = Provider::<Http>::try_from(self.rpc.as_str()).context("invalid rpc url")?;
+        let count = provider
+            .get_transaction_count(self.account, Some(BlockId::Number(BlockNumber::Pending)))
+            .await
+            .context("eth_getTransactionCount failed")?;
+        let report = self.state.lock().unwrap().apply_chain_nonce(count.as_u64());
+        if report.external_advance {
+            tracing::warn!(account = ?self.account, chain_nonce = report.chain_nonce, "nonce advanced outside the nonce manager");
+        }
+        if !report.gaps.is_empty() {
+            tracing::warn!(account = ?self.account, gaps = ?report.gaps, "nonce gaps detected");
+        }
+        Ok(report)
+    }
+
+    /// Reserve `count` consecutive nonces (one per bundle tx). Syncs from the node on first use.
+    pub async fn reserve(&self, count: u64) -> Result<NonceLease> {
+        let synced = self.state.lock().unwrap().synced;
+        if !synced {
+            self.sync().await?;
+        }
+        Ok(self.state.lock().unwrap().reserve(count))
+    }
+
+    /// Return a lease whose bundle was dropped or never submitted, so its nonces can be reused.
+    pub fn release(&self, lease: &NonceLease) {
+        let mut st = self.state.lock().unwrap();
+        for n in lease.nonces() {
+            if st.in_flight.remove(&n) {
+                st.free.insert(n);
+            }
+        }
+        st.compact();
+    }
+
+    /// Mark a lease as included on chain.
+    pub fn confirm(&self, lease: &NonceLease) {
+        let mut st = self.state.lock().unwrap();
+        for n in lease.nonces() {
+            st.in_flight.remove(&n);
+        }
+        st.chain_nonce = st.chain_nonce.max(lease.end());
+        st.free.retain(|n| *n >= lease.end());
+    }
+
+    /// Nonces currently handed out and not yet confirmed or released.
+    pub fn in_flight(&self) -> Vec<u64> {
+        self.state.lock().unwrap().in_flight.iter().copied().collect()
+    }
+
+    /// Released nonces that block higher in-flight nonces from being mined.
+    pub fn gaps(&self) -> Vec<u64> {
+        self.state.lock().unwrap().gaps()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn synced_at(chain_nonce: u64) -> NonceManager {
+        let m = NonceManager::new("http://127.0.0.1:8545".to_string(), Address::zero());
+        m.state.lock().unwrap().apply_chain_nonce(chain_nonce);
+        m
+    }
+
+    #[tokio::test]
+    async fn reserves_contiguous_leases_without_overlap() {
+        let m = synced_at(5);
+        let a = m.reserve(2).await.unwrap();
+        let b = m.reserve(3).await.unwrap();
+        assert_eq!(a, NonceLease { start: 5, count: 2 });
+        assert_eq!(b, NonceLease { start: 7, count: 3 });
+        assert_eq!(m.in_flight(), vec![5, 6, 7, 8, 9]);
+    }
+
+    #[tokio::test]
+    async fn released_leases_are_reused_and_gaps_reported() {
+        let m = synced_at(0);
+        let a = m.reserve(2).await.unwrap();
+        let _b = m.reserve(1).await.unwrap();
+        m.release(&a);
+        assert_eq!(m.gaps(), vec![0, 1]);
+        let c = m.reserve(2).await.unwrap();
+        assert_eq!(c, a);
+        assert!(m.gaps().is_empty());
+
+        // releasing the top lease rolls the counter back instead of leaving a gap
+        let d = m.reserve(1).await.unwrap();
+        assert_eq!(d.start, 3);
+        m.release(&d);
+        assert_eq!(m.reserve(1).await.unwrap().start, 3);
+    }
+
+    #[test]
+    fn resync_handles_external_txs_and_reorgs() {
+        let m = synced_at(0);
+        let mut st = m.state.lock().unwrap();
+        let lease = st.reserve(3);
+        assert_eq!(lease.start, 0);
+
+        // chain mined our first two txs and someone else sent two more
+        let r = st.apply_chain_nonce(5);
+        assert!(r.external_advance);
+        assert!(st.in_flight.is_empty());
+        assert_eq!(st.reserve(1).start, 5);
+
+        // reorg drops back to 4: nonce 4 is free again, 5 still in flight -> gap
+        let r = st.apply_chain_nonce(4);
+        assert!(!r.external_advance);
+        assert_eq!(r.gaps, vec![4]);
+        assert_eq!(st.reserve(1).start, 4);
+    }
+
+    #[tokio::test]
+    async fn sync_reads_pending_transaction_count() {
+        let server = httpmock::MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/")
+                .body_contains("eth_getTransactionCount")
+                .body_contains("pending");
+            then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"result":"0x2a"}"#);
+        });
+
+        let m = NonceManager::new(server.url("/"), Address::zero());
+        let lease = m.reserve(1).await.unwrap();
+        assert_eq!(lease.start, 42);
+        mock.assert();
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::Result;
+use ethers_core::types::U256;
+use crate::fees::base_fee_after;
+use crate::sim::{BalanceWatch, InclusionDelay, Scorer, SimulationBackend, SimulationOutcome};
+
+/// The bundle simulated for inclusion `delay_blocks` after the next block.
+#[derive(Debug, Clone)]
+pub struct DecayPoint {
+    pub delay_blocks: u64,
+    pub base_fee: U256,
+    /// `None` when the bundle could not be included at all, e.g. its fee cap is below
+    /// the base fee by then or a pending tx took its nonce
+    pub score: Option<i128>,
+    pub outcome: Option<SimulationOutcome>,
+    pub error: Option<String>,
+}
+
+/// How a bundle's score falls off as inclusion is delayed block by block.
+#[derive(Debug, Clone, Default)]
+pub struct ProfitDecay {
+    pub points: Vec<DecayPoint>,
+}
+
+/// Delays and fee path a `ProfitDecay` is measured over.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecayConfig {
+    /// Base fee of the next block; later blocks follow `utilization_bps`
+    pub next_base_fee: U256,
+    /// Share of the gas limit each block in between is assumed to use (5_000 = at target)
+    pub utilization_bps: u32,
+    pub max_delay: u64,
+    /// Known pending txs, replayed before the bundle at every delay
+    pub pending: Vec<Vec<u8>>,
+}
+
+impl DecayConfig {
+    pub fn new(next_base_fee: U256, max_delay: u64) -> Self {
+        Self { next_base_fee, utilization_bps: 5_000, max_delay, pending: Vec::new() }
+    }
+
+    pub fn with_utilization_bps(mut self, bps: u32) -> Self {
+        self.utilization_bps = bps;
+        self
+    }
+
+    pub fn with_pending(mut self, pending: Vec<Vec<u8>>) -> Self {
+        self.pending = pending;
+        self
+    }
+}
+
+impl ProfitDecay {
+    /// Simulate `signed_raw_txs` for inclusion 0..=`max_delay` blocks after the next block.
+    pub async fn measure<B, C>(backend: &B, scorer: &C, signed_raw_txs: &[Vec<u8>], watch: &BalanceWatch, config: &DecayConfig) -> Result<Self>
+    where
+        B: SimulationBackend + ?Sized,
+        C: Scorer + ?Sized,
+    {
+        let mut points = Vec::new();
+        for delay_blocks in 0..=config.max_delay {
+            let base_fee = base_fee_after(config.next_base_fee, delay_blocks.min(u32::MAX as u64) as u32, config.utilization_bps);
+            let delay = InclusionDelay { blocks: delay_blocks, base_fee: Some(base_fee), pending: config.pending.clone() };
+            let point = match backend.simulate_bundle_delayed(signed_raw_txs, &delay, watch).await {
+                Ok(outcome) => {
+                    DecayPoint { delay_blocks, base_fee, score: Some(scorer.score_outcome(&outcome, signed_raw_txs)), outcome: Some(outcome), error: None }
+                }
+                Err(e) => DecayPoint { delay_blocks, base_fee, score: None, outcome: None, error: Some(format!("{:#}", e)) },
+            };
+            points.push(point);
+        }
+        Ok(Self { points })
+    }
+
+    /// Score lost at `delay_blocks` relative to inclusion in the next block.
+    pub fn loss_at(&self, delay_blocks: u64) -> Option<i128> {
+        let first = self.points.first()?.score?;
+        let later = self.points.iter().find(|p| p.delay_blocks == delay_blocks)?.score?;
+        Some(first.saturating_sub(later))
+    }
+
+    /// Largest delay, counted without gaps from the next block, at which the bundle still scores above zero.
+    pub fn last_profitable_delay(&self) -> Option<u64> {
+        self.points.iter().take_while(|p| p.score.is_some_and(|s| s > 0)).last().map(|p| p.delay_blocks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::revm_backend::fixtures::{funded_db, signer, FixedProfit};
+    use crate::revm_backend::{RevmBackend, SimBlock};
+    use crate::signer::Signer;
+    use crate::tx::build_eip1559_tx;
+    use ethers_core::types::{Address, Bytes};
+
+    #[tokio::test]
+    async fn profit_decays_with_base_fee_and_pending_txs_apply_first() {
+        let signer = signer();
+        let from = signer.address().await.unwrap().unwrap();
+        let db = funded_db(&signer, U256::exp10(18)).await;
+        let gwei = U256::exp10(9);
+        let block = SimBlock { number: 100, timestamp: 1_000, base_fee: gwei, ..Default::default() };
+        let backend = RevmBackend::new(db, 1, block);
+        let tx = |nonce: u64| build_eip1559_tx(nonce.into(), Address::repeat_byte(0x77), U256::zero(), Bytes::default(), U256::from(21_000u64), U256::zero(), gwei * 13 / 10, 1);
+        let signed = vec![signer.sign_typed_transaction(&tx(0)).await.unwrap()];
+
+        // "profit" of 30_000 gwei, less the base fee burned for 21k gas
+        let fixed = FixedProfit(30_000_000_000_000);
+        let watch = BalanceWatch::new(vec![from]);
+        let decay = ProfitDecay::measure(&backend, &fixed, &signed, &watch, &DecayConfig::new(gwei, 3).with_utilization_bps(10_000)).await.unwrap();
+        let fees: Vec<U256> = decay.points.iter().map(|p| p.base_fee).collect();
+        assert_eq!(fees, vec![gwei, gwei * 9 / 8, gwei * 81 / 64, gwei * 729 / 512]);
+        assert_eq!(decay.points[0].score, Some(30_000_000_000_000 - 21_000_000_000_000));
+        assert_eq!(decay.loss_at(1), Some(21_000 * (gwei / 8).as_u128() as i128));
+        let receipt = &decay.points[2].outcome.as_ref().unwrap().receipts[0];
+        assert_eq!(receipt.block_number, Some(102u64.into()));
+        // the 1.3 gwei fee cap is below the base fee three blocks later
+        assert!(decay.points[3].score.is_none() && decay.points[3].error.is_some());
+        assert_eq!(decay.last_profitable_delay(), Some(2));
+
+        // a pending tx with our nonce lands first, so the bundle can no longer be included
+        let pending = vec![signer.sign_typed_transaction(&tx(0)).await.unwrap()];
+        let decay = ProfitDecay::measure(&backend, &fixed, &signed, &watch, &DecayConfig::new(gwei, 0).with_pending(pending)).await.unwrap();
+        assert!(decay.points[0].error.as_deref().unwrap().contains("tx 0 rejected"));
+    }
+}
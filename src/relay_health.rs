@@ -0,0 +1,214 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// When a breaker trips and how it recovers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failures that open the circuit
+    pub failure_threshold: u32,
+    /// How long an open circuit rejects calls before letting a probe through
+    pub open_for: Duration,
+    /// Concurrent probe calls allowed while half-open
+    pub half_open_probes: u32,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self { failure_threshold: 3, open_for: Duration::from_secs(30), half_open_probes: 1 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    /// Calls flow normally
+    Closed,
+    /// Calls are rejected until `until`
+    Open { until: Instant },
+    /// A limited number of probe calls decide whether to close or re-open
+    HalfOpen,
+}
+
+impl BreakerState {
+    /// Numeric encoding for metrics: 0 closed, 1 half-open, 2 open.
+    pub fn as_gauge(&self) -> f64 {
+        match self {
+            BreakerState::Closed => 0.0,
+            BreakerState::HalfOpen => 1.0,
+            BreakerState::Open { .. } => 2.0,
+        }
+    }
+}
+
+/// Per-endpoint circuit breaker. Time is passed in so callers and tests control it.
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    state: BreakerState,
+    consecutive_failures: u32,
+    probes_in_flight: u32,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self { config, state: BreakerState::Closed, consecutive_failures: 0, probes_in_flight: 0 }
+    }
+
+    /// Current state, moving an expired open circuit to half-open.
+    pub fn state(&mut self, now: Instant) -> BreakerState {
+        if let BreakerState::Open { until } = self.state
+            && now >= until
+        {
+            self.state = BreakerState::HalfOpen;
+            self.probes_in_flight = 0;
+        }
+        self.state
+    }
+
+    /// Whether a call may go out now. In half-open state this reserves a probe slot.
+    pub fn allow(&mut self, now: Instant) -> bool {
+        match self.state(now) {
+            BreakerState::Closed => true,
+            BreakerState::Open { .. } => false,
+            BreakerState::HalfOpen => {
+                if self.probes_in_flight < self.config.half_open_probes {
+                    self.probes_in_flight += 1;
+                    true
+                } else {
+                    false
+                }
+            }
+        }
+    }
+
+    /// Whether `allow` would let a call through now, without reserving a probe slot.
+    pub fn available(&mut self, now: Instant) -> bool {
+        match self.state(now) {
+            BreakerState::Closed => true,
+            BreakerState::Open { .. } => false,
+            BreakerState::HalfOpen => self.probes_in_flight < self.config.half_open_probes,
+        }
+    }
+
+    /// Give back a probe slot reserved by `allow` whose call never reported an outcome.
+    pub fn release_probe(&mut self) {
+        if self.state == BreakerState::HalfOpen {
+            self.probes_in_flight = self.probes_in_flight.saturating_sub(1);
+        }
+    }
+
+    pub fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+        self.probes_in_flight = 0;
+        self.state = BreakerState::Closed;
+    }
+
+    pub fn record_failure(&mut self, now: Instant) {
+        self.consecutive_failures += 1;
+        let trip = matches!(self.state, BreakerState::HalfOpen) || self.consecutive_failures >= self.config.failure_threshold;
+        if trip {
+            self.state = BreakerState::Open { until: now + self.config.open_for };
+            self.probes_in_flight = 0;
+        }
+    }
+}
+
+/// Rolling window of the most recent latencies.
+#[derive(Debug, Clone)]
+pub struct LatencyWindow {
+    samples: VecDeque<Duration>,
+    capacity: usize,
+}
+
+impl LatencyWindow {
+    pub fn new(capacity: usize) -> Self {
+        Self { samples: VecDeque::with_capacity(capacity), capacity: capacity.max(1) }
+    }
+
+    pub fn record(&mut self, latency: Duration) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(latency);
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Nearest-rank percentile (`p` in 0..=100) of the window.
+    pub fn percentile(&self, p: f64) -> Option<Duration> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<Duration> = self.samples.iter().copied().collect();
+        sorted.sort();
+        let rank = ((p.clamp(0.0, 100.0) / 100.0) * sorted.len() as f64).ceil() as usize;
+        Some(sorted[rank.saturating_sub(1).min(sorted.len() - 1)])
+    }
+}
+
+/// Breaker plus latency window for one endpoint.
+#[derive(Debug, Clone)]
+pub struct RelayHealth {
+    pub breaker: CircuitBreaker,
+    pub latency: LatencyWindow,
+}
+
+/// Latency samples kept per endpoint.
+pub const LATENCY_WINDOW: usize = 128;
+
+impl RelayHealth {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self { breaker: CircuitBreaker::new(config), latency: LatencyWindow::new(LATENCY_WINDOW) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn breaker_opens_probes_and_recovers() {
+        let config = CircuitBreakerConfig { failure_threshold: 2, open_for: Duration::from_secs(10), half_open_probes: 1 };
+        let mut b = CircuitBreaker::new(config);
+        let t0 = Instant::now();
+        b.record_failure(t0);
+        assert!(b.allow(t0));
+        b.record_failure(t0);
+        assert!(!b.allow(t0 + Duration::from_secs(5)));
+
+        // after the open period a single probe goes through
+        let t1 = t0 + Duration::from_secs(10);
+        assert_eq!(b.state(t1), BreakerState::HalfOpen);
+        assert!(b.available(t1) && b.available(t1));
+        assert!(b.allow(t1));
+        assert!(!b.available(t1) && !b.allow(t1));
+        b.release_probe();
+        assert!(b.allow(t1));
+
+        // a failed probe re-opens immediately; a successful one closes
+        b.record_failure(t1);
+        assert!(matches!(b.state(t1), BreakerState::Open { .. }));
+        let t2 = t1 + Duration::from_secs(10);
+        assert!(b.allow(t2));
+        b.record_success();
+        assert_eq!(b.state(t2), BreakerState::Closed);
+    }
+
+    #[test]
+    fn latency_percentiles_use_recent_samples() {
+        let mut w = LatencyWindow::new(4);
+        assert_eq!(w.percentile(50.0), None);
+        for ms in [100, 10, 20, 30, 40] {
+            w.record(Duration::from_millis(ms));
+        }
+        // the 100ms sample was evicted
+        assert_eq!(w.len(), 4);
+        assert_eq!(w.percentile(50.0), Some(Duration::from_millis(20)));
+        assert_eq!(w.percentile(99.0), Some(Duration::from_millis(40)));
+    }
+}
@@ -0,0 +1,558 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use ethers_core::types::H256;
+use crate::auth::FlashbotsAuth;
+use crate::signer::BasicEnvSigner;
+use crate::relay_health::{BreakerState, CircuitBreakerConfig, RelayHealth};
+use crate::executor::{BundleOptions, BundleRelay, BundleStats, RelayClient, RelayDialect, RelayError, SendBundleResponse};
+
+/// Default per-relay deadline for one submission.
+pub const DEFAULT_RELAY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// One relay or builder endpoint in a `RelaySet`.
+pub struct RelayEndpoint {
+    pub name: String,
+    pub dialect: RelayDialect,
+    pub timeout: Duration,
+    client: RelayClient,
+}
+
+impl RelayEndpoint {
+    pub fn new(name: impl Into<String>, url: String, dialect: RelayDialect, timeout: Duration) -> Result<Self> {
+        let client = RelayClient::with_url_and_timeout(url, timeout)?;
+        Ok(Self { name: name.into(), dialect, timeout, client })
+    }
+
+    /// Sign requests to this endpoint with `auth`. Relays that score searchers by
+    /// reputation key it on this address, so each endpoint may use its own key.
+    pub fn with_auth(mut self, auth: Arc<FlashbotsAuth>) -> Self {
+        self.client = self.client.with_auth(auth);
+        self
+    }
+
+    pub fn url(&self) -> Option<&str> {
+        self.client.relay_url()
+    }
+}
+
+/// Outcome of submitting a bundle to one endpoint.
+#[derive(Debug)]
+pub struct RelaySubmission {
+    pub relay: String,
+    pub latency: Duration,
+    pub result: Result<SendBundleResponse, RelayError>,
+}
+
+impl RelaySubmission {
+    pub fn accepted(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+/// Running acceptance and latency counters for one endpoint.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RelayStats {
+    pub submissions: u64,
+    pub accepted: u64,
+    pub failures: u64,
+    pub timeouts: u64,
+    /// Submissions not sent because the endpoint's circuit was open
+    pub skipped: u64,
+    pub last_latency: Duration,
+    pub total_latency: Duration,
+}
+
+impl RelayStats {
+    pub fn mean_latency(&self) -> Duration {
+        if self.submissions == 0 {
+            return Duration::ZERO;
+        }
+        self.total_latency / self.submissions as u32
+    }
+
+    fn record(&mut self, submission: &RelaySubmission) {
+        if matches!(submission.result, Err(RelayError::CircuitOpen)) {
+            self.skipped += 1;
+            return;
+        }
+        self.submissions += 1;
+        self.last_latency = submission.latency;
+        self.total_latency += submission.latency;
+        match &submission.result {
+            Ok(_) => self.accepted += 1,
+            Err(e) if e.is_timeout() => {
+                self.failures += 1;
+                self.timeouts += 1;
+            }
+            Err(_) => self.failures += 1,
+        }
+    }
+}
+
+/// Submits each bundle to several relays and builders in parallel. Every endpoint
+/// has its own auth key, timeout and dialect; a slow or failing endpoint only
+/// affects its own entry in the results.
+///
+/// Each endpoint also has a circuit breaker: after repeated transport failures,
+/// timeouts or 5xx answers it is skipped until a probe succeeds. Healthy endpoints
+/// are tried in order of median latency wherever order matters.
+#[derive(Default)]
+pub struct RelaySet {
+    endpoints: Vec<RelayEndpoint>,
+    breaker_config: CircuitBreakerConfig,
+    stats: Mutex<HashMap<String, RelayStats>>,
+    health: Mutex<HashMap<String, RelayHealth>>,
+}
+
+impl RelaySet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_endpoint(mut self, endpoint: RelayEndpoint) -> Self {
+        self.push(endpoint);
+        self
+    }
+
+    pub fn with_breaker_config(mut self, config: CircuitBreakerConfig) -> Self {
+        self.breaker_config = config;
+        self
+    }
+
+    pub fn push(&mut self, endpoint: RelayEndpoint) {
+        self.endpoints.push(endpoint);
+    }
+
+    /// Build from `RELAY_URLS`, comma-separated `name=dialect:url` entries where the
+    /// name and the `flashbots:`/`builder:` prefix are optional (entries without a
+    /// prefix use the lenient builder dialect). Falls back to `FLASHBOTS_RELAY_URL`
+    /// as a single Flashbots-dialect endpoint. Per endpoint, `RELAY_<NAME>_TIMEOUT_MS`
+    /// overrides `DEFAULT_RELAY_TIMEOUT` and `RELAY_<NAME>_AUTH_KEY` signs its requests
+    /// with its own key; otherwise `auth`, if given, is used.
+    pub fn from_env(auth: Option<Arc<FlashbotsAuth>>) -> Result<Self> {
+        Self::from_vars(|key| std::env::var(key).ok(), auth)
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>, auth: Option<Arc<FlashbotsAuth>>) -> Result<Self> {
+        let (urls, default_dialect) = match var("RELAY_URLS") {
+            Some(urls) => (urls, RelayDialect::Builder),
+            None => (var("FLASHBOTS_RELAY_URL").unwrap_or_default(), RelayDialect::Flashbots),
+        };
+        let mut set = Self::new();
+        for entry in urls.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (name, spec) = match entry.split_once('=') {
+                Some((name, spec)) => (name.trim(), spec.trim()),
+                None => (entry, entry),
+            };
+            let (dialect, url) = match spec.split_once(':') {
+                Some((prefix, url)) if prefix.eq_ignore_ascii_case("flashbots") => (RelayDialect::Flashbots, url),
+                Some((prefix, url)) if prefix.eq_ignore_ascii_case("builder") => (RelayDialect::Builder, url),
+                _ => (default_dialect, spec),
+            };
+            let name = if name == spec { url } else { name };
+            let prefix = format!("RELAY_{}_", env_key(name));
+            let timeout = match var(&format!("{}TIMEOUT_MS", prefix)) {
+                Some(ms) => Duration::from_millis(ms.trim().parse().map_err(|e| anyhow::anyhow!("{}TIMEOUT_MS: {}", prefix, e))?),
+                None => DEFAULT_RELAY_TIMEOUT,
+            };
+            let endpoint_auth = match var(&format!("{}AUTH_KEY", prefix)) {
+                Some(key) => Some(Arc::new(FlashbotsAuth::from_signer(Arc::new(BasicEnvSigner::from_secret(key))))),
+                None => auth.clone(),
+            };
+            let mut endpoint = RelayEndpoint::new(name, url.to_string(), dialect, timeout)?;
+            if let Some(auth) = endpoint_auth {
+                endpoint = endpoint.with_auth(auth);
+            }
+            set.push(endpoint);
+        }
+        Ok(set)
+    }
+
+    pub fn endpoints(&self) -> &[RelayEndpoint] {
+        &self.endpoints
+    }
+
+    /// Split `candidates` into endpoints to call, ordered by health (closed circuits
+    /// by p50 latency, then half-open probes), and endpoints skipped because their
+    /// circuit is open. Probe slots are only reserved by `guarded`, once a call goes out.
+    fn route<'a>(&self, candidates: impl Iterator<Item = &'a RelayEndpoint>) -> (Vec<&'a RelayEndpoint>, Vec<&'a RelayEndpoint>) {
+        let now = Instant::now();
+        let mut health = self.health.lock().unwrap();
+        let mut allowed = Vec::new();
+        let mut skipped = Vec::new();
+        for ep in candidates {
+            let h = health.entry(ep.name.clone()).or_insert_with(|| RelayHealth::new(self.breaker_config));
+            let probing = h.breaker.state(now) == BreakerState::HalfOpen;
+            if h.breaker.available(now) {
+                // Endpoints without samples sort first so they get measured.
+                let p50 = h.latency.percentile(50.0).unwrap_or(Duration::ZERO);
+                allowed.push((probing, p50, ep));
+            } else {
+                skipped.push(ep);
+            }
+        }
+        allowed.sort_by_key(|(probing, p50, _)| (*probing, *p50));
+        (allowed.into_iter().map(|(_, _, ep)| ep).collect(), skipped)
+    }
+
+    /// Reserve a call on `ep`'s breaker (a probe slot when half-open), then `observed`.
+    /// Fails with `CircuitOpen` if the circuit no longer allows the call.
+    async fn guarded<T>(&self, ep: &RelayEndpoint, call: impl std::future::Future<Output = Result<T, RelayError>>) -> (Duration, Result<T, RelayError>) {
+        let allowed = {
+            let mut health = self.health.lock().unwrap();
+            let h = health.entry(ep.name.clone()).or_insert_with(|| RelayHealth::new(self.breaker_config));
+            h.breaker.allow(Instant::now())
+        };
+        if !allowed {
+            return (Duration::ZERO, Err(RelayError::CircuitOpen));
+        }
+        let mut probe = ProbeSlot { health: &self.health, name: &ep.name, armed: true };
+        let out = self.observed(ep, call).await;
+        probe.armed = false;
+        out
+    }
+
+    /// Run `call` against `ep` under its timeout and feed the outcome to its breaker.
+    async fn observed<T>(&self, ep: &RelayEndpoint, call: impl std::future::Future<Output = Result<T, RelayError>>) -> (Duration, Result<T, RelayError>) {
+        let started = Instant::now();
+        // The reqwest timeout covers the request itself; the outer one also bounds request signing.
+        let result = match tokio::time::timeout(ep.timeout, call).await {
+            Ok(r) => r,
+            Err(_) => Err(RelayError::Timeout(ep.timeout)),
+        };
+        let latency = started.elapsed();
+        {
+            let mut health = self.health.lock().unwrap();
+            let h = health.entry(ep.name.clone()).or_insert_with(|| RelayHealth::new(self.breaker_config));
+            match &result {
+                // Answered, even if with a rejection: the endpoint itself is healthy.
+                Err(e) if e.is_retryable() => h.breaker.record_failure(Instant::now()),
+                _ => {
+                    h.breaker.record_success();
+                    h.latency.record(latency);
+                }
+            }
+            #[cfg(feature = "with-metrics")]
+            {
+                let state = h.breaker.state(Instant::now());
+                metrics::gauge!("relay.breaker_state", "relay" => ep.name.clone()).set(state.as_gauge());
+                for (label, p) in [("p50", 50.0), ("p95", 95.0), ("p99", 99.0)] {
+                    if let Some(v) = h.latency.percentile(p) {
+                        metrics::gauge!("relay.latency_percentile_seconds", "relay" => ep.name.clone(), "quantile" => label).set(v.as_secs_f64());
+                    }
+                }
+            }
+        }
+        (latency, result)
+    }
+
+    /// Submit to every endpoint whose circuit allows it, concurrently, and wait
+    /// for all of them (each bounded by its own timeout). Results come back in
+    /// routing order, followed by skipped endpoints with `RelayError::CircuitOpen`.
+    pub async fn submit_all(&self, signed_txs: &[Vec<u8>], block_number: Option<u64>, options: &BundleOptions) -> Vec<RelaySubmission> {
+        let (allowed, skipped) = self.route(self.endpoints.iter());
+        let futures = allowed.iter().map(|ep| async move {
+            let (latency, result) = self.guarded(ep, ep.client.submit_bundle_with_options(signed_txs, block_number, ep.dialect, options)).await;
+            RelaySubmission { relay: ep.name.clone(), latency, result }
+        });
+        let mut submissions = futures_util::future::join_all(futures).await;
+        submissions.extend(skipped.iter().map(|ep| RelaySubmission {
+            relay: ep.name.clone(),
+            latency: Duration::ZERO,
+            result: Err(RelayError::CircuitOpen),
+        }));
+
+        let mut stats = self.stats.lock().unwrap();
+        for s in submissions.iter() {
+            stats.entry(s.relay.clone()).or_default().record(s);
+            match &s.result {
+                Ok(resp) => tracing::info!(relay = %s.relay, latency_ms = s.latency.as_millis() as u64, bundle_hash = ?resp.bundle_hash, "bundle accepted"),
+                Err(RelayError::CircuitOpen) => tracing::debug!(relay = %s.relay, "relay skipped: circuit open"),
+                Err(e) => tracing::warn!(relay = %s.relay, latency_ms = s.latency.as_millis() as u64, error = %e, "bundle rejected"),
+            }
+            #[cfg(feature = "with-metrics")]
+            {
+                let outcome = match &s.result {
+                    Ok(_) => "accepted",
+                    Err(RelayError::CircuitOpen) => "skipped",
+                    Err(_) => "failed",
+                };
+                metrics::counter!("relay.submissions", "relay" => s.relay.clone(), "outcome" => outcome).increment(1);
+                if !matches!(s.result, Err(RelayError::CircuitOpen)) {
+                    metrics::histogram!("relay.latency_seconds", "relay" => s.relay.clone()).record(s.latency.as_secs_f64());
+                }
+            }
+        }
+        submissions
+    }
+
+    /// Snapshot of per-endpoint counters, keyed by endpoint name.
+    pub fn stats(&self) -> HashMap<String, RelayStats> {
+        self.stats.lock().unwrap().clone()
+    }
+
+    /// Snapshot of per-endpoint breaker state and latency window.
+    pub fn health(&self) -> HashMap<String, RelayHealth> {
+        self.health.lock().unwrap().clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl BundleRelay for RelaySet {
+    /// Fan out to all endpoints; succeeds if at least one accepted the bundle.
+    async fn send_bundle_with_options(&self, signed_txs: &[Vec<u8>], block_number: Option<u64>, options: &BundleOptions) -> Result<SendBundleResponse, RelayError> {
+        let mut last_err = RelayError::NotConfigured;
+        for s in self.submit_all(signed_txs, block_number, options).await {
+            match s.result {
+                Ok(resp) => return Ok(resp),
+                Err(e) => last_err = e,
+            }
+        }
+        Err(last_err)
+    }
+
+    /// Cancel on every endpoint; succeeds if at least one endpoint confirmed.
+    async fn cancel_bundle(&self, replacement_uuid: &str) -> Result<(), RelayError> {
+        // Cancellation ignores open circuits: a stale bundle is worse than a wasted request.
+        let futures = self.endpoints.iter().map(|ep| async move {
+            (ep, self.observed(ep, ep.client.cancel_bundle(replacement_uuid)).await.1)
+        });
+        let mut cancelled = false;
+        let mut last_err = RelayError::NotConfigured;
+        for (ep, result) in futures_util::future::join_all(futures).await {
+            match result {
+                Ok(()) => cancelled = true,
+                Err(e) => {
+                    tracing::warn!(relay = %ep.name, error = %e, "bundle cancellation failed");
+                    last_err = e;
+                }
+            }
+        }
+        if cancelled { Ok(()) } else { Err(last_err) }
+    }
+
+    /// Send to every healthy Flashbots-dialect endpoint; succeeds if any accepted.
+    async fn send_private_transaction(&self, raw_tx: &[u8], max_block_number: Option<u64>, fast: bool) -> Result<H256, RelayError> {
+        let (allowed, skipped) = self.route(self.endpoints.iter().filter(|ep| ep.dialect == RelayDialect::Flashbots));
+        let futures = allowed.iter().map(|ep| async move {
+            self.guarded(ep, ep.client.send_private_transaction(raw_tx, max_block_number, fast)).await.1
+        });
+        let mut last_err = if skipped.is_empty() { RelayError::NotConfigured } else { RelayError::CircuitOpen };
+        for result in futures_util::future::join_all(futures).await {
+            match result {
+                Ok(hash) => return Ok(hash),
+                Err(e) => last_err = e,
+            }
+        }
+        Err(last_err)
+    }
+
+    /// Ask healthy Flashbots-dialect endpoints, fastest first; builders do not expose bundle stats.
+    async fn bundle_stats(&self, bundle_hash: H256, block_number: u64) -> Result<BundleStats, RelayError> {
+        let (allowed, skipped) = self.route(self.endpoints.iter().filter(|ep| ep.dialect == RelayDialect::Flashbots));
+        let mut last_err = if skipped.is_empty() { RelayError::NotConfigured } else { RelayError::CircuitOpen };
+        for ep in allowed {
+            match self.guarded(ep, ep.client.get_bundle_stats(bundle_hash, block_number)).await.1 {
+                Ok(stats) => return Ok(stats),
+                Err(e) => last_err = e,
+            }
+        }
+        Err(last_err)
+    }
+}
+
+/// Releases the breaker reservation made by `guarded` if its call is dropped before
+/// reporting an outcome, so a half-open endpoint is not left without probe slots.
+struct ProbeSlot<'a> {
+    health: &'a Mutex<HashMap<String, RelayHealth>>,
+    name: &'a str,
+    armed: bool,
+}
+
+impl Drop for ProbeSlot<'_> {
+    fn drop(&mut self) {
+        if self.armed
+            && let Some(h) = self.health.lock().unwrap().get_mut(self.name)
+        {
+            h.breaker.release_probe();
+        }
+    }
+}
+
+/// `RELAY_<NAME>_*` form of an endpoint name: upper-case, other characters as `_`.
+fn env_key(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::compute_bundle_hash;
+
+    const SECRET: &str = "0123456789012345678901234567890123456789012345678901234567890123";
+    const BUNDLE_HASH: &str = "0x2228f5d8954ce31dc1601a8ba264dbd401bf1428388ce88238932815c5d6f23f";
+
+    #[tokio::test]
+    async fn slow_relay_does_not_delay_the_others() {
+        let fast = httpmock::MockServer::start();
+        let slow = httpmock::MockServer::start();
+        fast.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/").body_contains("eth_sendBundle");
+            then.status(200).body(format!(r#"{{"jsonrpc":"2.0","id":1,"result":{{"bundleHash":"{}"}}}}"#, BUNDLE_HASH));
+        });
+        slow.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/");
+            then.status(200).delay(Duration::from_secs(5)).body(r#"{"jsonrpc":"2.0","id":1,"result":null}"#);
+        });
+
+        let set = RelaySet::new()
+            .with_endpoint(RelayEndpoint::new("fast", fast.url("/"), RelayDialect::Flashbots, Duration::from_secs(2)).unwrap())
+            .with_endpoint(RelayEndpoint::new("slow", slow.url("/"), RelayDialect::Builder, Duration::from_millis(200)).unwrap());
+
+        let started = Instant::now();
+        let results = set.submit_all(&[vec![0x01]], Some(1), &BundleOptions::default()).await;
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!(results[0].accepted());
+        assert!(!results[1].accepted());
+
+        let resp = set.send_bundle(&[vec![0x01]], Some(1)).await.unwrap();
+        assert_eq!(resp.bundle_hash, BUNDLE_HASH.parse().unwrap());
+
+        let stats = set.stats();
+        assert_eq!(stats["fast"].submissions, 2);
+        assert_eq!(stats["fast"].accepted, 2);
+        assert_eq!(stats["slow"].failures, 2);
+        assert_eq!(stats["slow"].timeouts, 2);
+    }
+
+    #[tokio::test]
+    async fn open_circuit_skips_failing_relay() {
+        let up = httpmock::MockServer::start();
+        let down = httpmock::MockServer::start();
+        up.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/");
+            then.status(200).body(format!(r#"{{"jsonrpc":"2.0","id":1,"result":{{"bundleHash":"{}"}}}}"#, BUNDLE_HASH));
+        });
+        let down_mock = down.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/");
+            then.status(503).body("unavailable");
+        });
+
+        let breaker = CircuitBreakerConfig { failure_threshold: 2, open_for: Duration::from_secs(60), half_open_probes: 1 };
+        let set = RelaySet::new()
+            .with_breaker_config(breaker)
+            .with_endpoint(RelayEndpoint::new("down", down.url("/"), RelayDialect::Flashbots, DEFAULT_RELAY_TIMEOUT).unwrap())
+            .with_endpoint(RelayEndpoint::new("up", up.url("/"), RelayDialect::Flashbots, DEFAULT_RELAY_TIMEOUT).unwrap());
+
+        for _ in 0..3 {
+            assert!(set.send_bundle(&[vec![0x01]], Some(1)).await.is_ok());
+        }
+        down_mock.assert_hits(2);
+        let results = set.submit_all(&[vec![0x01]], Some(1), &BundleOptions::default()).await;
+        assert_eq!(results[0].relay, "up");
+        assert!(matches!(results[1].result, Err(RelayError::CircuitOpen)));
+
+        let stats = set.stats();
+        assert_eq!(stats["down"].failures, 2);
+        assert_eq!(stats["down"].skipped, 2);
+        let mut health = set.health();
+        assert!(matches!(health.get_mut("down").unwrap().breaker.state(Instant::now()), BreakerState::Open { .. }));
+        assert_eq!(health["up"].latency.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn unused_probe_slots_stay_free_for_later_calls() {
+        let first = httpmock::MockServer::start();
+        let second = httpmock::MockServer::start();
+        fn stats(server: &httpmock::MockServer, status: u16) -> httpmock::Mock<'_> {
+            server.mock(|when, then| {
+                when.method(httpmock::Method::POST).path("/").body_contains("flashbots_getBundleStatsV2");
+                then.status(status).body(r#"{"jsonrpc":"2.0","id":1,"result":{"isSimulated":true}}"#);
+            })
+        }
+        let mut failing = [stats(&first, 503), stats(&second, 503)];
+
+        let breaker = CircuitBreakerConfig { failure_threshold: 1, open_for: Duration::from_millis(50), half_open_probes: 1 };
+        let set = RelaySet::new()
+            .with_breaker_config(breaker)
+            .with_endpoint(RelayEndpoint::new("first", first.url("/"), RelayDialect::Flashbots, DEFAULT_RELAY_TIMEOUT).unwrap())
+            .with_endpoint(RelayEndpoint::new("second", second.url("/"), RelayDialect::Flashbots, DEFAULT_RELAY_TIMEOUT).unwrap());
+        let hash = H256::repeat_byte(1);
+        assert!(set.bundle_stats(hash, 1).await.is_err());
+        for mock in failing.iter_mut() {
+            mock.delete();
+        }
+
+        // both circuits are half-open; the first probe answers, so the second is never called
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        let mut first_ok = stats(&first, 200);
+        let second_ok = stats(&second, 200);
+        assert!(set.bundle_stats(hash, 1).await.unwrap().is_simulated);
+        assert_eq!(second_ok.hits(), 0);
+
+        // once the first endpoint fails, the second still gets its probe
+        first_ok.delete();
+        stats(&first, 503);
+        assert!(set.bundle_stats(hash, 1).await.unwrap().is_simulated);
+        second_ok.assert();
+    }
+
+    #[tokio::test]
+    async fn builder_dialect_accepts_null_result() {
+        let server = httpmock::MockServer::start();
+        server.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/");
+            then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"result":null}"#);
+        });
+        let set = RelaySet::new()
+            .with_endpoint(RelayEndpoint::new("builder", server.url("/"), RelayDialect::Builder, DEFAULT_RELAY_TIMEOUT).unwrap());
+
+        let signed = vec![vec![0x02, 0x03]];
+        let resp = set.send_bundle(&signed, None).await.unwrap();
+        assert_eq!(resp.bundle_hash, compute_bundle_hash(&signed));
+    }
+
+    #[tokio::test]
+    async fn from_vars_reads_dialect_timeout_and_auth_per_endpoint() {
+        let fb = httpmock::MockServer::start();
+        let builder = httpmock::MockServer::start();
+        let signed_fb = fb.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/").header_exists(crate::auth::FLASHBOTS_SIGNATURE_HEADER);
+            then.status(200).body(format!(r#"{{"jsonrpc":"2.0","id":1,"result":{{"bundleHash":"{}"}}}}"#, BUNDLE_HASH));
+        });
+        builder.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/");
+            then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"result":null}"#);
+        });
+        let vars: HashMap<String, String> = [
+            ("RELAY_URLS".to_string(), format!("fb=flashbots:{}, my-builder={}", fb.url("/"), builder.url("/"))),
+            ("RELAY_FB_AUTH_KEY".to_string(), SECRET.to_string()),
+            ("RELAY_MY_BUILDER_TIMEOUT_MS".to_string(), "750".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        let set = RelaySet::from_vars(|key| vars.get(key).cloned(), None).unwrap();
+
+        let eps = set.endpoints();
+        assert_eq!((eps[0].name.as_str(), eps[0].dialect, eps[0].timeout), ("fb", RelayDialect::Flashbots, DEFAULT_RELAY_TIMEOUT));
+        assert_eq!((eps[1].name.as_str(), eps[1].dialect, eps[1].timeout), ("my-builder", RelayDialect::Builder, Duration::from_millis(750)));
+        assert_eq!(eps[0].url(), Some(fb.url("/").as_str()));
+        let results = set.submit_all(&[vec![0x01]], Some(1), &BundleOptions::default()).await;
+        assert!(results.iter().all(RelaySubmission::accepted));
+        signed_fb.assert();
+
+        let fallback = format!("flashbots:{}", fb.url("/"));
+        let set = RelaySet::from_vars(|key| (key == "FLASHBOTS_RELAY_URL").then(|| fallback.clone()), None).unwrap();
+        assert_eq!(set.endpoints()[0].dialect, RelayDialect::Flashbots);
+        let bad: HashMap<&str, &str> = [("RELAY_URLS", "a=builder:http://x"), ("RELAY_A_TIMEOUT_MS", "soon")].into_iter().collect();
+        assert!(RelaySet::from_vars(|key| bad.get(key).map(|v| v.to_string()), None).is_err());
+    }
+
+    #[tokio::test]
+    async fn empty_set_is_not_configured() {
+        let set = RelaySet::new();
+        assert!(matches!(set.send_bundle(&[vec![0x01]], None).await, Err(RelayError::NotConfigured)));
+    }
+}
@@ -0,0 +1,271 @@
+use anyhow::{Result, Context};
+use ethers_core::types::H256;
+use ethers_providers::{Provider, Http, Middleware, StreamExt};
+use std::collections::BTreeSet;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use crate::executor::{BundleOptions, BundleRelay, MissReason};
+
+/// Expected seconds between blocks, used to predict the timestamp of future blocks.
+pub const SLOT_SECS: u64 = 12;
+
+/// A new chain head.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Head {
+    pub number: u64,
+    pub timestamp: u64,
+}
+
+/// How far ahead to target and when to give up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetargetConfig {
+    /// Number of upcoming blocks the bundle is submitted for on each head
+    pub blocks_ahead: u64,
+    pub slot_secs: u64,
+    /// Last block the bundle may target; `None` relies on `max_timestamp` only
+    pub max_block: Option<u64>,
+}
+
+impl Default for RetargetConfig {
+    fn default() -> Self {
+        Self { blocks_ahead: 3, slot_secs: SLOT_SECS, max_block: None }
+    }
+}
+
+/// Why re-targeting stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetargetOutcome {
+    /// All bundle transactions were mined; `block` is where the first one landed
+    Included { block: u64 },
+    /// No future block can satisfy `max_block`/`max_timestamp` any more
+    Expired { last_head: u64 },
+    /// The head source closed before inclusion or expiry
+    HeadsClosed,
+}
+
+/// One `eth_sendBundle` for one target block and, once that block has passed
+/// without inclusion, the relay's explanation of the miss.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubmissionAttempt {
+    pub block: u64,
+    pub bundle_hash: Option<H256>,
+    pub miss: Option<MissReason>,
+}
+
+/// Final outcome plus every submission made along the way.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetargetReport {
+    pub outcome: RetargetOutcome,
+    pub attempts: Vec<SubmissionAttempt>,
+}
+
+/// Re-submits a bundle for the next `blocks_ahead` blocks on every new head until
+/// it is included or no target block is valid any more.
+pub struct BundleRetargeter<'a> {
+    relay: &'a dyn BundleRelay,
+    rpc: String,
+    signed_txs: Vec<Vec<u8>>,
+    tx_hashes: Vec<H256>,
+    options: BundleOptions,
+    config: RetargetConfig,
+    submitted: BTreeSet<u64>,
+    attempts: Vec<SubmissionAttempt>,
+}
+
+impl<'a> BundleRetargeter<'a> {
+    /// `rpc` is used to check for inclusion of the bundle's transactions.
+    pub fn new(relay: &'a dyn BundleRelay, rpc: String, signed_txs: Vec<Vec<u8>>, options: BundleOptions, config: RetargetConfig) -> Self {
+        let tx_hashes = signed_txs.iter().map(|raw| H256::from(ethers_core::utils::keccak256(raw))).collect();
+        Self { relay, rpc, signed_txs, tx_hashes, options, config, submitted: BTreeSet::new(), attempts: Vec::new() }
+    }
+
+    /// Blocks that have already been targeted.
+    pub fn submitted_blocks(&self) -> Vec<u64> {
+        self.submitted.iter().copied().collect()
+    }
+
+    /// Submissions so far, with miss reasons for target blocks that have passed.
+    pub fn attempts(&self) -> &[SubmissionAttempt] {
+        &self.attempts
+    }
+
+    /// Upcoming blocks to submit for after `head`, skipping ones already targeted
+    /// and ones outside the block or timestamp window.
+    pub fn targets_for_head(&self, head: Head) -> Vec<u64> {
+        (head.number + 1..=head.number + self.config.blocks_ahead)
+            .filter(|b| !self.submitted.contains(b))
+            .filter(|b| self.config.max_block.is_none_or(|max| *b <= max))
+            .filter(|b| self.options.allows_timestamp(self.predicted_timestamp(head, *b)))
+            .collect()
+    }
+
+    /// Whether no block after `head` can include the bundle any more. A block too
+    /// early for `min_timestamp` does not expire it; later blocks may still qualify.
+    pub fn is_expired(&self, head: Head) -> bool {
+        let next = head.number + 1;
+        self.config.max_block.is_some_and(|max| next > max)
+            || self.options.max_timestamp.is_some_and(|max| self.predicted_timestamp(head, next) > max)
+    }
+
+    fn predicted_timestamp(&self, head: Head, block: u64) -> u64 {
+        head.timestamp + (block - head.number) * self.config.slot_secs
+    }
+
+    /// Drive re-targeting from `heads` until inclusion, expiry or the channel closes.
+    pub async fn run(&mut self, mut heads: mpsc::Receiver<Head>) -> Result<RetargetOutcome> {
+        let provider = Provider::<Http>::try_from(self.rpc.as_str()).context("invalid rpc url")?;
+        while let Some(head) = heads.recv().await {
+            if let Some(block) = self.inclusion_block(&provider).await {
+                tracing::info!(block, "bundle included");
+                return Ok(RetargetOutcome::Included { block });
+            }
+            self.explain_misses(head).await;
+            if self.is_expired(head) {
+                tracing::info!(head = head.number, "bundle no longer valid; stopping re-targeting");
+                return Ok(RetargetOutcome::Expired { last_head: head.number });
+            }
+            for block in self.targets_for_head(head) {
+                match self.relay.send_bundle_with_options(&self.signed_txs, Some(block), &self.options).await {
+                    Ok(resp) => {
+                        tracing::debug!(block, bundle_hash = ?resp.bundle_hash, "bundle targeted");
+                        self.submitted.insert(block);
+                        self.attempts.push(SubmissionAttempt { block, bundle_hash: Some(resp.bundle_hash), miss: None });
+                    }
+                    // Left out of `submitted` so the next head retries this block.
+                    Err(e) => {
+                        tracing::warn!(block, error = %e, "bundle submission failed");
+                        self.attempts.push(SubmissionAttempt { block, bundle_hash: None, miss: Some(MissReason::SubmissionFailed(e.to_string())) });
+                    }
+                }
+            }
+        }
+        Ok(RetargetOutcome::HeadsClosed)
+    }
+
+    /// Ask the relay why each accepted submission for a block at or below `head` missed.
+    async fn explain_misses(&mut self, head: Head) {
+        for attempt in self.attempts.iter_mut().filter(|a| a.miss.is_none() && a.block <= head.number) {
+            let Some(bundle_hash) = attempt.bundle_hash else { continue };
+            attempt.miss = Some(explain_miss(self.relay, bundle_hash, attempt.block).await);
+        }
+    }
+
+    /// Block of the first bundle tx once every tx has a receipt.
+    async fn inclusion_block(&self, provider: &Provider<Http>) -> Option<u64> {
+        let mut first_block = None;
+        for h in self.tx_hashes.iter() {
+            let receipt = provider.get_transaction_receipt(*h).await.ok().flatten()?;
+            let block = receipt.block_number?.as_u64();
+            first_block = Some(first_block.map_or(block, |b: u64| b.min(block)));
+        }
+        first_block
+    }
+}
+
+/// Ask `relay` why the bundle it accepted for `block` missed it, and log and count the reason.
+pub async fn explain_miss(relay: &dyn BundleRelay, bundle_hash: H256, block: u64) -> MissReason {
+    let reason = MissReason::from_stats(&relay.bundle_stats(bundle_hash, block).await);
+    tracing::info!(block, ?bundle_hash, reason = ?reason, "bundle missed target block");
+    #[cfg(feature = "with-metrics")]
+    {
+        metrics::counter!("bundle.misses", "reason" => reason.label()).increment(1);
+    }
+    reason
+}
+
+/// Watch `rpc` for new blocks and forward them as `Head`s, starting with the
+/// current head. The task stops when the receiver is dropped.
+pub fn spawn_head_watcher(rpc: &str, poll: Duration) -> Result<mpsc::Receiver<Head>> {
+    let provider = Provider::<Http>::try_from(rpc).context("invalid rpc url")?.interval(poll);
+    let (tx, rx) = mpsc::channel(16);
+    tokio::spawn(async move {
+        if let Ok(Some(latest)) = provider.get_block(ethers_core::types::BlockNumber::Latest).await
+            && let Some(number) = latest.number
+            && tx.send(Head { number: number.as_u64(), timestamp: latest.timestamp.as_u64() }).await.is_err()
+        {
+            return;
+        }
+        let mut blocks = match provider.watch_blocks().await {
+            Ok(w) => w,
+            Err(e) => {
+                tracing::warn!("failed to install block filter: {:?}", e);
+                return;
+            }
+        };
+        while let Some(hash) = blocks.next().await {
+            let block = match provider.get_block(hash).await {
+                Ok(Some(b)) => b,
+                _ => continue,
+            };
+            let Some(number) = block.number else { continue };
+            if tx.send(Head { number: number.as_u64(), timestamp: block.timestamp.as_u64() }).await.is_err() {
+                break;
+            }
+        }
+    });
+    Ok(rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::RelayClient;
+
+    const BUNDLE_HASH: &str = "0x2228f5d8954ce31dc1601a8ba264dbd401bf1428388ce88238932815c5d6f23f";
+
+    #[test]
+    fn targets_skip_submitted_and_late_blocks() {
+        let relay = RelayClient::without_relay().unwrap();
+        let options = BundleOptions { max_timestamp: Some(1_036), ..Default::default() };
+        let mut r = BundleRetargeter::new(&relay, "http://127.0.0.1:8545".to_string(), vec![vec![0x01]], options, RetargetConfig::default());
+        let head = Head { number: 100, timestamp: 1_000 };
+        assert_eq!(r.targets_for_head(head), vec![101, 102, 103]);
+        r.submitted.extend([101, 102]);
+        assert_eq!(r.targets_for_head(Head { number: 101, timestamp: 1_012 }), vec![103]);
+        assert!(r.is_expired(Head { number: 103, timestamp: 1_036 }));
+    }
+
+    #[tokio::test]
+    async fn retargets_on_each_head_until_expired() {
+        let rpc = httpmock::MockServer::start();
+        rpc.mock(|when, then| {
+            when.method(httpmock::Method::POST).body_contains("eth_getTransactionReceipt");
+            then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"result":null}"#);
+        });
+        let relay_server = httpmock::MockServer::start();
+        let ok = format!(r#"{{"jsonrpc":"2.0","id":1,"result":{{"bundleHash":"{}"}}}}"#, BUNDLE_HASH);
+        let m101 = relay_server.mock(|when, then| {
+            when.method(httpmock::Method::POST).body_contains(r#""blockNumber":"0x65""#).body_contains(r#""maxTimestamp":1020"#);
+            then.status(200).body(&ok);
+        });
+        let m102 = relay_server.mock(|when, then| {
+            when.method(httpmock::Method::POST).body_contains(r#""blockNumber":"0x66""#);
+            then.status(200).body(&ok);
+        });
+
+        let stats = relay_server.mock(|when, then| {
+            when.method(httpmock::Method::POST).body_contains("flashbots_getBundleStatsV2").body_contains(r#""blockNumber":"0x65""#);
+            then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"result":{"isSimulated":false,"receivedAt":"2024-01-01T00:00:00Z"}}"#);
+        });
+
+        let relay = RelayClient::with_url(relay_server.url("/")).unwrap();
+        let options = BundleOptions { max_timestamp: Some(1_020), ..Default::default() };
+        let config = RetargetConfig { blocks_ahead: 2, ..Default::default() };
+        let mut r = BundleRetargeter::new(&relay, rpc.url("/"), vec![vec![0x01]], options, config);
+
+        let (tx, rx) = mpsc::channel(4);
+        tx.send(Head { number: 100, timestamp: 1_000 }).await.unwrap();
+        tx.send(Head { number: 101, timestamp: 1_012 }).await.unwrap();
+        drop(tx);
+
+        let outcome = r.run(rx).await.unwrap();
+        assert_eq!(outcome, RetargetOutcome::Expired { last_head: 101 });
+        assert_eq!(m101.hits(), 1);
+        assert_eq!(m102.hits(), 0);
+        assert_eq!(r.submitted_blocks(), vec![101]);
+        // block 101 passed without inclusion, so the relay was asked why
+        assert_eq!(r.attempts().len(), 1);
+        assert_eq!(r.attempts()[0].miss, Some(MissReason::NotSimulated));
+        stats.assert();
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::fmt;
+use anyhow::{anyhow, Context, Result};
+use ethers_core::abi::{self, ethabi::AbiError, Abi, HumanReadableParser, ParamType, Token};
+use ethers_core::types::{Address, Bytes, U256};
+
+/// Selector of `Error(string)`.
+pub const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+/// Selector of `Panic(uint256)`.
+pub const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// Why a simulated tx failed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RevertReason {
+    /// `revert("...")` / `require(cond, "...")`
+    Error(String),
+    /// Compiler-inserted check, e.g. overflow (0x11) or division by zero (0x12)
+    Panic(U256),
+    /// Custom error found in the decoder's ABI set
+    Custom { name: String, args: Vec<Token> },
+    /// Revert data that matched nothing known (empty for a bare `revert()`)
+    Unknown(Bytes),
+    /// Exceptional halt such as out of gas or an invalid opcode
+    Halt(String),
+}
+
+impl RevertReason {
+    pub fn panic_description(code: U256) -> &'static str {
+        match code.low_u64() {
+            0x00 => "generic compiler panic",
+            0x01 => "assertion failed",
+            0x11 => "arithmetic overflow or underflow",
+            0x12 => "division or modulo by zero",
+            0x21 => "invalid enum value",
+            0x22 => "invalid storage byte array encoding",
+            0x31 => "pop on empty array",
+            0x32 => "array index out of bounds",
+            0x41 => "out of memory",
+            0x51 => "call to zero-initialized function",
+            _ => "unknown panic code",
+        }
+    }
+}
+
+impl fmt::Display for RevertReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RevertReason::Error(msg) => write!(f, "Error({:?})", msg),
+            RevertReason::Panic(code) => write!(f, "Panic(0x{:x}: {})", code, Self::panic_description(*code)),
+            RevertReason::Custom { name, args } => {
+                let args: Vec<String> = args.iter().map(fmt_token).collect();
+                write!(f, "{}({})", name, args.join(", "))
+            }
+            RevertReason::Unknown(data) if data.is_empty() => write!(f, "reverted without data"),
+            RevertReason::Unknown(data) => write!(f, "unknown revert {}", data),
+            RevertReason::Halt(reason) => write!(f, "halted: {}", reason),
+        }
+    }
+}
+
+/// Decodes revert data. `Error(string)` and `Panic(uint256)` are always known;
+/// custom errors come from the ABIs and signatures it is configured with.
+#[derive(Debug, Clone, Default)]
+pub struct RevertDecoder {
+    errors: HashMap<[u8; 4], AbiError>,
+}
+
+impl RevertDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register every custom error declared in `abi`.
+    pub fn with_abi(mut self, abi: &Abi) -> Self {
+        for error in abi.errors() {
+            self.errors.insert(selector(error), error.clone());
+        }
+        self
+    }
+
+    /// Register a human-readable error, e.g. `error InsufficientOutput(uint256 minOut, uint256 out)`.
+    pub fn with_error(mut self, signature: &str) -> Result<Self> {
+        let signature = if signature.trim_start().starts_with("error ") { signature.to_string() } else { format!("error {}", signature) };
+        let error = HumanReadableParser::parse_error(&signature).map_err(|e| anyhow!("invalid error signature {}: {:?}", signature, e))?;
+        self.errors.insert(selector(&error), error);
+        Ok(self)
+    }
+
+    pub fn decode(&self, data: &[u8]) -> RevertReason {
+        let Some((sel, args)) = data.split_first_chunk::<4>() else {
+            return RevertReason::Unknown(Bytes::from(data.to_vec()));
+        };
+        let decoded = match *sel {
+            ERROR_SELECTOR => abi::decode(&[ParamType::String], args).ok().and_then(|t| t.into_iter().next()?.into_string()).map(RevertReason::Error),
+            PANIC_SELECTOR => abi::decode(&[ParamType::Uint(256)], args).ok().and_then(|t| t.into_iter().next()?.into_uint()).map(RevertReason::Panic),
+            _ => self.errors.get(sel).and_then(|e| e.decode(args).ok().map(|args| RevertReason::Custom { name: e.name.clone(), args })),
+        };
+        decoded.unwrap_or_else(|| RevertReason::Unknown(Bytes::from(data.to_vec())))
+    }
+}
+
+/// Like `Token`'s `Display`, but with integers in decimal and 0x-prefixed addresses.
+fn fmt_token(token: &Token) -> String {
+    match token {
+        Token::Uint(v) => v.to_string(),
+        Token::Int(v) => ethers_core::types::I256::from_raw(*v).to_string(),
+        Token::Address(a) => format!("{:?}", a),
+        other => other.to_string(),
+    }
+}
+
+fn selector(error: &AbiError) -> [u8; 4] {
+    let mut sel = [0u8; 4];
+    sel.copy_from_slice(&error.signature().as_bytes()[..4]);
+    sel
+}
+
+/// One call in a tx's call tree, in the shape of geth's `callTracer`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CallFrame {
+    /// `CALL`, `STATICCALL`, `DELEGATECALL`, `CALLCODE`, `CREATE` or `CREATE2`
+    pub kind: String,
+    pub from: Address,
+    pub to: Option<Address>,
+    pub value: U256,
+    pub input: Bytes,
+    pub output: Bytes,
+    pub gas_used: u64,
+    /// Set when the frame reverted or halted
+    pub error: Option<String>,
+    pub calls: Vec<CallFrame>,
+}
+
+impl CallFrame {
+    /// Parse a `debug_traceTransaction` result produced with `{"tracer": "callTracer"}`.
+    pub fn from_call_tracer(v: &serde_json::Value) -> Result<Self> {
+        let field = |name: &str| v.get(name).and_then(|x| x.as_str());
+        let hex_bytes = |name: &str| -> Result<Bytes> {
+            match field(name) {
+                Some(s) => s.parse().with_context(|| format!("bad {} in call frame", name)),
+                None => Ok(Bytes::default()),
+            }
+        };
+        let quantity = |name: &str| -> Result<U256> {
+            match field(name) {
+                Some(s) => U256::from_str_radix(s.trim_start_matches("0x"), 16).with_context(|| format!("bad {} in call frame", name)),
+                None => Ok(U256::zero()),
+            }
+        };
+        Ok(CallFrame {
+            kind: field("type").unwrap_or("CALL").to_string(),
+            from: field("from").ok_or_else(|| anyhow!("call frame without from"))?.parse().context("bad from in call frame")?,
+            to: field("to").map(|s| s.parse()).transpose().context("bad to in call frame")?,
+            value: quantity("value")?,
+            input: hex_bytes("input")?,
+            output: hex_bytes("output")?,
+            gas_used: quantity("gasUsed")?.low_u64(),
+            error: field("error").map(str::to_string),
+            calls: match v.get("calls").and_then(|c| c.as_array()) {
+                Some(calls) => calls.iter().map(Self::from_call_tracer).collect::<Result<_>>()?,
+                None => Vec::new(),
+            },
+        })
+    }
+
+    /// The innermost failed frame on the failing path, where the revert originated.
+    pub fn revert_origin(&self) -> Option<&CallFrame> {
+        self.error.as_ref()?;
+        Some(self.calls.iter().rev().find_map(|c| c.revert_origin()).unwrap_or(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_builtin_and_custom_errors() {
+        let decoder = RevertDecoder::new().with_error("InsufficientOutput(uint256 minOut, uint256 out)").unwrap();
+
+        let mut data = ERROR_SELECTOR.to_vec();
+        data.extend(abi::encode(&[Token::String("K".into())]));
+        assert_eq!(decoder.decode(&data), RevertReason::Error("K".into()));
+
+        let mut data = PANIC_SELECTOR.to_vec();
+        data.extend(abi::encode(&[Token::Uint(0x11.into())]));
+        let panic = decoder.decode(&data);
+        assert_eq!(panic, RevertReason::Panic(0x11.into()));
+        assert_eq!(panic.to_string(), "Panic(0x11: arithmetic overflow or underflow)");
+
+        let mut data = ethers_core::utils::id("InsufficientOutput(uint256,uint256)").to_vec();
+        data.extend(abi::encode(&[Token::Uint(10.into()), Token::Uint(7.into())]));
+        let custom = decoder.decode(&data);
+        assert_eq!(custom.to_string(), "InsufficientOutput(10, 7)");
+
+        assert_eq!(decoder.decode(&[]).to_string(), "reverted without data");
+        assert!(matches!(RevertDecoder::new().decode(&data), RevertReason::Unknown(_)));
+    }
+
+    #[test]
+    fn parses_call_tracer_and_finds_revert_origin() {
+        let trace = serde_json::json!({
+            "type": "CALL", "from": "0x00000000000000000000000000000000000000aa", "to": "0x00000000000000000000000000000000000000bb",
+            "value": "0x0", "gasUsed": "0x5208", "input": "0x", "output": "0x", "error": "execution reverted",
+            "calls": [
+                { "type": "STATICCALL", "from": "0x00000000000000000000000000000000000000bb", "to": "0x00000000000000000000000000000000000000cc", "gasUsed": "0x10", "input": "0x01", "output": "0x02" },
+                { "type": "CALL", "from": "0x00000000000000000000000000000000000000bb", "to": "0x00000000000000000000000000000000000000dd", "gasUsed": "0x20", "input": "0x", "output": "0x08c379a0", "error": "execution reverted" }
+            ]
+        });
+        let frame = CallFrame::from_call_tracer(&trace).unwrap();
+        assert_eq!(frame.gas_used, 21000);
+        assert_eq!(frame.calls.len(), 2);
+        let origin = frame.revert_origin().unwrap();
+        assert_eq!(origin.to, Some(Address::from_low_u64_be(0xdd)));
+        assert!(frame.calls[0].revert_origin().is_none());
+    }
+}
@@ -0,0 +1,600 @@
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use ethers_core::types::transaction::eip2718::TypedTransaction;
+use ethers_core::types::{Address, Block, Bytes, Log, NameOrAddress, TransactionReceipt, H256, U256, U64};
+use ethers_core::utils::{keccak256, rlp};
+use revm::db::CacheDB;
+use revm::primitives::{
+    Address as RAddress, BlockEnv, Bytes as RBytes, ExecutionResult, Output, SpecId, TxEnv, TxKind, B256, U256 as RU256,
+};
+use revm::interpreter::{CallInputs, CallOutcome, CallValue, CreateInputs, CreateOutcome, InterpreterResult};
+use revm::primitives::CreateScheme;
+use revm::{inspector_handle_register, Database, DatabaseRef, Evm, EvmContext, Inspector};
+use crate::fees::next_base_fee;
+use crate::retarget::SLOT_SECS;
+use crate::revert::{CallFrame, RevertDecoder, RevertReason};
+use crate::sim::{balance_changes, balance_of_calldata, decode_balance, BalanceWatch, InclusionDelay, SimulationBackend, SimulationOutcome, Token};
+
+/// Block the bundle is simulated in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SimBlock {
+    pub number: u64,
+    pub timestamp: u64,
+    pub base_fee: U256,
+    pub coinbase: Address,
+    pub gas_limit: u64,
+}
+
+impl Default for SimBlock {
+    fn default() -> Self {
+        Self { number: 1, timestamp: 0, base_fee: U256::zero(), coinbase: Address::zero(), gas_limit: 30_000_000 }
+    }
+}
+
+impl SimBlock {
+    /// The block after `parent`: EIP-1559 base fee, one slot later. The real fee recipient
+    /// is unknown until the block is built, so the parent's is assumed.
+    pub fn after(parent: &Block<H256>) -> Self {
+        let base_fee = parent.base_fee_per_gas.map(|bf| next_base_fee(bf, parent.gas_used, parent.gas_limit)).unwrap_or_default();
+        Self {
+            number: parent.number.map_or(0, |n| n.as_u64()) + 1,
+            timestamp: parent.timestamp.as_u64() + SLOT_SECS,
+            base_fee,
+            coinbase: parent.author.unwrap_or_default(),
+            gas_limit: parent.gas_limit.as_u64(),
+        }
+    }
+}
+
+/// In-process EVM backend. Each simulation runs on a fresh `CacheDB` overlay over `db`,
+/// so the base state is never modified and no snapshot/revert round trips are needed.
+/// `db` should be cheap to clone (e.g. `Arc`-backed), since every simulation clones it.
+#[derive(Clone)]
+pub struct RevmBackend<DB> {
+    db: DB,
+    block: SimBlock,
+    chain_id: u64,
+    spec: SpecId,
+    revert_decoder: RevertDecoder,
+    call_traces: bool,
+}
+
+impl<DB> RevmBackend<DB>
+where
+    DB: DatabaseRef + Clone + Send + Sync,
+    DB::Error: std::fmt::Debug,
+{
+    pub fn new(db: DB, chain_id: u64, block: SimBlock) -> Self {
+        Self { db, block, chain_id, spec: SpecId::CANCUN, revert_decoder: RevertDecoder::new(), call_traces: false }
+    }
+
+    pub fn with_revert_decoder(mut self, decoder: RevertDecoder) -> Self {
+        self.revert_decoder = decoder;
+        self
+    }
+
+    /// Record a call tree for every tx. Costs an inspector hook per opcode, so off by default.
+    pub fn with_call_traces(mut self, enabled: bool) -> Self {
+        self.call_traces = enabled;
+        self
+    }
+
+    pub fn with_spec(mut self, spec: SpecId) -> Self {
+        self.spec = spec;
+        self
+    }
+
+    pub fn set_block(&mut self, block: SimBlock) {
+        self.block = block;
+    }
+
+    pub fn block(&self) -> SimBlock {
+        self.block
+    }
+
+    pub fn db(&self) -> &DB {
+        &self.db
+    }
+
+    /// Execute `signed_raw_txs` in order on top of the base state and build a receipt per tx.
+    /// Fails, like `eth_sendRawTransaction` would, if any tx is invalid (bad nonce, balance, ...).
+    pub fn run_bundle(&self, signed_raw_txs: &[Vec<u8>], next_base_fee: Option<U256>) -> Result<Vec<TransactionReceipt>> {
+        Ok(self.run_bundle_with_balances(signed_raw_txs, next_base_fee, &BalanceWatch::default())?.receipts)
+    }
+
+    /// `run_bundle`, recording the balances in `watch` before the first and after the last tx.
+    pub fn run_bundle_with_balances(&self, signed_raw_txs: &[Vec<u8>], next_base_fee: Option<U256>, watch: &BalanceWatch) -> Result<SimulationOutcome> {
+        let block = SimBlock { base_fee: next_base_fee.unwrap_or(self.block.base_fee), ..self.block };
+        self.run(signed_raw_txs, &block, &[], watch)
+    }
+
+    /// Run the bundle `delay.blocks` blocks after the configured block, one slot apart,
+    /// after replaying `delay.pending` on the same overlay.
+    pub fn run_bundle_delayed(&self, signed_raw_txs: &[Vec<u8>], delay: &InclusionDelay, watch: &BalanceWatch) -> Result<SimulationOutcome> {
+        let block = SimBlock {
+            number: self.block.number + delay.blocks,
+            timestamp: self.block.timestamp + delay.blocks * SLOT_SECS,
+            base_fee: delay.base_fee.unwrap_or(self.block.base_fee),
+            ..self.block
+        };
+        self.run(signed_raw_txs, &block, &delay.pending, watch)
+    }
+
+    fn run(&self, signed_raw_txs: &[Vec<u8>], block: &SimBlock, pending: &[Vec<u8>], watch: &BalanceWatch) -> Result<SimulationOutcome> {
+        let builder = Evm::builder().with_db(CacheDB::new(self.db.clone()));
+        if self.call_traces {
+            let mut evm = builder
+                .with_external_context(CallTracer::default())
+                .with_spec_id(self.spec)
+                .modify_cfg_env(|cfg| cfg.chain_id = self.chain_id)
+                .modify_block_env(|env| *env = block_env(block))
+                .append_handler_register(inspector_handle_register)
+                .build();
+            replay(&mut evm, pending);
+            self.execute(&mut evm, signed_raw_txs, block, watch, |evm| evm.context.external.take())
+        } else {
+            let mut evm = builder
+                .with_spec_id(self.spec)
+                .modify_cfg_env(|cfg| cfg.chain_id = self.chain_id)
+                .modify_block_env(|env| *env = block_env(block))
+                .build();
+            replay(&mut evm, pending);
+            self.execute(&mut evm, signed_raw_txs, block, watch, |_| None)
+        }
+    }
+
+    fn execute<'a, EXT>(
+        &self,
+        evm: &mut Evm<'a, EXT, CacheDB<DB>>,
+        signed_raw_txs: &[Vec<u8>],
+        block: &SimBlock,
+        watch: &BalanceWatch,
+        take_trace: impl Fn(&mut Evm<'a, EXT, CacheDB<DB>>) -> Option<CallFrame>,
+    ) -> Result<SimulationOutcome> {
+        let before = read_balances(evm, watch)?;
+        // drop frames recorded by the balanceOf calls
+        take_trace(evm);
+        let mut receipts = Vec::with_capacity(signed_raw_txs.len());
+        let mut reverts = Vec::with_capacity(signed_raw_txs.len());
+        let mut traces = Vec::with_capacity(signed_raw_txs.len());
+        let mut cumulative_gas_used = U256::zero();
+        for (i, raw) in signed_raw_txs.iter().enumerate() {
+            let (tx, from) = decode_signed_tx(raw).with_context(|| format!("decode tx {}", i))?;
+            *evm.tx_mut() = tx_env(&tx, from)?;
+            let result = evm.transact_commit().map_err(|e| anyhow!("tx {} rejected: {:?}", i, e))?;
+            traces.push(take_trace(evm));
+            reverts.push(match &result {
+                ExecutionResult::Success { .. } => None,
+                ExecutionResult::Revert { output, .. } => Some(self.revert_decoder.decode(output)),
+                ExecutionResult::Halt { reason, .. } => Some(RevertReason::Halt(format!("{:?}", reason))),
+            });
+
+            let gas_used = U256::from(result.gas_used());
+            cumulative_gas_used += gas_used;
+            let contract_address = match &result {
+                ExecutionResult::Success { output: Output::Create(_, Some(addr)), .. } => Some(Address::from(addr.0 .0)),
+                _ => None,
+            };
+            let logs = result
+                .logs()
+                .iter()
+                .enumerate()
+                .map(|(log_index, l)| Log {
+                    address: Address::from(l.address.0 .0),
+                    topics: l.topics().iter().map(|t| H256::from(t.0)).collect(),
+                    data: Bytes::from(l.data.data.to_vec()),
+                    block_number: Some(U64::from(block.number)),
+                    transaction_index: Some(U64::from(i)),
+                    log_index: Some(U256::from(log_index)),
+                    ..Default::default()
+                })
+                .collect();
+            receipts.push(TransactionReceipt {
+                transaction_hash: H256::from(keccak256(raw)),
+                transaction_index: U64::from(i),
+                block_number: Some(U64::from(block.number)),
+                from,
+                to: tx.to().and_then(|t| t.as_address().copied()),
+                cumulative_gas_used,
+                gas_used: Some(gas_used),
+                contract_address,
+                logs,
+                status: Some(U64::from(result.is_success() as u64)),
+                transaction_type: Some(U64::from(tx_type(&tx))),
+                effective_gas_price: Some(effective_gas_price(&tx, block.base_fee)),
+                ..Default::default()
+            });
+        }
+        let after = read_balances(evm, watch)?;
+        Ok(SimulationOutcome::new(receipts, balance_changes(watch, &before, &after), watch).with_diagnostics(reverts, traces))
+    }
+}
+
+/// Simulations run on the blocking pool: execution is CPU-bound, and a `ForkDb` blocks
+/// its thread on RPC for state it has not cached yet.
+#[async_trait]
+impl<DB> SimulationBackend for RevmBackend<DB>
+where
+    DB: DatabaseRef + Clone + Send + Sync + 'static,
+    DB::Error: std::fmt::Debug,
+{
+    async fn simulate_bundle_delayed(&self, signed_raw_txs: &[Vec<u8>], delay: &InclusionDelay, watch: &BalanceWatch) -> Result<SimulationOutcome> {
+        let (signed_raw_txs, delay, watch) = (signed_raw_txs.to_vec(), delay.clone(), watch.clone());
+        self.blocking(move |backend| backend.run_bundle_delayed(&signed_raw_txs, &delay, &watch)).await
+    }
+}
+
+impl<DB> RevmBackend<DB>
+where
+    DB: DatabaseRef + Clone + Send + Sync + 'static,
+    DB::Error: std::fmt::Debug,
+{
+    async fn blocking<T: Send + 'static>(&self, f: impl FnOnce(&Self) -> Result<T> + Send + 'static) -> Result<T> {
+        let backend = self.clone();
+        tokio::task::spawn_blocking(move || f(&backend)).await.context("simulation task failed")?
+    }
+}
+
+/// Commit `pending` txs ahead of a bundle, skipping any that do not decode or apply.
+fn replay<EXT, DB>(evm: &mut Evm<'_, EXT, CacheDB<DB>>, pending: &[Vec<u8>])
+where
+    DB: DatabaseRef,
+    DB::Error: std::fmt::Debug,
+{
+    for (i, raw) in pending.iter().enumerate() {
+        let applied = decode_signed_tx(raw).and_then(|(tx, from)| {
+            *evm.tx_mut() = tx_env(&tx, from)?;
+            evm.transact_commit().map_err(|e| anyhow!("{:?}", e))
+        });
+        if let Err(e) = applied {
+            tracing::debug!("pending tx {} skipped: {:?}", i, e);
+        }
+    }
+}
+
+/// Read `watch.entries()` from the current state. ERC-20 balances are read with a
+/// `balanceOf` call that is executed but not committed.
+fn read_balances<EXT, DB>(evm: &mut Evm<'_, EXT, CacheDB<DB>>, watch: &BalanceWatch) -> Result<Vec<U256>>
+where
+    DB: DatabaseRef,
+    DB::Error: std::fmt::Debug,
+{
+    let mut balances = Vec::new();
+    for (account, token) in watch.entries() {
+        let balance = match token {
+            Token::Native => {
+                let info = evm.db_mut().basic(to_raddress(account)).map_err(|e| anyhow!("load {:?}: {:?}", account, e))?;
+                info.map(|i| U256(i.balance.into_limbs())).unwrap_or_default()
+            }
+            Token::Erc20(t) => decode_balance(t, &static_call(evm, t, balance_of_calldata(account))?)?,
+        };
+        balances.push(balance);
+    }
+    Ok(balances)
+}
+
+/// Execute a zero-priced call from the zero address without committing its state.
+pub(crate) fn static_call<EXT, DB>(evm: &mut Evm<'_, EXT, CacheDB<DB>>, to: Address, data: Vec<u8>) -> Result<Vec<u8>>
+where
+    DB: DatabaseRef,
+    DB::Error: std::fmt::Debug,
+{
+    let saved_tx = evm.tx().clone();
+    let saved_base_fee = evm.block().basefee;
+    *evm.tx_mut() = TxEnv {
+        caller: RAddress::ZERO,
+        gas_limit: 1_000_000,
+        gas_price: RU256::ZERO,
+        transact_to: TxKind::Call(to_raddress(to)),
+        data: RBytes::from(data),
+        nonce: None,
+        ..Default::default()
+    };
+    evm.block_mut().basefee = RU256::ZERO;
+    let result = evm.transact();
+    *evm.tx_mut() = saved_tx;
+    evm.block_mut().basefee = saved_base_fee;
+    let result = result.map_err(|e| anyhow!("call to {:?} failed: {:?}", to, e))?.result;
+    match result {
+        ExecutionResult::Success { output, .. } => Ok(output.into_data().to_vec()),
+        other => Err(anyhow!("call to {:?} did not succeed: {:?}", to, other)),
+    }
+}
+
+/// Builds a `CallFrame` tree per transaction from revm's call/create hooks.
+#[derive(Debug, Default)]
+pub(crate) struct CallTracer {
+    stack: Vec<CallFrame>,
+    root: Option<CallFrame>,
+}
+
+impl CallTracer {
+    /// Trace of the last executed tx.
+    pub(crate) fn take(&mut self) -> Option<CallFrame> {
+        self.stack.clear();
+        self.root.take()
+    }
+
+    fn finish(&mut self, result: &InterpreterResult, created: Option<RAddress>) {
+        let Some(mut frame) = self.stack.pop() else { return };
+        frame.output = Bytes::from(result.output.to_vec());
+        frame.gas_used = result.gas.spent();
+        if let Some(addr) = created {
+            frame.to = Some(Address::from(addr.0 .0));
+        }
+        if result.result.is_revert() {
+            frame.error = Some("execution reverted".to_string());
+        } else if result.result.is_error() {
+            frame.error = Some(format!("{:?}", result.result));
+        }
+        match self.stack.last_mut() {
+            Some(parent) => parent.calls.push(frame),
+            None => self.root = Some(frame),
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for CallTracer {
+    fn call(&mut self, _context: &mut EvmContext<DB>, inputs: &mut CallInputs) -> Option<CallOutcome> {
+        let value = match inputs.value {
+            CallValue::Transfer(v) | CallValue::Apparent(v) => v,
+        };
+        self.stack.push(CallFrame {
+            kind: format!("{:?}", inputs.scheme).to_uppercase(),
+            from: Address::from(inputs.caller.0 .0),
+            to: Some(Address::from(inputs.target_address.0 .0)),
+            value: U256(value.into_limbs()),
+            input: Bytes::from(inputs.input.to_vec()),
+            ..Default::default()
+        });
+        None
+    }
+
+    fn call_end(&mut self, _context: &mut EvmContext<DB>, _inputs: &CallInputs, outcome: CallOutcome) -> CallOutcome {
+        self.finish(&outcome.result, None);
+        outcome
+    }
+
+    fn create(&mut self, _context: &mut EvmContext<DB>, inputs: &mut CreateInputs) -> Option<CreateOutcome> {
+        let kind = match inputs.scheme {
+            CreateScheme::Create => "CREATE",
+            CreateScheme::Create2 { .. } => "CREATE2",
+        };
+        self.stack.push(CallFrame {
+            kind: kind.to_string(),
+            from: Address::from(inputs.caller.0 .0),
+            value: U256(inputs.value.into_limbs()),
+            input: Bytes::from(inputs.init_code.to_vec()),
+            ..Default::default()
+        });
+        None
+    }
+
+    fn create_end(&mut self, _context: &mut EvmContext<DB>, _inputs: &CreateInputs, outcome: CreateOutcome) -> CreateOutcome {
+        self.finish(&outcome.result, outcome.address);
+        outcome
+    }
+}
+
+fn block_env(block: &SimBlock) -> BlockEnv {
+    let mut env = BlockEnv {
+        number: RU256::from(block.number),
+        timestamp: RU256::from(block.timestamp),
+        basefee: to_ru256(block.base_fee),
+        coinbase: to_raddress(block.coinbase),
+        gas_limit: RU256::from(block.gas_limit),
+        prevrandao: Some(B256::ZERO),
+        ..Default::default()
+    };
+    env.set_blob_excess_gas_and_price(0);
+    env
+}
+
+/// Decode a signed raw tx and recover its sender.
+pub(crate) fn decode_signed_tx(raw: &[u8]) -> Result<(TypedTransaction, Address)> {
+    let (tx, sig) = TypedTransaction::decode_signed(&rlp::Rlp::new(raw)).map_err(|e| anyhow!("invalid signed tx: {}", e))?;
+    let from = sig.recover(tx.sighash()).context("recover sender")?;
+    Ok((tx, from))
+}
+
+fn tx_env(tx: &TypedTransaction, from: Address) -> Result<TxEnv> {
+    let transact_to = match tx.to() {
+        Some(NameOrAddress::Address(a)) => TxKind::Call(to_raddress(*a)),
+        Some(NameOrAddress::Name(n)) => return Err(anyhow!("unresolved ENS recipient {}", n)),
+        None => TxKind::Create,
+    };
+    let mut env = TxEnv {
+        caller: to_raddress(from),
+        gas_limit: tx.gas().map(|g| g.as_u64()).unwrap_or(30_000_000),
+        gas_price: to_ru256(tx.gas_price().unwrap_or_default()),
+        transact_to,
+        value: to_ru256(tx.value().copied().unwrap_or_default()),
+        data: RBytes::from(tx.data().map(|d| d.to_vec()).unwrap_or_default()),
+        nonce: tx.nonce().map(|n| n.as_u64()),
+        chain_id: tx.chain_id().map(|c| c.as_u64()),
+        ..Default::default()
+    };
+    if let Some(list) = tx.access_list() {
+        env.access_list = list
+            .0
+            .iter()
+            .map(|item| (to_raddress(item.address), item.storage_keys.iter().map(|k| RU256::from_be_bytes(k.0)).collect()))
+            .collect();
+    }
+    if let TypedTransaction::Eip1559(req) = tx {
+        env.gas_price = to_ru256(req.max_fee_per_gas.unwrap_or_default());
+        env.gas_priority_fee = Some(to_ru256(req.max_priority_fee_per_gas.unwrap_or_default()));
+    }
+    Ok(env)
+}
+
+fn tx_type(tx: &TypedTransaction) -> u64 {
+    match tx {
+        TypedTransaction::Legacy(_) => 0,
+        TypedTransaction::Eip2930(_) => 1,
+        TypedTransaction::Eip1559(_) => 2,
+    }
+}
+
+fn effective_gas_price(tx: &TypedTransaction, base_fee: U256) -> U256 {
+    match tx {
+        TypedTransaction::Eip1559(req) => {
+            let max_fee = req.max_fee_per_gas.unwrap_or_default();
+            let tip = req.max_priority_fee_per_gas.unwrap_or_default();
+            max_fee.min(base_fee.saturating_add(tip))
+        }
+        _ => tx.gas_price().unwrap_or_default(),
+    }
+}
+
+pub(crate) fn to_raddress(a: Address) -> RAddress {
+    RAddress::from(a.0)
+}
+
+pub(crate) fn to_ru256(v: U256) -> RU256 {
+    RU256::from_limbs(v.0)
+}
+
+/// Fixtures for tests that simulate on an in-memory `RevmBackend`.
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use super::*;
+    use crate::signer::{BasicEnvSigner, Signer};
+    use crate::sim::{ProfitScorer, Scorer};
+    use crate::tx::build_eip1559_tx;
+    use revm::db::InMemoryDB;
+    use revm::primitives::AccountInfo;
+
+    const SECRET: &str = "0123456789012345678901234567890123456789012345678901234567890123";
+
+    pub(crate) fn signer() -> BasicEnvSigner {
+        BasicEnvSigner::from_secret(SECRET.to_string())
+    }
+
+    /// An empty state in which `signer` holds `balance` wei.
+    pub(crate) async fn funded_db(signer: &BasicEnvSigner, balance: U256) -> InMemoryDB {
+        let from = signer.address().await.unwrap().unwrap();
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(to_raddress(from), AccountInfo { balance: to_ru256(balance), ..Default::default() });
+        db
+    }
+
+    pub(crate) async fn sign(signer: &BasicEnvSigner, nonce: u64, to: Address, value: u64) -> Vec<u8> {
+        let tx = build_eip1559_tx(U256::from(nonce), to, U256::from(value), Bytes::default(), U256::from(50_000u64), U256::from(2u64), U256::from(100u64), 1);
+        signer.sign_typed_transaction(&tx).await.unwrap()
+    }
+
+    /// Realized PnL (`ProfitScorer`) plus a fixed profit in wei the bundle is assumed to make.
+    pub(crate) struct FixedProfit(pub i128);
+
+    impl Scorer for FixedProfit {
+        fn score(&self, _receipts: &[TransactionReceipt], _signed_txs: &[Vec<u8>], _expected_pnl: Option<&[i128]>) -> i128 {
+            0
+        }
+
+        fn score_outcome(&self, outcome: &SimulationOutcome, signed_txs: &[Vec<u8>]) -> i128 {
+            ProfitScorer::new(0).score_outcome(outcome, signed_txs) + self.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::fixtures::{funded_db, sign, signer};
+    use crate::signer::{BasicEnvSigner, Signer};
+    use crate::sim::{GasCostScorer, ProfitScorer, Scorer};
+    use crate::tx::build_eip1559_tx;
+    use revm::db::InMemoryDB;
+    use revm::primitives::{AccountInfo, Bytecode};
+
+    async fn funded_backend(signer: &BasicEnvSigner) -> (RevmBackend<InMemoryDB>, Address) {
+        let mut db = funded_db(signer, U256::exp10(18)).await;
+        // PUSH1 0 PUSH1 0 REVERT
+        let reverter = Address::from_low_u64_be(0xdead);
+        let code = Bytecode::new_raw(RBytes::from(vec![0x60, 0x00, 0x60, 0x00, 0xfd]));
+        db.insert_account_info(to_raddress(reverter), AccountInfo { code_hash: code.hash_slow(), code: Some(code), ..Default::default() });
+        let block = SimBlock { base_fee: U256::from(10u64), ..Default::default() };
+        (RevmBackend::new(db, 1, block), reverter)
+    }
+
+    #[tokio::test]
+    async fn runs_bundle_in_process_and_scores_receipts() {
+        let signer = signer();
+        let (backend, reverter) = funded_backend(&signer).await;
+        let to = Address::from_low_u64_be(0xbeef);
+        let signed = vec![sign(&signer, 0, to, 1_000).await, sign(&signer, 1, reverter, 0).await];
+
+        let receipts = backend.simulate_bundle(&signed, None).await.unwrap();
+        assert_eq!(receipts.len(), 2);
+        assert_eq!(receipts[0].status, Some(U64::from(1u64)));
+        assert_eq!(receipts[0].gas_used, Some(U256::from(21_000u64)));
+        assert_eq!(receipts[0].effective_gas_price, Some(U256::from(12u64)));
+        assert_eq!(receipts[0].transaction_hash, H256::from(keccak256(&signed[0])));
+        // the second tx sees the first one's nonce bump and reverts in the contract
+        assert_eq!(receipts[1].status, Some(U64::from(0u64)));
+        assert_eq!(GasCostScorer.score(&receipts[..1], &signed, None), -21_000 * 12);
+
+        // the base state is untouched, so the same bundle replays; a later base fee is applied
+        let replay = backend.simulate_bundle(&signed[..1], Some(U256::from(50u64))).await.unwrap();
+        assert_eq!(replay[0].effective_gas_price, Some(U256::from(52u64)));
+    }
+
+    #[tokio::test]
+    async fn records_balance_changes_and_scores_realized_profit() {
+        let signer = signer();
+        let (mut backend, _) = funded_backend(&signer).await;
+        let from = signer.address().await.unwrap().unwrap();
+        let coinbase = Address::from_low_u64_be(0xc0);
+        backend.set_block(SimBlock { coinbase, ..backend.block() });
+        // WETH-like: empty calldata credits msg.value to the caller, otherwise balanceOf(arg)
+        let weth = Address::from_low_u64_be(0xe7);
+        let code = Bytecode::new_raw(RBytes::from(hex::decode("36156011576004355460005260206000f35b34335401335500").unwrap()));
+        backend.db.insert_account_info(to_raddress(weth), AccountInfo { code_hash: code.hash_slow(), code: Some(code), ..Default::default() });
+
+        let tx = build_eip1559_tx(U256::zero(), weth, U256::from(1_000u64), Bytes::default(), U256::from(100_000u64), U256::from(2u64), U256::from(100u64), 1);
+        let signed = vec![signer.sign_typed_transaction(&tx).await.unwrap()];
+        let watch = BalanceWatch::new(vec![from]).with_tokens(vec![weth]).with_coinbase(coinbase);
+        let outcome = backend.simulate_bundle_with_balances(&signed, None, &watch).await.unwrap();
+
+        let gas_used = outcome.receipts[0].gas_used.unwrap().as_u64() as i128;
+        assert_eq!(outcome.pnl[&Token::Erc20(weth)], 1_000);
+        assert_eq!(outcome.native_pnl(), -1_000 - gas_used * 12);
+        assert_eq!(outcome.coinbase_delta, Some(gas_used * 2));
+        assert_eq!(ProfitScorer::new(1).score_outcome(&outcome, &signed), outcome.native_pnl());
+    }
+
+    #[tokio::test]
+    async fn decodes_nested_revert_and_records_call_tree() {
+        let signer = signer();
+        let (mut backend, _) = funded_backend(&signer).await;
+        let install = |backend: &mut RevmBackend<InMemoryDB>, addr: Address, code: String| {
+            let code = Bytecode::new_raw(RBytes::from(hex::decode(code).unwrap()));
+            backend.db.insert_account_info(to_raddress(addr), AccountInfo { code_hash: code.hash_slow(), code: Some(code), ..Default::default() });
+        };
+        // inner: revert with Error("K") copied from its own code
+        let inner = Address::from_low_u64_be(0x1111);
+        let reason = format!("08c379a0{:064x}{:064x}4b{}", 0x20, 1, "00".repeat(31));
+        install(&mut backend, inner, format!("6064600c60003960646000fd{}", reason));
+        // outer: CALL inner, then revert with whatever it returned
+        let outer = Address::from_low_u64_be(0x2222);
+        install(&mut backend, outer, "600060006000600060007300000000000000000000000000000000000011115af13d600060003e3d6000fd".to_string());
+        let backend = backend.with_call_traces(true);
+
+        let signed = vec![sign(&signer, 0, outer, 0).await];
+        let outcome = backend.simulate_bundle_with_balances(&signed, None, &BalanceWatch::default()).await.unwrap();
+        assert_eq!(outcome.first_revert(), Some((0, &RevertReason::Error("K".into()))));
+        let trace = outcome.traces[0].as_ref().unwrap();
+        assert_eq!((trace.kind.as_str(), trace.to), ("CALL", Some(outer)));
+        assert_eq!(trace.calls.len(), 1);
+        assert_eq!(trace.revert_origin().unwrap().to, Some(inner));
+    }
+
+    #[tokio::test]
+    async fn invalid_nonce_fails_the_bundle() {
+        let signer = signer();
+        let (backend, _) = funded_backend(&signer).await;
+        let signed = vec![sign(&signer, 5, Address::zero(), 0).await];
+        assert!(backend.simulate_bundle(&signed, None).await.is_err());
+    }
+}
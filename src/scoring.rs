@@ -0,0 +1,412 @@
+use anyhow::{bail, Result};
+use ethers_core::types::{Address, TransactionReceipt, U256};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use crate::revert::CallFrame;
+use crate::sim::{ConfigurableScorer, GasCostScorer, ProfitScorer, Scorer, SimulationOutcome, Token};
+use crate::tx::BidStrategy;
+
+/// Score of a bundle that must never be chosen. Matches the revert score of `GasCostScorer`;
+/// combinators pass it through instead of adding to it.
+pub const REJECTED: i128 = i128::MIN / 4;
+
+/// Sum of weighted scores. Rejected as soon as one part rejects.
+pub struct WeightedSum {
+    pub parts: Vec<(i128, Box<dyn Scorer>)>,
+}
+
+impl WeightedSum {
+    pub fn new() -> Self {
+        Self { parts: Vec::new() }
+    }
+
+    pub fn with(mut self, weight: i128, scorer: impl Scorer + 'static) -> Self {
+        self.parts.push((weight, Box::new(scorer)));
+        self
+    }
+
+    fn combine(&self, score: impl Fn(&dyn Scorer) -> i128) -> i128 {
+        let mut total = 0i128;
+        for (weight, scorer) in &self.parts {
+            let s = score(scorer.as_ref());
+            if s <= REJECTED {
+                return REJECTED;
+            }
+            total = total.saturating_add(s.saturating_mul(*weight));
+        }
+        total
+    }
+}
+
+impl Default for WeightedSum {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scorer for WeightedSum {
+    fn score(&self, receipts: &[TransactionReceipt], signed_txs: &[Vec<u8>], expected_pnl: Option<&[i128]>) -> i128 {
+        self.combine(|s| s.score(receipts, signed_txs, expected_pnl))
+    }
+
+    fn score_outcome(&self, outcome: &SimulationOutcome, signed_txs: &[Vec<u8>]) -> i128 {
+        self.combine(|s| s.score_outcome(outcome, signed_txs))
+    }
+}
+
+/// Rejects bundles whose wrapped score is below `min_profit`. Wrap a profit measure
+/// (`ProfitScorer`, `TokenValueScorer`) so the threshold is in wei.
+pub struct MinProfit {
+    pub min_profit: i128,
+    pub inner: Box<dyn Scorer>,
+}
+
+impl MinProfit {
+    pub fn new(min_profit: i128, inner: impl Scorer + 'static) -> Self {
+        Self { min_profit, inner: Box::new(inner) }
+    }
+
+    fn gate(&self, score: i128) -> i128 {
+        if score < self.min_profit { REJECTED } else { score }
+    }
+}
+
+impl Scorer for MinProfit {
+    fn score(&self, receipts: &[TransactionReceipt], signed_txs: &[Vec<u8>], expected_pnl: Option<&[i128]>) -> i128 {
+        self.gate(self.inner.score(receipts, signed_txs, expected_pnl))
+    }
+
+    fn score_outcome(&self, outcome: &SimulationOutcome, signed_txs: &[Vec<u8>]) -> i128 {
+        self.gate(self.inner.score_outcome(outcome, signed_txs))
+    }
+}
+
+/// Rejects bundles that use more than `max_gas` in total.
+pub struct MaxGas {
+    pub max_gas: u64,
+    pub inner: Box<dyn Scorer>,
+}
+
+impl MaxGas {
+    pub fn new(max_gas: u64, inner: impl Scorer + 'static) -> Self {
+        Self { max_gas, inner: Box::new(inner) }
+    }
+
+    fn exceeded(&self, receipts: &[TransactionReceipt]) -> bool {
+        let used = receipts.iter().fold(U256::zero(), |acc, r| acc.saturating_add(r.gas_used.unwrap_or_default()));
+        used > U256::from(self.max_gas)
+    }
+}
+
+impl Scorer for MaxGas {
+    fn score(&self, receipts: &[TransactionReceipt], signed_txs: &[Vec<u8>], expected_pnl: Option<&[i128]>) -> i128 {
+        if self.exceeded(receipts) { REJECTED } else { self.inner.score(receipts, signed_txs, expected_pnl) }
+    }
+
+    fn score_outcome(&self, outcome: &SimulationOutcome, signed_txs: &[Vec<u8>]) -> i128 {
+        if self.exceeded(&outcome.receipts) { REJECTED } else { self.inner.score_outcome(outcome, signed_txs) }
+    }
+}
+
+/// Subtracts `penalty` when any tx touches a blocklisted contract. Receipts show the
+/// called contract and log emitters; with call traces every nested call is checked too.
+pub struct BlocklistPenalty {
+    pub contracts: HashSet<Address>,
+    pub penalty: i128,
+    pub inner: Box<dyn Scorer>,
+}
+
+impl BlocklistPenalty {
+    pub fn new(contracts: impl IntoIterator<Item = Address>, penalty: i128, inner: impl Scorer + 'static) -> Self {
+        Self { contracts: contracts.into_iter().collect(), penalty, inner: Box::new(inner) }
+    }
+
+    fn touched(&self, receipts: &[TransactionReceipt], traces: &[Option<CallFrame>]) -> bool {
+        let in_receipts = receipts.iter().any(|r| {
+            r.to.is_some_and(|to| self.contracts.contains(&to)) || r.logs.iter().any(|log| self.contracts.contains(&log.address))
+        });
+        in_receipts || traces.iter().flatten().any(|frame| self.in_frame(frame))
+    }
+
+    fn in_frame(&self, frame: &CallFrame) -> bool {
+        frame.to.is_some_and(|to| self.contracts.contains(&to)) || frame.calls.iter().any(|c| self.in_frame(c))
+    }
+
+    fn apply(&self, score: i128, touched: bool) -> i128 {
+        if score <= REJECTED || !touched { score } else { score.saturating_sub(self.penalty) }
+    }
+}
+
+impl Scorer for BlocklistPenalty {
+    fn score(&self, receipts: &[TransactionReceipt], signed_txs: &[Vec<u8>], expected_pnl: Option<&[i128]>) -> i128 {
+        self.apply(self.inner.score(receipts, signed_txs, expected_pnl), self.touched(receipts, &[]))
+    }
+
+    fn score_outcome(&self, outcome: &SimulationOutcome, signed_txs: &[Vec<u8>]) -> i128 {
+        self.apply(self.inner.score_outcome(outcome, signed_txs), self.touched(&outcome.receipts, &outcome.traces))
+    }
+}
+
+/// Native value of a token: `native_per_unit` wei buys `10^decimals` base units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct TokenPrice {
+    pub token: Address,
+    #[serde(deserialize_with = "de_unsigned_wei")]
+    pub native_per_unit: u128,
+    pub decimals: u8,
+}
+
+impl TokenPrice {
+    /// Native value of `amount` base units, rounded toward zero.
+    pub fn to_native(&self, amount: i128) -> i128 {
+        let value = U256::from(amount.unsigned_abs()).saturating_mul(U256::from(self.native_per_unit)) / U256::exp10(self.decimals as usize);
+        let value = <U256 as TryInto<u128>>::try_into(value).map_or(i128::MAX, |v| v.min(i128::MAX as u128) as i128);
+        if amount < 0 { -value } else { value }
+    }
+}
+
+/// Realized PnL across native and ERC-20 balances, converted to native value at fixed
+/// prices, minus `revert_penalty` per reverted tx. Tokens without a price are ignored.
+/// Without an outcome it scores like `ProfitScorer`.
+pub struct TokenValueScorer {
+    pub prices: BTreeMap<Address, TokenPrice>,
+    pub revert_penalty: i128,
+}
+
+impl TokenValueScorer {
+    pub fn new(revert_penalty: i128) -> Self {
+        Self { prices: BTreeMap::new(), revert_penalty }
+    }
+
+    pub fn with_price(mut self, price: TokenPrice) -> Self {
+        self.prices.insert(price.token, price);
+        self
+    }
+
+    pub fn native_value(&self, outcome: &SimulationOutcome) -> i128 {
+        outcome.pnl.iter().fold(0i128, |acc, (token, delta)| {
+            let value = match token {
+                Token::Native => *delta,
+                Token::Erc20(t) => self.prices.get(t).map_or(0, |p| p.to_native(*delta)),
+            };
+            acc.saturating_add(value)
+        })
+    }
+}
+
+impl Scorer for TokenValueScorer {
+    fn score(&self, receipts: &[TransactionReceipt], signed_txs: &[Vec<u8>], expected_pnl: Option<&[i128]>) -> i128 {
+        ProfitScorer::new(self.revert_penalty).score(receipts, signed_txs, expected_pnl)
+    }
+
+    fn score_outcome(&self, outcome: &SimulationOutcome, _signed_txs: &[Vec<u8>]) -> i128 {
+        self.native_value(outcome).saturating_sub(self.revert_penalty.saturating_mul(outcome.reverted() as i128))
+    }
+}
+
+/// Declarative scoring policy, e.g. loaded from JSON. Amounts are integers or, above
+/// 64 bits, decimal strings:
+///
+/// ```json
+/// { "type": "min_profit", "min_profit": 1000000000000000,
+///   "scorer": { "type": "token_value", "revert_penalty": 0,
+///               "prices": [{ "token": "0xa0b8...", "native_per_unit": 300000000000000, "decimals": 6 }] } }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScorerConfig {
+    GasCost,
+    Profit {
+        #[serde(default, deserialize_with = "de_wei")]
+        revert_penalty: i128,
+    },
+    /// `ConfigurableScorer` with an optional percentage bid to the builder
+    Configurable {
+        #[serde(deserialize_with = "de_wei")]
+        revert_penalty: i128,
+        #[serde(deserialize_with = "de_wei")]
+        gas_weight: i128,
+        #[serde(deserialize_with = "de_wei")]
+        pnl_weight: i128,
+        #[serde(default)]
+        bid_bps: Option<u32>,
+    },
+    TokenValue {
+        #[serde(default, deserialize_with = "de_wei")]
+        revert_penalty: i128,
+        #[serde(default)]
+        prices: Vec<TokenPrice>,
+    },
+    WeightedSum {
+        parts: Vec<WeightedPart>,
+    },
+    MinProfit {
+        #[serde(deserialize_with = "de_wei")]
+        min_profit: i128,
+        scorer: Box<ScorerConfig>,
+    },
+    MaxGas {
+        max_gas: u64,
+        scorer: Box<ScorerConfig>,
+    },
+    Blocklist {
+        contracts: Vec<Address>,
+        #[serde(deserialize_with = "de_wei")]
+        penalty: i128,
+        scorer: Box<ScorerConfig>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct WeightedPart {
+    #[serde(deserialize_with = "de_wei")]
+    pub weight: i128,
+    pub scorer: ScorerConfig,
+}
+
+impl Default for ScorerConfig {
+    /// The `ConfigurableScorer` preset: revert penalty 1e18, gas and P&L weighted 1.
+    fn default() -> Self {
+        ScorerConfig::Configurable { revert_penalty: 1_000_000_000_000_000_000, gas_weight: 1, pnl_weight: 1, bid_bps: None }
+    }
+}
+
+impl ScorerConfig {
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn build(&self) -> Result<Box<dyn Scorer>> {
+        Ok(match self {
+            ScorerConfig::GasCost => Box::new(GasCostScorer),
+            ScorerConfig::Profit { revert_penalty } => Box::new(ProfitScorer::new(*revert_penalty)),
+            ScorerConfig::Configurable { revert_penalty, gas_weight, pnl_weight, bid_bps } => {
+                let mut scorer = ConfigurableScorer::new(*revert_penalty, *gas_weight, *pnl_weight);
+                if let Some(bps) = bid_bps {
+                    if *bps > 10_000 {
+                        bail!("bid_bps {} exceeds 10000", bps);
+                    }
+                    scorer = scorer.with_bid_strategy(BidStrategy::Percentage { bps: *bps });
+                }
+                Box::new(scorer)
+            }
+            ScorerConfig::TokenValue { revert_penalty, prices } => {
+                Box::new(prices.iter().fold(TokenValueScorer::new(*revert_penalty), |s, p| s.with_price(*p)))
+            }
+            ScorerConfig::WeightedSum { parts } => {
+                if parts.is_empty() {
+                    bail!("weighted_sum needs at least one part");
+                }
+                let parts = parts.iter().map(|p| Ok((p.weight, p.scorer.build()?))).collect::<Result<_>>()?;
+                Box::new(WeightedSum { parts })
+            }
+            ScorerConfig::MinProfit { min_profit, scorer } => Box::new(MinProfit { min_profit: *min_profit, inner: scorer.build()? }),
+            ScorerConfig::MaxGas { max_gas, scorer } => Box::new(MaxGas { max_gas: *max_gas, inner: scorer.build()? }),
+            ScorerConfig::Blocklist { contracts, penalty, scorer } => {
+                Box::new(BlocklistPenalty { contracts: contracts.iter().copied().collect(), penalty: *penalty, inner: scorer.build()? })
+            }
+        })
+    }
+}
+
+/// Wei amount from a JSON integer or decimal string. Tagged enums buffer their fields,
+/// which loses serde's `i128` support, so large values have to come in as strings.
+fn de_wei<'de, D: serde::Deserializer<'de>>(d: D) -> std::result::Result<i128, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Wei {
+        Signed(i64),
+        Unsigned(u64),
+        Decimal(String),
+    }
+    match Wei::deserialize(d)? {
+        Wei::Signed(v) => Ok(v as i128),
+        Wei::Unsigned(v) => Ok(v as i128),
+        Wei::Decimal(s) => s.parse().map_err(serde::de::Error::custom),
+    }
+}
+
+fn de_unsigned_wei<'de, D: serde::Deserializer<'de>>(d: D) -> std::result::Result<u128, D::Error> {
+    u128::try_from(de_wei(d)?).map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{BalanceChange, BalanceWatch};
+    use ethers_core::types::Log;
+
+    fn receipt(gas: u64, to: Address) -> TransactionReceipt {
+        TransactionReceipt {
+            status: Some(1u64.into()),
+            gas_used: Some(gas.into()),
+            effective_gas_price: Some(1u64.into()),
+            to: Some(to),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn combinators_gate_penalize_and_convert() {
+        let us = Address::repeat_byte(0x01);
+        let usdc = Address::repeat_byte(0x0c);
+        let pool = Address::repeat_byte(0x0d);
+        let sanctioned = Address::repeat_byte(0xbb);
+        let watch = BalanceWatch::new(vec![us]).with_tokens(vec![usdc]);
+        let change = |token, before: u64, after: u64| BalanceChange { account: us, token, before: before.into(), after: after.into() };
+        let mut pool_receipt = receipt(50_000, pool);
+        pool_receipt.logs.push(Log { address: sanctioned, ..Default::default() });
+        // paid 60k gas at 1 wei, received 2 USDC worth 0.0005 ETH each
+        let outcome = SimulationOutcome::new(
+            vec![receipt(10_000, us), pool_receipt],
+            vec![change(Token::Native, 1_000_000, 940_000), change(Token::Erc20(usdc), 0, 2_000_000)],
+            &watch,
+        );
+
+        let value = TokenValueScorer::new(0).with_price(TokenPrice { token: usdc, native_per_unit: 500_000_000_000_000, decimals: 6 });
+        assert_eq!(value.score_outcome(&outcome, &[]), 1_000_000_000_000_000 - 60_000);
+        assert_eq!(TokenPrice { token: usdc, native_per_unit: 3, decimals: 1 }.to_native(-25), -7);
+
+        assert_eq!(MinProfit::new(1_000_000_000_000_000, ProfitScorer::new(0)).score_outcome(&outcome, &[]), REJECTED);
+        assert_eq!(MaxGas::new(59_999, ProfitScorer::new(0)).score_outcome(&outcome, &[]), REJECTED);
+        assert_eq!(MaxGas::new(60_000, ProfitScorer::new(0)).score_outcome(&outcome, &[]), -60_000);
+
+        // the blocklisted contract only shows up as a log emitter
+        assert_eq!(BlocklistPenalty::new([sanctioned], 1_000, ProfitScorer::new(0)).score_outcome(&outcome, &[]), -61_000);
+        let traced = SimulationOutcome::new(vec![receipt(10_000, pool)], Vec::new(), &watch).with_diagnostics(
+            vec![None],
+            vec![Some(CallFrame { to: Some(pool), calls: vec![CallFrame { to: Some(sanctioned), ..Default::default() }], ..Default::default() })],
+        );
+        assert_eq!(BlocklistPenalty::new([sanctioned], 1_000, ProfitScorer::new(0)).score_outcome(&traced, &[]), -1_000);
+
+        let sum = WeightedSum::new().with(2, ProfitScorer::new(0)).with(1, GasCostScorer);
+        assert_eq!(sum.score_outcome(&outcome, &[]), -120_000 - 60_000);
+        let mut reverted = outcome.clone();
+        reverted.receipts[0].status = Some(0u64.into());
+        assert_eq!(sum.score_outcome(&reverted, &[]), REJECTED);
+    }
+
+    #[test]
+    fn builds_scorer_tree_from_config() {
+        let json = r#"{
+            "type": "blocklist", "contracts": ["0xbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb"], "penalty": 5000,
+            "scorer": { "type": "max_gas", "max_gas": 100000,
+                "scorer": { "type": "weighted_sum", "parts": [
+                    { "weight": 1, "scorer": { "type": "min_profit", "min_profit": "100",
+                        "scorer": { "type": "token_value", "prices": [{ "token": "0x0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c", "native_per_unit": "500000000000000000000", "decimals": 6 }] } } },
+                    { "weight": 2, "scorer": { "type": "configurable", "revert_penalty": 0, "gas_weight": 0, "pnl_weight": 1, "bid_bps": 5000 } }
+                ] } } }"#;
+        let config = ScorerConfig::from_json(json).unwrap();
+        assert!(format!("{:?}", config).contains("native_per_unit: 500000000000000000000"));
+        let scorer = config.build().unwrap();
+        let receipts = [receipt(21_000, Address::repeat_byte(0xbb))];
+        // profit: 1000 - 21000 gas < 100 → rejected
+        assert_eq!(scorer.score(&receipts, &[], Some(&[1_000])), REJECTED);
+        // 100_000 - 21_000 = 79_000; configurable: 100_000 - 50% bid of 79_000 = 60_500; minus blocklist penalty
+        assert_eq!(scorer.score(&receipts, &[], Some(&[100_000])), 79_000 + 2 * 60_500 - 5_000);
+
+        assert!(matches!(ScorerConfig::default(), ScorerConfig::Configurable { .. }));
+        assert!(ScorerConfig::WeightedSum { parts: vec![] }.build().is_err());
+        assert!(ScorerConfig::from_json(r#"{ "type": "configurable", "revert_penalty": 0, "gas_weight": 1, "pnl_weight": 1, "bid_bps": 20000 }"#).unwrap().build().is_err());
+    }
+}
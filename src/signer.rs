@@ -1,5 +1,6 @@
 use anyhow::{Result, Context};
 use async_trait::async_trait;
+use ethers_core::types::Address;
 use ethers_core::types::transaction::eip2718::TypedTransaction;
 
 /// Signing abstraction. In prod, implement HSM/KMS-backed signer.
@@ -10,6 +11,14 @@ pub trait Signer: Send + Sync {
 
     /// Sign a `TypedTransaction` (EIP-1559 aware) and return signed raw tx bytes.
     async fn sign_typed_transaction(&self, tx: &TypedTransaction) -> Result<Vec<u8>>;
+
+    /// Optional: address of the signing key (needed for relay authentication)
+    async fn address(&self) -> Result<Option<Address>> { Ok(None) }
+
+    /// Optional: EIP-191 `personal_sign` over `message`, returned as 65-byte r||s||v
+    async fn sign_message(&self, _message: &[u8]) -> Result<Vec<u8>> {
+        Err(anyhow::anyhow!("signer does not support EIP-191 message signing"))
+    }
 }
 
 /// In-memory/test signer (does nothing; for unit tests)
@@ -69,6 +78,19 @@ impl Signer for BasicEnvSigner {
         let raw = tx.rlp_signed(&sig);
         Ok(raw.to_vec())
     }
+
+    async fn address(&self) -> Result<Option<Address>> {
+        use ethers_signers::{LocalWallet, Signer};
+        use std::str::FromStr;
+
+        let wallet = LocalWallet::from_str(&self.secret).context("invalid private key")?;
+        Ok(Some(wallet.address()))
+    }
+
+    async fn sign_message(&self, message: &[u8]) -> Result<Vec<u8>> {
+        // `sign_transaction` above already signs its bytes as an EIP-191 message
+        self.sign_transaction(message).await
+    }
 }
 
 /// Remote signer interface (HSM/KMS). Implement this for a client that talks to
@@ -1,17 +1,29 @@
 use anyhow::{Result, Context};
-use ethers_providers::{Provider, Http, Middleware};
-use ethers_core::types::{Address, Bytes, transaction::eip2718::TypedTransaction, U256, transaction::eip2718::TypedTransaction as TTx, TransactionReceipt};
+use ethers_providers::{Provider, Http, HttpClientError, JsonRpcClient, Middleware};
+use ethers_core::types::{Address, Bytes, transaction::eip2718::TypedTransaction, U256, transaction::eip2718::TypedTransaction as TTx, TransactionReceipt, H256, U64, BlockNumber, transaction::eip2930::AccessList};
+use ethers_core::utils::keccak256;
 use crate::signer::Signer;
+use crate::l2fee::L1FeeParams;
+use crate::tx::BidStrategy;
+use crate::nonce::{NonceLease, NonceManager};
+use crate::executor::CallBundleResponse;
+use crate::retarget::SLOT_SECS;
+use crate::revert::{CallFrame, RevertDecoder, RevertReason};
+use std::collections::BTreeMap;
 use std::convert::TryInto;
+use std::str::FromStr;
 use std::time::Duration;
-use futures_util::stream::{FuturesUnordered, StreamExt};
-use tokio::sync::Semaphore;
 use std::sync::Arc;
 
 /// Scorer for simulated bundles. Returns a signed 128-bit score (higher is better).
 pub trait Scorer: Send + Sync {
     /// Score receipts and optional expected pnl per tx. Returns a signed i128 value (higher is better).
     fn score(&self, receipts: &[TransactionReceipt], signed_txs: &[Vec<u8>], expected_pnl: Option<&[i128]>) -> i128;
+
+    /// Score a simulation that also recorded balance changes. Defaults to `score` on the receipts.
+    fn score_outcome(&self, outcome: &SimulationOutcome, signed_txs: &[Vec<u8>]) -> i128 {
+        self.score(&outcome.receipts, signed_txs, None)
+    }
 }
 
 /// Default scorer: penalize reverts heavily, otherwise score is negative gas cost.
@@ -40,23 +52,112 @@ impl Scorer for GasCostScorer {
         }
         total
     }
+
+    fn score_outcome(&self, outcome: &SimulationOutcome, signed_txs: &[Vec<u8>]) -> i128 {
+        let score = self.score(&outcome.receipts, signed_txs, None);
+        if score == i128::MIN / 4 && let Some((i, reason)) = outcome.first_revert() {
+            tracing::debug!("candidate rejected: tx {} reverted with {}", i, reason);
+        }
+        score
+    }
+}
+
+/// Gas cost scorer for OP-stack chains: like `GasCostScorer`, but also charges the
+/// L1 data fee of each signed tx, which usually dominates cost on Optimism and Base.
+pub struct L2GasCostScorer {
+    pub l1_fee_params: L1FeeParams,
+}
+
+impl L2GasCostScorer {
+    pub fn new(l1_fee_params: L1FeeParams) -> Self {
+        Self { l1_fee_params }
+    }
+}
+
+impl Scorer for L2GasCostScorer {
+    fn score(&self, receipts: &[TransactionReceipt], signed_txs: &[Vec<u8>], expected_pnl: Option<&[i128]>) -> i128 {
+        let mut total = GasCostScorer.score(receipts, signed_txs, expected_pnl);
+        if total == i128::MIN / 4 {
+            return total;
+        }
+        for raw in signed_txs.iter().take(receipts.len()) {
+            total = total.saturating_sub(u256_to_i128_clamped(self.l1_fee_params.l1_fee(raw)));
+        }
+        total
+    }
 }
 
 /// Configurable scorer with weights for revert penalty, gas cost and expected P&L.
+/// When `l1_fee_params` is set, the OP-stack L1 data fee is counted as gas cost.
+/// When `bid_strategy` is set, the builder payment it implies is deducted from P&L.
+/// This is the `configurable` preset of `scoring::ScorerConfig`, which composes scorers.
 pub struct ConfigurableScorer {
     pub revert_penalty: i128,
     pub gas_weight: i128,
     pub pnl_weight: i128,
+    pub l1_fee_params: Option<L1FeeParams>,
+    pub bid_strategy: Option<BidStrategy>,
+}
+
+/// Bundle profit before and after the builder bid, in wei.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ProfitBreakdown {
+    /// Sum of expected P&L across txs
+    pub gross_profit: i128,
+    /// Gas (and L1 data fee) paid by the bundle
+    pub gas_cost: i128,
+    /// Coinbase payment implied by the bid strategy on `gross_profit - gas_cost`
+    pub bid: i128,
+    /// `gross_profit - gas_cost - bid`
+    pub net_profit: i128,
 }
 
 impl ConfigurableScorer {
     pub fn new(revert_penalty: i128, gas_weight: i128, pnl_weight: i128) -> Self {
-        Self { revert_penalty, gas_weight, pnl_weight }
+        Self { revert_penalty, gas_weight, pnl_weight, l1_fee_params: None, bid_strategy: None }
+    }
+
+    /// Include the L1 data fee of each signed tx in the gas cost (OP-stack chains).
+    pub fn with_l1_fee_params(mut self, params: L1FeeParams) -> Self {
+        self.l1_fee_params = Some(params);
+        self
+    }
+
+    /// Deduct a coinbase-transfer bid, sized from simulated profit, from the score.
+    pub fn with_bid_strategy(mut self, strategy: BidStrategy) -> Self {
+        self.bid_strategy = Some(strategy);
+        self
+    }
+
+    fn tx_cost(&self, receipt: &TransactionReceipt, raw: Option<&Vec<u8>>) -> i128 {
+        let cost = receipt.gas_used.unwrap_or_default().saturating_mul(receipt.effective_gas_price.unwrap_or_default());
+        let mut cost_i128 = match <ethers_core::types::U256 as TryInto<u128>>::try_into(cost) {
+            Ok(v) => v as i128,
+            Err(_) => i128::MAX / 8,
+        };
+        if let (Some(params), Some(raw)) = (self.l1_fee_params.as_ref(), raw) {
+            cost_i128 = cost_i128.saturating_add(u256_to_i128_clamped(params.l1_fee(raw)));
+        }
+        cost_i128
+    }
+
+    /// Unweighted profit of a simulated bundle and the builder bid it implies.
+    pub fn profit_breakdown(&self, receipts: &[TransactionReceipt], signed_txs: &[Vec<u8>], expected_pnl: Option<&[i128]>) -> ProfitBreakdown {
+        let gas_cost = receipts.iter().enumerate().fold(0i128, |acc, (i, r)| acc.saturating_add(self.tx_cost(r, signed_txs.get(i))));
+        let gross_profit = expected_pnl
+            .map(|pnls| pnls.iter().take(receipts.len()).fold(0i128, |acc, p| acc.saturating_add(*p)))
+            .unwrap_or(0);
+        let before_bid = gross_profit.saturating_sub(gas_cost);
+        let bid = match (&self.bid_strategy, before_bid > 0) {
+            (Some(strategy), true) => u256_to_i128_clamped(strategy.bid_for(U256::from(before_bid as u128))),
+            _ => 0,
+        };
+        ProfitBreakdown { gross_profit, gas_cost, bid, net_profit: before_bid - bid }
     }
 }
 
 impl Scorer for ConfigurableScorer {
-    fn score(&self, receipts: &[TransactionReceipt], _signed_txs: &[Vec<u8>], expected_pnl: Option<&[i128]>) -> i128 {
+    fn score(&self, receipts: &[TransactionReceipt], signed_txs: &[Vec<u8>], expected_pnl: Option<&[i128]>) -> i128 {
         let mut total: i128 = 0;
         for (i, r) in receipts.iter().enumerate() {
             if let Some(status) = r.status {
@@ -65,155 +166,625 @@ impl Scorer for ConfigurableScorer {
                     // continue to aggregate gas cost even on revert
                 }
             }
-            let gas_used = r.gas_used.unwrap_or_default();
-            let gas_price = r.effective_gas_price.unwrap_or_default();
-            let cost = gas_used.saturating_mul(gas_price);
-            let cost_i128 = match <ethers_core::types::U256 as TryInto<u128>>::try_into(cost) {
-                Ok(v) => v as i128,
-                Err(_) => i128::MAX / 8,
-            };
-            total -= cost_i128 * self.gas_weight;
+            total -= self.tx_cost(r, signed_txs.get(i)) * self.gas_weight;
             if let Some(pnls) = expected_pnl {
                 if i < pnls.len() {
                     total += pnls[i] * self.pnl_weight;
                 }
             }
         }
+        if self.bid_strategy.is_some() {
+            total -= self.profit_breakdown(receipts, signed_txs, expected_pnl).bid * self.pnl_weight;
+        }
         total
     }
 }
 
+/// Asset whose balance is tracked during simulation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Token {
+    Native,
+    Erc20(Address),
+}
+
+/// Accounts and tokens whose balances are recorded around a simulated bundle.
+/// `accounts` are ours (executor contract, EOA) and make up realized PnL; include the
+/// EOA so gas is counted. `coinbase` is tracked separately to see what the builder got.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BalanceWatch {
+    pub accounts: Vec<Address>,
+    pub tokens: Vec<Address>,
+    pub coinbase: Option<Address>,
+}
+
+impl BalanceWatch {
+    pub fn new(accounts: Vec<Address>) -> Self {
+        Self { accounts, ..Default::default() }
+    }
+
+    pub fn with_tokens(mut self, tokens: Vec<Address>) -> Self {
+        self.tokens = tokens;
+        self
+    }
+
+    pub fn with_coinbase(mut self, coinbase: Address) -> Self {
+        self.coinbase = Some(coinbase);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty() && self.coinbase.is_none()
+    }
+
+    /// Every (account, token) pair to read: all tokens for our accounts, native only for coinbase.
+    pub fn entries(&self) -> Vec<(Address, Token)> {
+        let mut entries = Vec::new();
+        for account in &self.accounts {
+            entries.push((*account, Token::Native));
+            entries.extend(self.tokens.iter().map(|t| (*account, Token::Erc20(*t))));
+        }
+        entries.extend(self.coinbase.map(|c| (c, Token::Native)));
+        entries
+    }
+}
+
+/// Balance of one account in one token before and after the bundle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BalanceChange {
+    pub account: Address,
+    pub token: Token,
+    pub before: U256,
+    pub after: U256,
+}
+
+impl BalanceChange {
+    pub fn delta(&self) -> i128 {
+        if self.after >= self.before {
+            u256_to_i128_clamped(self.after - self.before)
+        } else {
+            -u256_to_i128_clamped(self.before - self.after)
+        }
+    }
+}
+
+/// Receipts plus the balance changes a bundle caused and why any tx failed.
+#[derive(Debug, Clone, Default)]
+pub struct SimulationOutcome {
+    pub receipts: Vec<TransactionReceipt>,
+    pub balances: Vec<BalanceChange>,
+    /// Net change per token across `BalanceWatch::accounts`
+    pub pnl: std::collections::BTreeMap<Token, i128>,
+    /// Native balance change of the watched coinbase
+    pub coinbase_delta: Option<i128>,
+    /// Decoded revert reason per tx (`None` for successful txs or when unavailable)
+    pub reverts: Vec<Option<RevertReason>>,
+    /// Call trace per tx, when the backend is configured to keep them
+    pub traces: Vec<Option<CallFrame>>,
+}
+
+impl SimulationOutcome {
+    pub fn new(receipts: Vec<TransactionReceipt>, balances: Vec<BalanceChange>, watch: &BalanceWatch) -> Self {
+        let mut pnl = std::collections::BTreeMap::new();
+        let mut coinbase_delta = None;
+        for change in &balances {
+            if watch.accounts.contains(&change.account) {
+                let entry = pnl.entry(change.token).or_insert(0i128);
+                *entry = entry.saturating_add(change.delta());
+            } else if Some(change.account) == watch.coinbase {
+                coinbase_delta = Some(change.delta());
+            }
+        }
+        Self { receipts, balances, pnl, coinbase_delta, ..Default::default() }
+    }
+
+    pub fn with_diagnostics(mut self, reverts: Vec<Option<RevertReason>>, traces: Vec<Option<CallFrame>>) -> Self {
+        self.reverts = reverts;
+        self.traces = traces;
+        self
+    }
+
+    /// Index and reason of the first reverted tx with a known reason.
+    pub fn first_revert(&self) -> Option<(usize, &RevertReason)> {
+        self.reverts.iter().enumerate().find_map(|(i, r)| r.as_ref().map(|r| (i, r)))
+    }
+
+    /// Realized profit in the native token, gas included if the EOA is watched.
+    pub fn native_pnl(&self) -> i128 {
+        self.pnl.get(&Token::Native).copied().unwrap_or(0)
+    }
+
+    pub fn reverted(&self) -> usize {
+        self.receipts.iter().filter(|r| r.status.map(|s| s.as_u64() == 0).unwrap_or(false)).count()
+    }
+}
+
+/// Scores on realized native-token profit from simulated balance changes, minus
+/// `revert_penalty` per reverted tx. Without an outcome it falls back to expected P&L less gas.
+pub struct ProfitScorer {
+    pub revert_penalty: i128,
+}
+
+impl ProfitScorer {
+    pub fn new(revert_penalty: i128) -> Self {
+        Self { revert_penalty }
+    }
+}
+
+impl Scorer for ProfitScorer {
+    fn score(&self, receipts: &[TransactionReceipt], signed_txs: &[Vec<u8>], expected_pnl: Option<&[i128]>) -> i128 {
+        ConfigurableScorer::new(self.revert_penalty, 1, 1).score(receipts, signed_txs, expected_pnl)
+    }
+
+    fn score_outcome(&self, outcome: &SimulationOutcome, _signed_txs: &[Vec<u8>]) -> i128 {
+        outcome.native_pnl().saturating_sub(self.revert_penalty.saturating_mul(outcome.reverted() as i128))
+    }
+}
+
+/// Calldata for ERC-20 `balanceOf(account)`.
+pub(crate) fn balance_of_calldata(account: Address) -> Vec<u8> {
+    let mut data = ethers_core::utils::id("balanceOf(address)").to_vec();
+    data.extend_from_slice(&ethers_core::abi::encode(&[ethers_core::abi::Token::Address(account)]));
+    data
+}
+
+/// Decode a `balanceOf` return value.
+pub(crate) fn decode_balance(token: Address, out: &[u8]) -> Result<U256> {
+    if out.len() < 32 {
+        return Err(anyhow::anyhow!("{:?}.balanceOf returned {} bytes", token, out.len()));
+    }
+    Ok(U256::from_big_endian(&out[..32]))
+}
+
+/// A difference between a relay's `eth_callBundle` result and local receipts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SimulationMismatch {
+    /// Relay and local simulation executed a different number of txs
+    TxCount { relay: usize, local: usize },
+    /// Txs at `index` are not the same transaction
+    TxHash { index: usize, relay: H256, local: H256 },
+    /// One side reverted and the other did not
+    Status { index: usize, relay_reverted: bool, local_reverted: bool, relay_error: Option<String> },
+    /// Both succeeded but used different amounts of gas
+    GasUsed { index: usize, relay: u64, local: u64 },
+}
+
+/// Compare a relay simulation against `Simulator::simulate_signed_bundle` receipts,
+/// tx by tx. An empty result means both agree on hashes, statuses and gas used.
+pub fn diff_relay_simulation(relay: &CallBundleResponse, local: &[TransactionReceipt]) -> Vec<SimulationMismatch> {
+    let mut mismatches = Vec::new();
+    if relay.results.len() != local.len() {
+        mismatches.push(SimulationMismatch::TxCount { relay: relay.results.len(), local: local.len() });
+    }
+    for (index, (r, l)) in relay.results.iter().zip(local.iter()).enumerate() {
+        if r.tx_hash != l.transaction_hash {
+            mismatches.push(SimulationMismatch::TxHash { index, relay: r.tx_hash, local: l.transaction_hash });
+            continue;
+        }
+        let local_reverted = l.status.map(|s| s.as_u64() == 0).unwrap_or(false);
+        if r.is_revert() != local_reverted {
+            mismatches.push(SimulationMismatch::Status {
+                index,
+                relay_reverted: r.is_revert(),
+                local_reverted,
+                relay_error: r.revert.clone().or_else(|| r.error.clone()),
+            });
+            continue;
+        }
+        let local_gas = l.gas_used.unwrap_or_default().low_u64();
+        if r.gas_used != local_gas {
+            mismatches.push(SimulationMismatch::GasUsed { index, relay: r.gas_used, local: local_gas });
+        }
+    }
+    mismatches
+}
+
+/// Per-account `eth_call` state override.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountOverride {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance: Option<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<U64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<Bytes>,
+    /// Slots to patch; the rest of the account's storage is kept
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub state_diff: BTreeMap<H256, H256>,
+}
+
+/// The `stateOverride` set of an `eth_call`, keyed by account.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+#[serde(transparent)]
+pub struct StateOverrides(pub BTreeMap<Address, AccountOverride>);
+
+impl StateOverrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn account(&mut self, address: Address) -> &mut AccountOverride {
+        self.0.entry(address).or_default()
+    }
+
+    pub fn with_balance(mut self, address: Address, balance: U256) -> Self {
+        self.account(address).balance = Some(balance);
+        self
+    }
+
+    pub fn with_code(mut self, address: Address, code: Bytes) -> Self {
+        self.account(address).code = Some(code);
+        self
+    }
+
+    pub fn with_storage(mut self, address: Address, slot: H256, value: H256) -> Self {
+        self.account(address).state_diff.insert(slot, value);
+        self
+    }
+
+    /// Give `holder` a token balance, writing `balances[holder]` for a Solidity token whose
+    /// balance mapping sits at storage slot `balance_slot` (0 for OpenZeppelin ERC20,
+    /// 3 for WETH9). Vyper and proxy tokens lay storage out differently.
+    pub fn with_erc20_balance(self, token: Address, holder: Address, amount: U256, balance_slot: u64) -> Self {
+        let slot = mapping_slot(holder.into(), H256::from_low_u64_be(balance_slot));
+        self.with_storage(token, slot, u256_to_h256(amount))
+    }
+
+    /// Approve `spender` for `amount` of `owner`'s tokens, writing `allowance[owner][spender]`
+    /// with the allowance mapping at `allowance_slot` (1 for OpenZeppelin ERC20, 4 for WETH9).
+    pub fn with_erc20_allowance(self, token: Address, owner: Address, spender: Address, amount: U256, allowance_slot: u64) -> Self {
+        let inner = mapping_slot(owner.into(), H256::from_low_u64_be(allowance_slot));
+        let slot = mapping_slot(spender.into(), inner);
+        self.with_storage(token, slot, u256_to_h256(amount))
+    }
+}
+
+/// Storage slot of `mapping[key]` for a Solidity mapping declared at `slot`.
+pub fn mapping_slot(key: H256, slot: H256) -> H256 {
+    let mut buf = [0u8; 64];
+    buf[..32].copy_from_slice(key.as_bytes());
+    buf[32..].copy_from_slice(slot.as_bytes());
+    H256(keccak256(buf))
+}
+
+fn u256_to_h256(v: U256) -> H256 {
+    let mut out = [0u8; 32];
+    v.to_big_endian(&mut out);
+    H256(out)
+}
+
+/// The `blockOverrides` of an `eth_call`: the header fields the call sees.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockOverrides {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number: Option<U64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time: Option<U64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_fee: Option<U256>,
+}
+
+impl BlockOverrides {
+    pub fn with_number(mut self, number: u64) -> Self {
+        self.number = Some(number.into());
+        self
+    }
+
+    pub fn with_time(mut self, timestamp: u64) -> Self {
+        self.time = Some(timestamp.into());
+        self
+    }
+
+    pub fn with_base_fee(mut self, base_fee: U256) -> Self {
+        self.base_fee = Some(base_fee);
+        self
+    }
+}
+
+/// Result of a single `eth_call`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CallResult {
+    Success(Bytes),
+    Revert(RevertReason),
+}
+
+/// Outcome of trying an EIP-2930 access list on one tx; all zero for a legacy tx.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccessListGain {
+    /// `eth_estimateGas` without an access list
+    pub gas_without: u64,
+    /// `eth_estimateGas` with the generated list attached, including its intrinsic cost
+    pub gas_with: u64,
+    pub addresses: usize,
+    pub slots: usize,
+    /// The list was attached (it lowered gas)
+    pub attached: bool,
+}
+
+impl AccessListGain {
+    /// Gas saved by the attached list; zero when it was not attached.
+    pub fn saving(&self) -> u64 {
+        if self.attached { self.gas_without.saturating_sub(self.gas_with) } else { 0 }
+    }
+}
+
+/// A later inclusion than the next block: `blocks` extra blocks pass, one slot each, and
+/// `pending` txs expected to land first are replayed before the bundle.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InclusionDelay {
+    pub blocks: u64,
+    /// Base fee of the inclusion block; `None` keeps the backend's next base fee
+    pub base_fee: Option<U256>,
+    /// Signed txs replayed before the bundle; ones that fail to apply are skipped
+    pub pending: Vec<Vec<u8>>,
+}
+
+/// Something that can execute a signed bundle on top of current state and return one
+/// receipt per tx for a `Scorer`, without persisting the bundle's effects.
+/// Implementors provide `simulate_bundle_delayed`; the other methods are its zero-delay cases.
+#[async_trait::async_trait]
+pub trait SimulationBackend: Send + Sync {
+    async fn simulate_bundle(&self, signed_raw_txs: &[Vec<u8>], next_base_fee: Option<U256>) -> Result<Vec<TransactionReceipt>> {
+        Ok(self.simulate_bundle_with_balances(signed_raw_txs, next_base_fee, &BalanceWatch::default()).await?.receipts)
+    }
+
+    /// Like `simulate_bundle`, also recording the balances in `watch` before and after.
+    async fn simulate_bundle_with_balances(&self, signed_raw_txs: &[Vec<u8>], next_base_fee: Option<U256>, watch: &BalanceWatch) -> Result<SimulationOutcome> {
+        let delay = InclusionDelay { base_fee: next_base_fee, ..Default::default() };
+        self.simulate_bundle_delayed(signed_raw_txs, &delay, watch).await
+    }
+
+    /// Like `simulate_bundle_with_balances`, but included `delay.blocks` blocks later.
+    async fn simulate_bundle_delayed(&self, signed_raw_txs: &[Vec<u8>], delay: &InclusionDelay, watch: &BalanceWatch) -> Result<SimulationOutcome>;
+}
+
 /// Simulation / backtesting helper backed by a forked node (Anvil/Hardhat).
 #[derive(Clone)]
 pub struct Simulator {
     rpc: String,
+    revert_decoder: Arc<RevertDecoder>,
+    call_traces: bool,
 }
 
 impl Simulator {
     pub fn new() -> Self {
         let rpc = std::env::var("ANVIL_RPC_URL").unwrap_or_else(|_| "http://127.0.0.1:8545".to_string());
-        Self { rpc }
+        Self::with_rpc(rpc)
+    }
+
+    pub fn with_rpc(rpc: String) -> Self {
+        Self { rpc, revert_decoder: Arc::new(RevertDecoder::new()), call_traces: false }
+    }
+
+    /// Decode custom errors from these ABIs when a simulated tx reverts.
+    pub fn with_revert_decoder(mut self, decoder: RevertDecoder) -> Self {
+        self.revert_decoder = Arc::new(decoder);
+        self
+    }
+
+    /// Keep a `callTracer` trace of every simulated tx (reverted txs are always traced).
+    pub fn with_call_traces(mut self, enabled: bool) -> Self {
+        self.call_traces = enabled;
+        self
     }
 
     /// Simulate a bundle by taking a snapshot, sending each signed raw tx (in order),
     /// waiting for receipts, then reverting to snapshot to avoid affecting persistent state.
     pub async fn simulate_signed_bundle(&self, signed_raw_txs: &[Vec<u8>], set_next_block_base_fee: Option<U256>) -> Result<Vec<TransactionReceipt>> {
+        Ok(self.simulate_signed_bundle_with_balances(signed_raw_txs, set_next_block_base_fee, &BalanceWatch::default()).await?.receipts)
+    }
+
+    /// `simulate_signed_bundle`, reading the balances in `watch` right after the snapshot
+    /// and again after the last receipt, before reverting.
+    pub async fn simulate_signed_bundle_with_balances(&self, signed_raw_txs: &[Vec<u8>], set_next_block_base_fee: Option<U256>, watch: &BalanceWatch) -> Result<SimulationOutcome> {
+        let delay = InclusionDelay { base_fee: set_next_block_base_fee, ..Default::default() };
+        self.simulate_signed_bundle_delayed(signed_raw_txs, &delay, watch).await
+    }
+
+    /// `simulate_signed_bundle_with_balances` after mining `delay.blocks` empty blocks one
+    /// slot apart and replaying `delay.pending`. Anvil automines, so each replayed tx takes
+    /// a block of its own; the base fee override applies to the bundle's first block.
+    pub async fn simulate_signed_bundle_delayed(&self, signed_raw_txs: &[Vec<u8>], delay: &InclusionDelay, watch: &BalanceWatch) -> Result<SimulationOutcome> {
         let provider = Provider::<Http>::try_from(self.rpc.as_str()).context("invalid rpc url")?;
 
         // Create snapshot
         let snap_id: serde_json::Value = provider.request("evm_snapshot", ()).await.context("snapshot failed")?;
 
-        // Optionally set the next block's base fee for the simulation
-        if let Some(bf) = set_next_block_base_fee {
-            let bf_hex = format!("0x{:x}", bf);
-            let _r: serde_json::Value = provider.request("evm_setNextBlockBaseFeePerGas", vec![bf_hex]).await.context("set base fee failed")?;
-        }
+        let outcome = async {
+            if delay.blocks > 0 {
+                let params = vec![format!("0x{:x}", delay.blocks), format!("0x{:x}", SLOT_SECS)];
+                let _r: serde_json::Value = provider.request("anvil_mine", params).await.context("anvil_mine failed")?;
+            }
+            for (i, raw) in delay.pending.iter().enumerate() {
+                match provider.send_raw_transaction(Bytes::from(raw.clone())).await {
+                    Ok(pending) => {
+                        if let Err(e) = tokio::time::timeout(Duration::from_secs(10), pending).await {
+                            tracing::warn!("pending tx {} not mined: {:?}", i, e);
+                        }
+                    }
+                    Err(e) => tracing::debug!("pending tx {} skipped: {:?}", i, e),
+                }
+            }
+
+            // Optionally set the next block's base fee for the simulation
+            if let Some(bf) = delay.base_fee {
+                let bf_hex = format!("0x{:x}", bf);
+                let _r: serde_json::Value = provider.request("evm_setNextBlockBaseFeePerGas", vec![bf_hex]).await.context("set base fee failed")?;
+            }
+
+            let before = read_balances(&provider, watch).await?;
+            let mut results = Vec::new();
 
-        let mut results = Vec::new();
+            for raw in signed_raw_txs.iter() {
+                let b = Bytes::from(raw.clone());
+                let pending = provider.send_raw_transaction(b).await.context("send_raw failed")?;
+                let receipt_opt = tokio::time::timeout(Duration::from_secs(10), pending).await.context("timeout awaiting tx")??;
+                let receipt = receipt_opt.ok_or_else(|| anyhow::anyhow!("no receipt returned"))?;
+                results.push(receipt);
+            }
+            let after = read_balances(&provider, watch).await?;
+            let (reverts, traces) = self.explain_receipts(&provider, &results).await;
+            anyhow::Ok(SimulationOutcome::new(results, balance_changes(watch, &before, &after), watch).with_diagnostics(reverts, traces))
+        }
+        .await;
 
-        for raw in signed_raw_txs.iter() {
-            let b = Bytes::from(raw.clone());
-            let pending = provider.send_raw_transaction(b).await.context("send_raw failed")?;
-            let receipt_opt = tokio::time::timeout(Duration::from_secs(10), pending).await.context("timeout awaiting tx")??;
-            let receipt = receipt_opt.ok_or_else(|| anyhow::anyhow!("no receipt returned"))?;
-            results.push(receipt);
+        // Revert snapshot to clean state, also when the simulation failed part way
+        let reverted = provider.request::<_, bool>("evm_revert", vec![snap_id]).await.context("revert failed");
+        match (outcome, reverted) {
+            (Ok(outcome), reverted) => reverted.map(|_| outcome),
+            (Err(e), Err(revert_err)) => {
+                tracing::warn!("{:#} after failed simulation", revert_err);
+                Err(e)
+            }
+            (Err(e), Ok(_)) => Err(e),
         }
-        // Revert snapshot to clean state
-        let _: bool = provider.request("evm_revert", vec![snap_id]).await.context("revert failed")?;
+    }
 
-        Ok(results)
+    /// Trace reverted txs (or all, with `call_traces`) to decode why they failed.
+    /// A node without `debug_traceTransaction` just leaves the reasons empty.
+    async fn explain_receipts(&self, provider: &Provider<Http>, receipts: &[TransactionReceipt]) -> (Vec<Option<RevertReason>>, Vec<Option<CallFrame>>) {
+        let mut reverts = Vec::with_capacity(receipts.len());
+        let mut traces = Vec::with_capacity(receipts.len());
+        for r in receipts {
+            let reverted = r.status.map(|s| s.as_u64() == 0).unwrap_or(false);
+            if !reverted && !self.call_traces {
+                reverts.push(None);
+                traces.push(None);
+                continue;
+            }
+            let params = serde_json::json!([r.transaction_hash, {"tracer": "callTracer"}]);
+            let frame = match provider.request::<_, serde_json::Value>("debug_traceTransaction", params).await {
+                Ok(v) => CallFrame::from_call_tracer(&v).map_err(|e| tracing::debug!("bad call trace for {:?}: {}", r.transaction_hash, e)).ok(),
+                Err(e) => {
+                    tracing::debug!("debug_traceTransaction unavailable for {:?}: {}", r.transaction_hash, e);
+                    None
+                }
+            };
+            reverts.push(frame.as_ref().filter(|_| reverted).map(|f| revert_from_frame(&self.revert_decoder, f)));
+            traces.push(frame.filter(|_| self.call_traces));
+        }
+        (reverts, traces)
     }
 
-    /// Simulate an unsigned bundle by trying multiple base nonces in parallel. For each offset in
-    /// `0..nonce_range` we assign nonce = base_nonce + offset for the first tx, and
-    /// increment by 1 for each subsequent transaction. We sign each nonce sequence
-    /// using `signer` and simulate the resulting signed bundle. The `concurrency` param
-    /// bounds concurrent attempts. Each attempt can optionally set the next block base fee
-    /// to `set_next_block_base_fee` for gas dynamics testing. Returns tuples of (nonce, score, receipts).
-    pub async fn simulate_unsigned_bundle_try_nonces_with_scorer<'a, S: Signer + ?Sized + Send + Sync + 'static, C: Scorer + ?Sized + Send + Sync + 'static>(
+    /// Reserve exact nonces for `unsigned_txs` from `nonces`, sign and simulate the bundle.
+    /// On success the caller owns the returned lease and must `confirm` or `release` it;
+    /// on failure the lease is released before returning the error.
+    pub async fn simulate_unsigned_bundle_with_nonce_manager<S: Signer + ?Sized, C: Scorer + ?Sized>(
         &self,
         unsigned_txs: &[TypedTransaction],
-        signer: std::sync::Arc<S>,
-        base_nonce: u64,
-        nonce_range: u64,
-        concurrency: usize,
-        scorer: std::sync::Arc<C>,
+        signer: &S,
+        nonces: &NonceManager,
+        scorer: &C,
         set_next_block_base_fee: Option<U256>,
-    ) -> Result<Vec<(u64, i128, Vec<TransactionReceipt>, Vec<Vec<u8>>)>> {
-        let sem = Arc::new(Semaphore::new(concurrency));
-        let mut futs = FuturesUnordered::new();
-
-        for offset in 0..nonce_range {
-            let permit = sem.clone().acquire_owned().await.unwrap();
-            let unsigned = unsigned_txs.to_vec();
-            let signer_cloned = signer.clone();
-            let scorer_cloned = scorer.clone();
-            let sim = self.clone();
-            let bf = set_next_block_base_fee;
-            futs.push(tokio::spawn(async move {
-                // drop permit when done
-                let _permit = permit;
-                // Build signed bundle for this offset
-                let mut signed_blob = Vec::new();
-                let mut current = base_nonce + offset;
-                for tx in unsigned.iter() {
-                    let tx_with_nonce = set_nonce_tx(&tx, U256::from(current));
-                    let signed = signer_cloned.sign_typed_transaction(&tx_with_nonce).await.map_err(|e| anyhow::anyhow!(e.to_string()))?;
-                    signed_blob.push(signed);
-                    current += 1;
-                }
-                // simulate
-                let receipts = sim.simulate_signed_bundle(&signed_blob, bf).await.map_err(|e| anyhow::anyhow!(e.to_string()))?;
-                let score = scorer_cloned.score(&receipts, &signed_blob, None);
-                Ok::<(u64, i128, Vec<TransactionReceipt>, Vec<Vec<u8>>), anyhow::Error>((base_nonce + offset, score, receipts, signed_blob))
-            }));
+    ) -> Result<(NonceLease, i128, Vec<TransactionReceipt>, Vec<Vec<u8>>)> {
+        let lease = nonces.reserve(unsigned_txs.len() as u64).await?;
+        let attempt = async {
+            let mut signed_blob = Vec::with_capacity(unsigned_txs.len());
+            for (tx, nonce) in unsigned_txs.iter().zip(lease.nonces()) {
+                let tx_with_nonce = set_nonce_tx(tx, U256::from(nonce));
+                signed_blob.push(signer.sign_typed_transaction(&tx_with_nonce).await?);
+            }
+            let receipts = self.simulate_signed_bundle(&signed_blob, set_next_block_base_fee).await?;
+            Ok::<_, anyhow::Error>((receipts, signed_blob))
+        };
+        match attempt.await {
+            Ok((receipts, signed_blob)) => {
+                let score = scorer.score(&receipts, &signed_blob, None);
+                Ok((lease, score, receipts, signed_blob))
+            }
+            Err(e) => {
+                nonces.release(&lease);
+                Err(e)
+            }
         }
+    }
 
-        let mut results = Vec::new();
-        while let Some(r) = futs.next().await {
-            match r {
-                Ok(Ok(tuple)) => results.push(tuple),
-                Ok(Err(e)) => tracing::warn!("attempt failed: {:?}", e),
-                Err(e) => tracing::warn!("task join error: {:?}", e),
-            }
+    /// Run `call` once with `eth_call` against `block` (default `latest`), with state and
+    /// header overrides. Nothing is signed, no nonce is used and no snapshot is taken, so
+    /// this is the cheap path for screening: fund and approve the executor with
+    /// `StateOverrides` instead of setting up real balances. Needs a node that accepts the
+    /// override arguments (geth, reth, Anvil).
+    pub async fn call_with_overrides(&self, call: &TypedTransaction, block: Option<BlockNumber>, state: &StateOverrides, block_overrides: Option<&BlockOverrides>) -> Result<CallResult> {
+        let transport = Http::from_str(&self.rpc).context("invalid rpc url")?;
+        let block = block.unwrap_or(BlockNumber::Latest);
+        let mut params = vec![serde_json::to_value(call)?, serde_json::to_value(block)?, serde_json::to_value(state)?];
+        if let Some(overrides) = block_overrides {
+            params.push(serde_json::to_value(overrides)?);
         }
+        match JsonRpcClient::request::<_, Bytes>(&transport, "eth_call", params).await {
+            Ok(out) => Ok(CallResult::Success(out)),
+            Err(HttpClientError::JsonRpcError(err)) => match revert_data(err.data.as_ref()) {
+                Some(data) => Ok(CallResult::Revert(self.revert_decoder.decode(&data))),
+                // geth reports a bare `revert()` without data
+                None if err.message.contains("execution reverted") => Ok(CallResult::Revert(RevertReason::Unknown(Bytes::default()))),
+                None => Err(anyhow::anyhow!("eth_call failed: {} ({})", err.message, err.code)),
+            },
+            Err(e) => Err(e).context("eth_call failed"),
+        }
+    }
 
-        // Sort by nonce
-        results.sort_by_key(|t| t.0);
-        Ok(results)
+    /// Generate an access list for `tx` with `eth_createAccessList` and attach it only if
+    /// `eth_estimateGas` with the list comes out lower than without it. Both sides are
+    /// estimates so the refund and 63/64 headroom cancel out; `gasUsed` from
+    /// `eth_createAccessList` is not comparable. Legacy txs cannot carry a list and are
+    /// returned unchanged without any RPC call.
+    pub async fn with_access_list(&self, tx: &TypedTransaction) -> Result<(TypedTransaction, AccessListGain)> {
+        if matches!(tx, TypedTransaction::Legacy(_)) {
+            return Ok((tx.clone(), AccessListGain::default()));
+        }
+        let provider = Provider::<Http>::try_from(self.rpc.as_str()).context("invalid rpc url")?;
+        let mut bare = tx.clone();
+        bare.set_access_list(AccessList::default());
+        let gas_without = provider.estimate_gas(&bare, None).await.context("estimate_gas failed")?.low_u64();
+        let generated = provider.create_access_list(&bare, None).await.context("eth_createAccessList failed")?;
+        let mut gain = AccessListGain {
+            gas_without,
+            gas_with: gas_without,
+            addresses: generated.access_list.0.len(),
+            slots: generated.access_list.0.iter().map(|item| item.storage_keys.len()).sum(),
+            attached: false,
+        };
+        if generated.access_list.0.is_empty() {
+            return Ok((tx.clone(), gain));
+        }
+        let mut listed = bare;
+        listed.set_access_list(generated.access_list);
+        gain.gas_with = provider.estimate_gas(&listed, None).await.context("estimate_gas with access list failed")?.low_u64();
+        if gain.gas_with < gas_without {
+            gain.attached = true;
+            return Ok((listed, gain));
+        }
+        Ok((tx.clone(), gain))
     }
 
-    /// Choose the best nonce strategy, return the signed bundle for submission, plus receipts and score.
-    pub async fn choose_best_nonce_strategy<S: Signer + ?Sized + Send + Sync + 'static, C: Scorer + ?Sized + Send + Sync + 'static>(
-        &self,
-        unsigned_txs: &[TypedTransaction],
-        signer: std::sync::Arc<S>,
-        base_nonce: u64,
-        nonce_range: u64,
-        concurrency: usize,
-        scorer: std::sync::Arc<C>,
-        set_next_block_base_fee: Option<U256>,
-    ) -> Result<Option<(u64, i128, Vec<Vec<u8>>, Vec<TransactionReceipt>)>> {
-        let results = self.simulate_unsigned_bundle_try_nonces_with_scorer(unsigned_txs, signer, base_nonce, nonce_range, concurrency, scorer, set_next_block_base_fee).await?;
-        // pick max scoring
-        let mut best: Option<(u64, i128, Vec<Vec<u8>>, Vec<TransactionReceipt>)> = None;
-        for (nonce, score, receipts, signed_blob) in results.into_iter() {
-            match &best {
-                None => best = Some((nonce, score, signed_blob, receipts)),
-                Some((_, best_score, _, _)) => {
-                    if score > *best_score {
-                        best = Some((nonce, score, signed_blob, receipts));
-                    }
+    /// `with_access_list` for every tx of a bundle. Lists are generated against the latest
+    /// state, without the effects of earlier bundle txs; a tx whose estimate fails (e.g. it
+    /// depends on an earlier tx) is kept as is with no gain reported.
+    pub async fn with_access_lists(&self, unsigned_txs: &[TypedTransaction]) -> (Vec<TypedTransaction>, Vec<Option<AccessListGain>>) {
+        let mut txs = Vec::with_capacity(unsigned_txs.len());
+        let mut gains = Vec::with_capacity(unsigned_txs.len());
+        for (index, tx) in unsigned_txs.iter().enumerate() {
+            match self.with_access_list(tx).await {
+                Ok((tx, gain)) => {
+                    tracing::debug!(index, saving = gain.saving(), attached = gain.attached, "access list");
+                    txs.push(tx);
+                    gains.push(Some(gain));
+                }
+                Err(e) => {
+                    tracing::debug!(index, "no access list: {:?}", e);
+                    txs.push(tx.clone());
+                    gains.push(None);
                 }
             }
         }
-        Ok(best)
+        (txs, gains)
     }
 
     /// Autosubmit a chosen signed bundle: prefer relay submission; if no relay configured, send raw txs sequentially to provider.
-    pub async fn autosubmit_signed_bundle(&self, signed_blob: &[Vec<u8>], relay_client: &crate::executor::RelayClient) -> Result<serde_json::Value> {
+    pub async fn autosubmit_signed_bundle(&self, signed_blob: &[Vec<u8>], relay_client: &dyn crate::executor::BundleRelay) -> Result<serde_json::Value> {
         // Try relay first
-        if let Ok(resp) = relay_client.submit_flashbots_bundle(signed_blob, None).await {
+        if let Ok(resp) = relay_client.send_bundle(signed_blob, None).await {
             return Ok(serde_json::json!({"relay": resp}));
         }
 
@@ -241,6 +812,73 @@ impl Simulator {
     }
 }
 
+/// Anvil backend: snapshot, send each tx, wait for receipts, revert.
+#[async_trait::async_trait]
+impl SimulationBackend for Simulator {
+    async fn simulate_bundle_delayed(&self, signed_raw_txs: &[Vec<u8>], delay: &InclusionDelay, watch: &BalanceWatch) -> Result<SimulationOutcome> {
+        self.simulate_signed_bundle_delayed(signed_raw_txs, delay, watch).await
+    }
+}
+
+async fn read_balances(provider: &Provider<Http>, watch: &BalanceWatch) -> Result<Vec<U256>> {
+    let mut balances = Vec::new();
+    for (account, token) in watch.entries() {
+        let balance = match token {
+            Token::Native => provider.get_balance(account, None).await.context("get_balance failed")?,
+            Token::Erc20(t) => {
+                let req = ethers_core::types::TransactionRequest::new().to(t).data(Bytes::from(balance_of_calldata(account)));
+                let out = provider.call(&TypedTransaction::Legacy(req), None).await.context("balanceOf failed")?;
+                decode_balance(t, &out)?
+            }
+        };
+        balances.push(balance);
+    }
+    Ok(balances)
+}
+
+/// Revert reason of a failed top-level call frame.
+fn revert_from_frame(decoder: &RevertDecoder, frame: &CallFrame) -> RevertReason {
+    match frame.error.as_deref() {
+        Some(err) if frame.output.is_empty() && err != "execution reverted" => RevertReason::Halt(err.to_string()),
+        _ => decoder.decode(&frame.output),
+    }
+}
+
+/// Revert data carried in a JSON-RPC error: a hex string, or `{ "data": "0x.." }` on some nodes.
+fn revert_data(data: Option<&serde_json::Value>) -> Option<Bytes> {
+    let data = data?;
+    let hex = data.as_str().or_else(|| data.get("data")?.as_str())?;
+    hex.parse().ok()
+}
+
+/// Pair up balances read for `watch.entries()` before and after a bundle.
+pub(crate) fn balance_changes(watch: &BalanceWatch, before: &[U256], after: &[U256]) -> Vec<BalanceChange> {
+    watch
+        .entries()
+        .into_iter()
+        .zip(before.iter().zip(after.iter()))
+        .map(|((account, token), (before, after))| BalanceChange { account, token, before: *before, after: *after })
+        .collect()
+}
+
+/// Convert a wei amount to i128, clamping like the scorers do for oversized costs.
+fn u256_to_i128_clamped(v: U256) -> i128 {
+    match <U256 as TryInto<u128>>::try_into(v) {
+        Ok(v) => v.min(i128::MAX as u128) as i128,
+        Err(_) => i128::MAX / 8,
+    }
+}
+
+/// Sign `unsigned_txs` with consecutive nonces starting at `nonce`.
+pub(crate) async fn sign_from_nonce<S: Signer + ?Sized>(unsigned_txs: &[TypedTransaction], signer: &S, nonce: u64) -> Result<Vec<Vec<u8>>> {
+    let mut signed_blob = Vec::with_capacity(unsigned_txs.len());
+    for (tx, nonce) in unsigned_txs.iter().zip(nonce..) {
+        let tx_with_nonce = set_nonce_tx(tx, U256::from(nonce));
+        signed_blob.push(signer.sign_typed_transaction(&tx_with_nonce).await?);
+    }
+    Ok(signed_blob)
+}
+
 fn set_nonce_tx(tx: &TypedTransaction, nonce: U256) -> TypedTransaction {
     match tx.clone() {
         TypedTransaction::Eip1559(req) => {
@@ -255,18 +893,18 @@ fn set_nonce_tx(tx: &TypedTransaction, nonce: U256) -> TypedTransaction {
 mod tests {
     use super::*;
     use crate::tx::build_eip1559_tx;
-    use crate::signer::{BasicEnvSigner, Signer};
+    use crate::signer::BasicEnvSigner;
     use ethers_core::types::{U256, Address, Bytes, transaction::eip2718::TypedTransaction};
 
     #[tokio::test]
     #[ignore]
-    async fn simulate_unsigned_bundle_on_anvil_try_nonces() {
+    async fn bid_search_on_anvil() {
         let sim = Simulator::new();
         // requires env PRIVATE_KEY and anvil running
         let private = match std::env::var("PRIVATE_KEY") {
             Ok(v) => v,
             Err(_) => {
-                eprintln!("Skipping simulate_unsigned_bundle_on_anvil_try_nonces: set PRIVATE_KEY without 0x and run Anvil");
+                eprintln!("Skipping bid_search_on_anvil: set PRIVATE_KEY without 0x and run Anvil");
                 return;
             }
         };
@@ -278,7 +916,7 @@ mod tests {
         let base_nonce = provider.get_transaction_count(addr, None).await.unwrap().as_u64();
 
         let chain_id = provider.get_chainid().await.unwrap().as_u64();
-        let tx = build_eip1559_tx(
+        let build = |_size: U256| Ok(vec![build_eip1559_tx(
             U256::from(0u64),
             Address::zero(),
             U256::from(0u64),
@@ -287,18 +925,16 @@ mod tests {
             U256::from(1_000_000_000u64),
             U256::from(100_000_000_000u64),
             chain_id,
-        );
+        )]);
 
-        let unsigned: Vec<TypedTransaction> = vec![tx];
+        let gwei = U256::from(1_000_000_000u64);
+        let fees = crate::fees::FeeRecommendation { predicted_base_fee: gwei, max_fee_per_gas: gwei * 100, max_priority_fee_per_gas: gwei };
+        let search = crate::bid_search::BidSearch::new(fees, base_nonce, chain_id, Address::zero(), BalanceWatch::new(vec![addr]))
+            .with_priority_fees(vec![gwei, gwei * 2, gwei * 3]);
         let signer = BasicEnvSigner::from_secret(private);
-        let signer_arc = std::sync::Arc::new(signer);
-
-        let outcomes = sim.simulate_unsigned_bundle_try_nonces_with_scorer(&unsigned, signer_arc.clone(), base_nonce, 3, 2, std::sync::Arc::new(GasCostScorer), None).await.unwrap();
-        assert!(outcomes.len() == 3);
-
-        // choose best
-        let best = sim.choose_best_nonce_strategy(&unsigned, signer_arc.clone(), base_nonce, 3, 2, std::sync::Arc::new(GasCostScorer), None).await.unwrap();
-        assert!(best.is_some());
+        let model = crate::bid_search::TipInclusionModel::new(gwei);
+        let candidates = search.run(&sim, &signer, &ProfitScorer::new(0), &model, build).await.unwrap();
+        assert!(candidates.len() == 3);
     }
 
     #[test]
@@ -316,4 +952,273 @@ mod tests {
             _ => panic!("expected eip1559"),
         }
     }
+
+    #[test]
+    fn l2_gas_cost_scorer_charges_l1_data_fee() {
+        let params = L1FeeParams::Bedrock {
+            l1_base_fee: U256::from(1_000u64),
+            overhead: U256::from(0u64),
+            scalar: U256::from(1u64),
+            decimals: U256::from(0u64),
+        };
+        let receipt = TransactionReceipt {
+            status: Some(1u64.into()),
+            gas_used: Some(U256::from(21000u64)),
+            effective_gas_price: Some(U256::from(1u64)),
+            ..Default::default()
+        };
+        let signed = vec![vec![1u8; 10]];
+        let l1 = GasCostScorer.score(std::slice::from_ref(&receipt), &signed, None);
+        let l2 = L2GasCostScorer::new(params).score(std::slice::from_ref(&receipt), &signed, None);
+        // 10 non-zero bytes * 16 gas * 1000 wei
+        assert_eq!(l1 - l2, 160_000);
+
+        let configurable = ConfigurableScorer::new(0, 1, 0).with_l1_fee_params(params);
+        assert_eq!(configurable.score(&[receipt], &signed, None), l2);
+    }
+
+    #[test]
+    fn configurable_scorer_deducts_coinbase_bid() {
+        let receipt = TransactionReceipt {
+            status: Some(1u64.into()),
+            gas_used: Some(U256::from(100u64)),
+            effective_gas_price: Some(U256::from(10u64)),
+            ..Default::default()
+        };
+        let receipts = vec![receipt.clone(), receipt];
+        let pnl = [11_000i128, 0];
+        let scorer = ConfigurableScorer::new(0, 1, 1);
+        assert_eq!(scorer.score(&receipts, &[], Some(&pnl)), 9_000);
+
+        let scorer = scorer.with_bid_strategy(BidStrategy::Percentage { bps: 9_000 });
+        let breakdown = scorer.profit_breakdown(&receipts, &[], Some(&pnl));
+        assert_eq!(breakdown, ProfitBreakdown { gross_profit: 11_000, gas_cost: 2_000, bid: 8_100, net_profit: 900 });
+        assert_eq!(scorer.score(&receipts, &[], Some(&pnl)), 900);
+
+        // nothing is bid on an unprofitable bundle
+        assert_eq!(scorer.profit_breakdown(&receipts, &[], Some(&[1_000, 0])).bid, 0);
+    }
+
+    #[tokio::test]
+    async fn nonce_manager_lease_released_when_simulation_fails() {
+        let server = httpmock::MockServer::start();
+        server.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/").body_contains("eth_getTransactionCount");
+            then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"result":"0x7"}"#);
+        });
+        server.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/").body_contains("evm_snapshot");
+            then.status(500);
+        });
+        let sim = Simulator::with_rpc(server.url("/"));
+        let nonces = NonceManager::new(server.url("/"), Address::zero());
+        let signer = crate::revm_backend::fixtures::signer();
+        let tx = build_eip1559_tx(U256::zero(), Address::zero(), U256::zero(), Bytes::from(vec![]), U256::from(21000u64), U256::one(), U256::from(10u64), 1);
+
+        let res = sim.simulate_unsigned_bundle_with_nonce_manager(&[tx.clone(), tx], &signer, &nonces, &GasCostScorer, None).await;
+        assert!(res.is_err());
+        assert!(nonces.in_flight().is_empty());
+        assert_eq!(nonces.reserve(2).await.unwrap(), NonceLease { start: 7, count: 2 });
+    }
+
+    #[tokio::test]
+    async fn call_with_overrides_sends_overrides_and_decodes_revert() {
+        let token = Address::repeat_byte(0x11);
+        let executor = Address::repeat_byte(0x22);
+        let router = Address::repeat_byte(0x33);
+        let state = StateOverrides::new()
+            .with_balance(executor, U256::exp10(18))
+            .with_erc20_balance(token, executor, U256::from(500u64), 0)
+            .with_erc20_allowance(token, executor, router, U256::MAX, 1);
+        let balance_slot = mapping_slot(executor.into(), H256::zero());
+        let allowance_slot = mapping_slot(router.into(), mapping_slot(executor.into(), H256::from_low_u64_be(1)));
+        assert_eq!(state.0[&token].state_diff.len(), 2);
+        assert_eq!(state.0[&token].state_diff[&balance_slot], H256::from_low_u64_be(500));
+        assert_eq!(state.0[&token].state_diff[&allowance_slot], H256::repeat_byte(0xff));
+        let blocks = BlockOverrides::default().with_time(1_700_000_000).with_base_fee(U256::from(7u64));
+
+        let mut revert_data = crate::revert::ERROR_SELECTOR.to_vec();
+        revert_data.extend(ethers_core::abi::encode(&[ethers_core::abi::Token::String("K".into())]));
+        let server = httpmock::MockServer::start();
+        let ok = server.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/")
+                .body_contains("\"eth_call\"")
+                .body_contains(format!("\"{:?}\":{{\"balance\":\"0xde0b6b3a7640000\"}}", executor))
+                .body_contains(format!("{:?}", allowance_slot))
+                .body_contains("\"baseFee\":\"0x7\"")
+                .body_contains("\"time\":\"0x6553f100\"");
+            then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"result":"0x01"}"#);
+        });
+        let sim = Simulator::with_rpc(server.url("/"));
+        let call = TypedTransaction::Eip1559(ethers_core::types::Eip1559TransactionRequest::new().from(executor).to(router).data(Bytes::from(vec![0xab])));
+        let res = sim.call_with_overrides(&call, None, &state, Some(&blocks)).await.unwrap();
+        assert_eq!(res, CallResult::Success(Bytes::from(vec![1u8])));
+        ok.assert();
+
+        let revert = server.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/").body_contains("\"eth_call\"");
+            then.status(200).body(serde_json::json!({"jsonrpc": "2.0", "id": 2, "error": {"code": 3, "message": "execution reverted: K", "data": Bytes::from(revert_data.clone())}}).to_string());
+        });
+        let res = sim.call_with_overrides(&call, None, &state, None).await.unwrap();
+        assert_eq!(res, CallResult::Revert(RevertReason::Error("K".into())));
+        revert.assert();
+    }
+
+    #[tokio::test]
+    async fn access_list_attached_only_when_it_saves_gas() {
+        let pool = Address::repeat_byte(0x11);
+        let plain = Address::repeat_byte(0x22);
+        let costly = Address::repeat_byte(0x33);
+        let server = httpmock::MockServer::start();
+        let rpc = |method: &'static str, to: Address, result: serde_json::Value| {
+            server.mock(move |when, then| {
+                when.method(httpmock::Method::POST).path("/").body_contains(method).body_contains(format!("{:?}", to));
+                then.status(200).body(serde_json::json!({"jsonrpc": "2.0", "id": 1, "result": result}).to_string());
+            })
+        };
+        let list = serde_json::json!([{"address": format!("{:?}", pool), "storageKeys": [format!("{:?}", H256::zero()), format!("{:?}", H256::from_low_u64_be(1))]}]);
+        // estimates without a list carry `"accessList":[]`, those with one carry its `storageKeys`
+        let estimate = |to: Address, listed: bool, result: &'static str| {
+            server.mock(move |when, then| {
+                let when = when.method(httpmock::Method::POST).path("/").body_contains("eth_estimateGas").body_contains(format!("{:?}", to));
+                if listed { when.body_contains("storageKeys"); } else { when.body_contains(r#""accessList":[]"#); }
+                then.status(200).body(serde_json::json!({"jsonrpc": "2.0", "id": 1, "result": result}).to_string());
+            })
+        };
+        estimate(pool, false, "0x1d4c0");
+        rpc("eth_createAccessList", pool, serde_json::json!({"accessList": list, "gasUsed": "0x1b968"}));
+        estimate(pool, true, "0x1c138");
+        estimate(plain, false, "0x5208");
+        rpc("eth_createAccessList", plain, serde_json::json!({"accessList": [{"address": format!("{:?}", plain), "storageKeys": []}], "gasUsed": "0x5b98"}));
+        estimate(plain, true, "0x5b98");
+        // `gasUsed` suggests a saving, but the estimate with the list attached is higher
+        estimate(costly, false, "0xc350");
+        rpc("eth_createAccessList", costly, serde_json::json!({"accessList": [{"address": format!("{:?}", costly), "storageKeys": [format!("{:?}", H256::zero())]}], "gasUsed": "0xbb80"}));
+        let with_costly = estimate(costly, true, "0xcc90");
+
+        let sim = Simulator::with_rpc(server.url("/"));
+        let tx = |to: Address| build_eip1559_tx(U256::zero(), to, U256::zero(), Bytes::from(vec![0x01]), U256::from(200_000u64), U256::one(), U256::from(10u64), 1);
+        // no mock answers for this legacy tx, so any RPC for it would fail and report no gain
+        let legacy = TypedTransaction::Legacy(ethers_core::types::TransactionRequest::new().to(Address::repeat_byte(0x44)).data(Bytes::from(vec![0x01])));
+        let (txs, gains) = sim.with_access_lists(&[tx(pool), tx(plain), tx(costly), legacy.clone()]).await;
+
+        let gain = gains[0].clone().unwrap();
+        assert_eq!(gain, AccessListGain { gas_without: 120_000, gas_with: 115_000, addresses: 1, slots: 2, attached: true });
+        assert_eq!(gain.saving(), 5_000);
+        assert_eq!(txs[0].access_list().unwrap().0[0].storage_keys.len(), 2);
+
+        let gain = gains[1].clone().unwrap();
+        assert!(!gain.attached);
+        assert_eq!(gain.saving(), 0);
+        assert!(txs[1].access_list().unwrap().0.is_empty());
+
+        let gain = gains[2].clone().unwrap();
+        with_costly.assert();
+        assert_eq!(gain, AccessListGain { gas_without: 50_000, gas_with: 52_368, addresses: 1, slots: 1, attached: false });
+        assert_eq!(gain.saving(), 0);
+        assert!(txs[2].access_list().unwrap().0.is_empty());
+
+        assert_eq!(gains[3], Some(AccessListGain::default()));
+        assert_eq!(txs[3], legacy);
+    }
+
+    #[tokio::test]
+    async fn delayed_simulation_mines_blocks_and_skips_rejected_pending_txs() {
+        let server = httpmock::MockServer::start();
+        let rpc = |method: &'static str, extra: &'static str, result: &'static str| {
+            server.mock(move |when, then| {
+                when.method(httpmock::Method::POST).path("/").body_contains(method).body_contains(extra);
+                then.status(200).body(format!(r#"{{"jsonrpc":"2.0","id":1,"result":{}}}"#, result));
+            })
+        };
+        let snapshot = rpc("evm_snapshot", "", r#""0x1""#);
+        let mine = rpc("anvil_mine", r#"["0x2","0xc"]"#, "null");
+        let base_fee = rpc("evm_setNextBlockBaseFeePerGas", "0x3b9aca00", "true");
+        let revert = rpc("evm_revert", "0x1", "true");
+        let pending = server.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/").body_contains("eth_sendRawTransaction");
+            then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"nonce too low"}}"#);
+        });
+
+        let sim = Simulator::with_rpc(server.url("/"));
+        let delay = InclusionDelay { blocks: 2, base_fee: Some(U256::exp10(9)), pending: vec![vec![0x02]] };
+        let outcome = sim.simulate_signed_bundle_delayed(&[], &delay, &BalanceWatch::default()).await.unwrap();
+        assert!(outcome.receipts.is_empty());
+        for mock in [snapshot, mine, base_fee, revert, pending] {
+            mock.assert();
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_bundle_send_still_reverts_the_snapshot() {
+        let server = httpmock::MockServer::start();
+        let rpc = |method: &'static str, result: &'static str| {
+            server.mock(move |when, then| {
+                when.method(httpmock::Method::POST).path("/").body_contains(method);
+                then.status(200).body(format!(r#"{{"jsonrpc":"2.0","id":1,"result":{}}}"#, result));
+            })
+        };
+        rpc("evm_snapshot", r#""0x1""#);
+        let mine = rpc("anvil_mine", "null");
+        let revert = rpc("evm_revert", "true");
+        server.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/").body_contains("eth_sendRawTransaction");
+            then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"max fee per gas less than block base fee"}}"#);
+        });
+
+        let sim = Simulator::with_rpc(server.url("/"));
+        let delay = InclusionDelay { blocks: 3, ..Default::default() };
+        let err = sim.simulate_signed_bundle_delayed(&[vec![0x02]], &delay, &BalanceWatch::default()).await.unwrap_err();
+        assert!(format!("{:#}", err).contains("send_raw failed"));
+        mine.assert();
+        revert.assert();
+    }
+
+    #[test]
+    fn diff_relay_simulation_reports_mismatches() {
+        use crate::executor::CallBundleTxResult;
+        let h1 = H256::repeat_byte(1);
+        let h2 = H256::repeat_byte(2);
+        let relay_tx = |hash: H256, gas_used: u64, revert: Option<&str>| CallBundleTxResult {
+            tx_hash: hash,
+            from_address: None,
+            to_address: None,
+            gas_used,
+            gas_price: U256::zero(),
+            gas_fees: U256::zero(),
+            coinbase_diff: U256::zero(),
+            eth_sent_to_coinbase: U256::zero(),
+            value: None,
+            error: revert.map(|_| "execution reverted".to_string()),
+            revert: revert.map(str::to_string),
+        };
+        let receipt = |hash: H256, gas: u64, status: u64| TransactionReceipt {
+            transaction_hash: hash,
+            gas_used: Some(U256::from(gas)),
+            status: Some(status.into()),
+            ..Default::default()
+        };
+        let relay = CallBundleResponse {
+            bundle_hash: None,
+            bundle_gas_price: U256::zero(),
+            coinbase_diff: U256::zero(),
+            eth_sent_to_coinbase: U256::zero(),
+            gas_fees: U256::zero(),
+            state_block_number: 1,
+            total_gas_used: 0,
+            results: vec![relay_tx(h1, 21_000, None), relay_tx(h2, 30_000, Some("too late"))],
+        };
+
+        assert!(diff_relay_simulation(&relay, &[receipt(h1, 21_000, 1), receipt(h2, 30_000, 0)]).is_empty());
+
+        let diff = diff_relay_simulation(&relay, &[receipt(h1, 25_000, 1), receipt(h2, 30_000, 1)]);
+        assert_eq!(diff, vec![
+            SimulationMismatch::GasUsed { index: 0, relay: 21_000, local: 25_000 },
+            SimulationMismatch::Status { index: 1, relay_reverted: true, local_reverted: false, relay_error: Some("too late".to_string()) },
+        ]);
+
+        let diff = diff_relay_simulation(&relay, &[receipt(h2, 21_000, 1)]);
+        assert_eq!(diff[0], SimulationMismatch::TxCount { relay: 2, local: 1 });
+        assert!(matches!(diff[1], SimulationMismatch::TxHash { index: 0, .. }));
+    }
 }
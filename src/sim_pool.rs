@@ -0,0 +1,321 @@
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use ethers_core::types::{Block, H256, U256};
+use ethers_providers::{Http, Middleware, Provider};
+use futures_util::future::join_all;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::process::{Child, Command};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use crate::fork_db::ForkDb;
+use crate::revm_backend::{RevmBackend, SimBlock};
+use crate::sim::{BalanceWatch, InclusionDelay, SimulationBackend, SimulationOutcome, Simulator};
+
+/// A forked simulation backend that can be moved to a new chain head.
+#[async_trait]
+pub trait ForkWorker: SimulationBackend {
+    /// Re-fork at `head`, dropping any state left by earlier simulations.
+    async fn reset_to(&mut self, head: &Block<H256>) -> Result<()>;
+}
+
+/// An Anvil fork of `fork_url`, either spawned and owned by this process or already running.
+pub struct AnvilFork {
+    sim: Simulator,
+    rpc: String,
+    fork_url: String,
+    /// Spawned with `kill_on_drop`, so the process exits with the worker
+    _child: Option<Child>,
+}
+
+impl AnvilFork {
+    /// Use an Anvil instance that is already running at `rpc`, forked from `fork_url`.
+    pub fn attach(rpc: String, fork_url: String) -> Self {
+        Self { sim: Simulator::with_rpc(rpc.clone()), rpc, fork_url, _child: None }
+    }
+
+    /// Start `anvil_bin` forking `fork_url` on `port` and wait until it answers RPC.
+    pub async fn spawn(anvil_bin: &str, fork_url: String, port: u16) -> Result<Self> {
+        let child = Command::new(anvil_bin)
+            .args(["--fork-url", &fork_url, "--port", &port.to_string(), "--silent"])
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("failed to start {}", anvil_bin))?;
+        let rpc = format!("http://127.0.0.1:{}", port);
+        let provider = Provider::<Http>::try_from(rpc.as_str()).context("invalid rpc url")?;
+        let ready = async {
+            while provider.get_chainid().await.is_err() {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(30), ready).await.map_err(|_| anyhow!("anvil on port {} did not start", port))?;
+        Ok(Self { sim: Simulator::with_rpc(rpc.clone()), rpc, fork_url, _child: Some(child) })
+    }
+
+    /// Replace the default `Simulator` (e.g. to add a revert decoder); its rpc is kept.
+    pub fn with_simulator(mut self, sim: Simulator) -> Self {
+        self.sim = sim;
+        self
+    }
+
+    pub fn rpc(&self) -> &str {
+        &self.rpc
+    }
+}
+
+#[async_trait]
+impl SimulationBackend for AnvilFork {
+    async fn simulate_bundle_delayed(&self, signed_raw_txs: &[Vec<u8>], delay: &InclusionDelay, watch: &BalanceWatch) -> Result<SimulationOutcome> {
+        self.sim.simulate_signed_bundle_delayed(signed_raw_txs, delay, watch).await
+    }
+}
+
+/// `anvil_reset` to the new head; this also drops every snapshot taken on the old fork.
+#[async_trait]
+impl ForkWorker for AnvilFork {
+    async fn reset_to(&mut self, head: &Block<H256>) -> Result<()> {
+        let number = head.number.ok_or_else(|| anyhow!("head without a block number"))?.as_u64();
+        let provider = Provider::<Http>::try_from(self.rpc.as_str()).context("invalid rpc url")?;
+        let params = serde_json::json!([{ "forking": { "jsonRpcUrl": self.fork_url, "blockNumber": number } }]);
+        let _r: serde_json::Value = provider.request("anvil_reset", params).await.context("anvil_reset failed")?;
+        Ok(())
+    }
+}
+
+/// Re-pins the fork db and simulates in the block after `head`. Pooled workers must not
+/// share a `ForkDb` (clones share the pinned block), so give each its own `ForkDb::new`.
+#[async_trait]
+impl ForkWorker for RevmBackend<ForkDb> {
+    async fn reset_to(&mut self, head: &Block<H256>) -> Result<()> {
+        let number = head.number.ok_or_else(|| anyhow!("head without a block number"))?.as_u64();
+        self.db().advance_to(number).await?;
+        self.set_block(SimBlock::after(head));
+        Ok(())
+    }
+}
+
+struct Slot<W> {
+    worker: W,
+    /// Hash of the head the worker was last reset to, so a same-height reorg resets it too
+    synced: Option<H256>,
+}
+
+/// A fixed set of forks. Each simulation leases a worker for its exclusive use, so
+/// concurrent simulations never see each other's state. Workers are reset to the head
+/// set with `set_head` when they are next leased.
+pub struct SimPool<W> {
+    idle: Mutex<Vec<Slot<W>>>,
+    permits: Arc<Semaphore>,
+    head: Mutex<Option<Arc<Block<H256>>>>,
+    size: usize,
+}
+
+impl<W: ForkWorker> SimPool<W> {
+    pub fn new(workers: Vec<W>) -> Arc<Self> {
+        let size = workers.len();
+        Arc::new(Self {
+            idle: Mutex::new(workers.into_iter().map(|worker| Slot { worker, synced: None }).collect()),
+            permits: Arc::new(Semaphore::new(size)),
+            head: Mutex::new(None),
+            size,
+        })
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn idle(&self) -> usize {
+        self.idle.lock().unwrap().len()
+    }
+
+    /// New chain head; workers leased after this are reset to it first.
+    pub fn set_head(&self, head: Block<H256>) {
+        *self.head.lock().unwrap() = Some(Arc::new(head));
+    }
+
+    pub fn head_number(&self) -> Option<u64> {
+        self.head.lock().unwrap().as_ref().and_then(|h| h.number).map(|n| n.as_u64())
+    }
+
+    /// Wait for a free worker, resetting it to the current head if it is on another block.
+    /// A worker whose reset fails goes back to the pool and is retried on the next lease.
+    pub async fn lease(self: &Arc<Self>) -> Result<SimLease<W>> {
+        let permit = self.permits.clone().acquire_owned().await.context("simulator pool closed")?;
+        let slot = self.idle.lock().unwrap().pop().expect("a permit guarantees an idle worker");
+        let mut lease = SimLease { pool: self.clone(), slot: Some(slot), _permit: permit };
+        let head = self.head.lock().unwrap().clone();
+        if let Some(head) = head {
+            let slot = lease.slot.as_mut().unwrap();
+            if head.hash.is_none() || slot.synced != head.hash {
+                slot.worker.reset_to(&head).await?;
+                slot.synced = head.hash;
+            }
+        }
+        Ok(lease)
+    }
+
+    /// Follow new blocks on `rpc` and `set_head` for each. Stops when `rpc` can no longer
+    /// be watched or the pool is dropped.
+    pub fn spawn_head_follower(self: &Arc<Self>, rpc: &str, poll: Duration) -> Result<tokio::task::JoinHandle<()>>
+    where
+        W: Send + 'static,
+    {
+        let mut heads = crate::retarget::spawn_head_watcher(rpc, poll)?;
+        let provider = Provider::<Http>::try_from(rpc).context("invalid rpc url")?;
+        let pool = Arc::downgrade(self);
+        Ok(tokio::spawn(async move {
+            while let Some(head) = heads.recv().await {
+                let Some(pool) = pool.upgrade() else { break };
+                match provider.get_block(head.number).await {
+                    Ok(Some(block)) => pool.set_head(block),
+                    Ok(None) => {}
+                    Err(e) => tracing::warn!("failed to fetch head {}: {:?}", head.number, e),
+                }
+            }
+        }))
+    }
+
+    /// Lease a worker and run `f` on it; the worker goes back to the pool when `f` is done.
+    pub async fn run<T>(self: &Arc<Self>, f: impl AsyncFnOnce(&W) -> Result<T>) -> Result<T> {
+        let lease = self.lease().await?;
+        f(&lease).await
+    }
+
+    /// Simulate each bundle on its own leased worker, at most `size()` at a time.
+    /// Results are in the order of `bundles`.
+    pub async fn simulate_many(self: &Arc<Self>, bundles: &[Vec<Vec<u8>>], next_base_fee: Option<U256>, watch: &BalanceWatch) -> Vec<Result<SimulationOutcome>> {
+        join_all(bundles.iter().map(|signed| self.run(async |worker| worker.simulate_bundle_with_balances(signed, next_base_fee, watch).await))).await
+    }
+}
+
+/// Exclusive use of one pooled worker; it goes back to the pool on drop.
+pub struct SimLease<W> {
+    pool: Arc<SimPool<W>>,
+    slot: Option<Slot<W>>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl<W> Deref for SimLease<W> {
+    type Target = W;
+
+    fn deref(&self) -> &W {
+        &self.slot.as_ref().unwrap().worker
+    }
+}
+
+impl<W> DerefMut for SimLease<W> {
+    fn deref_mut(&mut self) -> &mut W {
+        &mut self.slot.as_mut().unwrap().worker
+    }
+}
+
+impl<W> Drop for SimLease<W> {
+    fn drop(&mut self) {
+        // return the worker before the permit is released
+        if let Some(slot) = self.slot.take() {
+            self.pool.idle.lock().unwrap().push(slot);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers_core::types::TransactionReceipt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct FakeFork {
+        id: usize,
+        resets: Arc<AtomicUsize>,
+        block: Option<u64>,
+    }
+
+    #[async_trait]
+    impl SimulationBackend for FakeFork {
+        async fn simulate_bundle_delayed(&self, _signed_raw_txs: &[Vec<u8>], _delay: &InclusionDelay, watch: &BalanceWatch) -> Result<SimulationOutcome> {
+            tokio::task::yield_now().await;
+            let receipts = vec![TransactionReceipt { block_number: self.block.map(Into::into), ..Default::default() }];
+            Ok(SimulationOutcome::new(receipts, Vec::new(), watch))
+        }
+    }
+
+    #[async_trait]
+    impl ForkWorker for FakeFork {
+        async fn reset_to(&mut self, head: &Block<H256>) -> Result<()> {
+            self.resets.fetch_add(1, Ordering::SeqCst);
+            self.block = head.number.map(|n| n.as_u64());
+            Ok(())
+        }
+    }
+
+    fn head(number: u64) -> Block<H256> {
+        Block { number: Some(number.into()), hash: Some(H256::from_low_u64_be(number)), ..Default::default() }
+    }
+
+    #[tokio::test]
+    async fn leases_are_exclusive_and_reset_once_per_head() {
+        let resets = Arc::new(AtomicUsize::new(0));
+        let pool = SimPool::new((0..2).map(|id| FakeFork { id, resets: resets.clone(), block: None }).collect());
+
+        let a = pool.lease().await.unwrap();
+        let b = pool.lease().await.unwrap();
+        assert_ne!(a.id, b.id);
+        assert_eq!(pool.idle(), 0);
+        assert!(tokio::time::timeout(Duration::from_millis(50), pool.lease()).await.is_err());
+        drop(a);
+        let c = pool.lease().await.unwrap();
+        drop((b, c));
+        assert_eq!(resets.load(Ordering::SeqCst), 0);
+
+        pool.set_head(head(10));
+        let a = pool.lease().await.unwrap();
+        let b = pool.lease().await.unwrap();
+        assert_eq!(a.simulate_bundle(&[], None).await.unwrap()[0].block_number, Some(10u64.into()));
+        drop((a, b));
+        let _again = pool.lease().await.unwrap();
+        assert_eq!(resets.load(Ordering::SeqCst), 2);
+
+        pool.set_head(head(11));
+        assert_eq!(pool.lease().await.unwrap().block, Some(11));
+        assert_eq!(resets.load(Ordering::SeqCst), 3);
+
+        // a reorg replacing block 11 resets the worker again
+        pool.set_head(Block { hash: Some(H256::repeat_byte(0xbb)), ..head(11) });
+        assert_eq!(pool.lease().await.unwrap().block, Some(11));
+        assert_eq!(resets.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn simulate_many_leases_a_worker_per_bundle() {
+        let resets = Arc::new(AtomicUsize::new(0));
+        let pool = SimPool::new((0..2).map(|id| FakeFork { id, resets: resets.clone(), block: None }).collect());
+        pool.set_head(head(7));
+
+        let outcomes = pool.simulate_many(&[vec![], vec![], vec![]], None, &BalanceWatch::default()).await;
+        assert_eq!(outcomes.len(), 3);
+        assert!(outcomes.iter().all(|o| o.as_ref().unwrap().receipts[0].block_number == Some(7u64.into())));
+        assert_eq!(resets.load(Ordering::SeqCst), 2);
+        assert_eq!(pool.idle(), 2);
+
+        let id = pool.run(async |worker| Ok(worker.id)).await.unwrap();
+        assert!(id < 2);
+    }
+
+    #[tokio::test]
+    async fn anvil_fork_resets_to_head() {
+        let server = httpmock::MockServer::start();
+        let reset = server.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/")
+                .body_contains("\"anvil_reset\"")
+                .body_contains("\"blockNumber\":42")
+                .body_contains("\"jsonRpcUrl\":\"http://upstream:8545\"");
+            then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"result":null}"#);
+        });
+        let pool = SimPool::new(vec![AnvilFork::attach(server.url("/"), "http://upstream:8545".to_string())]);
+        pool.set_head(head(42));
+        let lease = pool.lease().await.unwrap();
+        assert_eq!(lease.rpc(), server.url("/"));
+        reset.assert();
+    }
+}
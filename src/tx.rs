@@ -2,6 +2,7 @@ use ethers_core::types::{Address, Bytes, NameOrAddress, U256};
 use ethers_core::types::transaction::eip1559::Eip1559TransactionRequest;
 use ethers_core::types::transaction::eip2718::TypedTransaction;
 use anyhow::Result;
+use crate::fees::FeeRecommendation;
 
 /// Build a basic EIP-1559 `TypedTransaction`.
 pub fn build_eip1559_tx(
@@ -27,6 +28,76 @@ pub fn build_eip1559_tx(
     TypedTransaction::Eip1559(tx)
 }
 
+/// Build an EIP-1559 tx priced from a fee oracle recommendation
+/// (see `fees::FeeOracle::recommend`).
+pub fn build_eip1559_tx_with_fees(
+    nonce: U256,
+    to: Address,
+    value: U256,
+    data: Bytes,
+    gas_limit: U256,
+    fees: &FeeRecommendation,
+    chain_id: u64,
+) -> TypedTransaction {
+    build_eip1559_tx(nonce, to, value, data, gas_limit, fees.max_priority_fee_per_gas, fees.max_fee_per_gas, chain_id)
+}
+
+/// Gas limit for a plain ETH transfer to the block builder.
+pub const COINBASE_TRANSFER_GAS: u64 = 21_000;
+
+/// How much of a bundle's simulated profit is bid to the block builder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BidStrategy {
+    /// Fixed share of profit in basis points (10_000 = 100%)
+    Percentage { bps: u32 },
+    /// Bidding curve of `(profit_wei, share_bps)` points sorted by profit. The share is
+    /// interpolated linearly between points and held flat outside them.
+    Curve(Vec<(U256, u32)>),
+}
+
+impl BidStrategy {
+    /// Share of `profit` to bid, in basis points (capped at 10_000).
+    pub fn share_bps(&self, profit: U256) -> u32 {
+        let bps = match self {
+            BidStrategy::Percentage { bps } => *bps,
+            BidStrategy::Curve(points) => match points.iter().position(|(p, _)| profit < *p) {
+                None => points.last().map(|(_, s)| *s).unwrap_or(0),
+                Some(0) => points[0].1,
+                Some(i) => {
+                    let (p0, s0) = points[i - 1];
+                    let (p1, s1) = points[i];
+                    let (x, dx) = (profit - p0, p1 - p0);
+                    if s1 >= s0 {
+                        s0 + (U256::from(s1 - s0) * x / dx).as_u32()
+                    } else {
+                        s0 - (U256::from(s0 - s1) * x / dx).as_u32()
+                    }
+                }
+            },
+        };
+        bps.min(10_000)
+    }
+
+    /// Builder payment for a bundle with simulated `profit` (wei).
+    pub fn bid_for(&self, profit: U256) -> U256 {
+        profit.saturating_mul(U256::from(self.share_bps(profit))) / U256::from(10_000u64)
+    }
+}
+
+/// Build the final bundle leg that pays `bid` wei to the builder's `coinbase`.
+/// The coinbase must be known for the target block (e.g. the relay's builder fee
+/// recipient); paying whatever `block.coinbase` turns out to be needs a contract call.
+pub fn build_coinbase_transfer_tx(
+    nonce: U256,
+    coinbase: Address,
+    bid: U256,
+    max_priority_fee_per_gas: U256,
+    max_fee_per_gas: U256,
+    chain_id: u64,
+) -> TypedTransaction {
+    build_eip1559_tx(nonce, coinbase, bid, Bytes::default(), U256::from(COINBASE_TRANSFER_GAS), max_priority_fee_per_gas, max_fee_per_gas, chain_id)
+}
+
 /// Given a list of signed raw tx bytes, produce a JSON array suitable for a
 /// Flashbots-style bundle submission (array of hex strings prefixed with 0x).
 pub fn bundle_from_signed_txs(signed: &[Vec<u8>]) -> serde_json::Value {
@@ -55,6 +126,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn builds_eip1559_tx_from_fee_recommendation() {
+        let fees = FeeRecommendation {
+            predicted_base_fee: U256::from(10u64),
+            max_fee_per_gas: U256::from(25u64),
+            max_priority_fee_per_gas: U256::from(2u64),
+        };
+        let tx = build_eip1559_tx_with_fees(U256::from(1u64), Address::zero(), U256::zero(), Bytes::from(vec![]), U256::from(21000u64), &fees, 1);
+        match tx {
+            TypedTransaction::Eip1559(r) => {
+                assert_eq!(r.max_fee_per_gas, Some(U256::from(25u64)));
+                assert_eq!(r.max_priority_fee_per_gas, Some(U256::from(2u64)));
+            }
+            _ => panic!("expected Eip1559 transaction"),
+        }
+    }
+
     #[test]
     fn bundle_from_tx_encodes_hex_array() {
         let signed = vec![vec![0x01, 0x02, 0x03], vec![0xab, 0xcd]];
@@ -64,4 +152,28 @@ mod tests {
         assert_eq!(arr[0].as_str().unwrap(), "0x010203");
         assert_eq!(arr[1].as_str().unwrap(), "0xabcd");
     }
+
+    #[test]
+    fn bid_strategy_percentage_and_curve() {
+        let eth = U256::exp10(18);
+        assert_eq!(BidStrategy::Percentage { bps: 9_000 }.bid_for(eth), eth * 9 / 10);
+        assert_eq!(BidStrategy::Percentage { bps: 20_000 }.bid_for(eth), eth);
+
+        // 50% at 0.1 ETH rising to 90% at 1.1 ETH
+        let curve = BidStrategy::Curve(vec![(eth / 10, 5_000), (eth * 11 / 10, 9_000)]);
+        assert_eq!(curve.share_bps(U256::zero()), 5_000);
+        assert_eq!(curve.share_bps(eth * 6 / 10), 7_000);
+        assert_eq!(curve.share_bps(eth * 5), 9_000);
+        assert_eq!(curve.bid_for(eth * 6 / 10), eth * 42 / 100);
+        assert_eq!(BidStrategy::Curve(vec![]).bid_for(eth), U256::zero());
+    }
+
+    #[test]
+    fn builds_coinbase_transfer_leg() {
+        let coinbase = Address::from_low_u64_be(0xc0);
+        let tx = build_coinbase_transfer_tx(U256::from(3u64), coinbase, U256::from(5u64), U256::zero(), U256::from(10u64), 1);
+        assert_eq!(tx.to(), Some(&NameOrAddress::Address(coinbase)));
+        assert_eq!(tx.value(), Some(&U256::from(5u64)));
+        assert_eq!(tx.gas(), Some(&U256::from(COINBASE_TRANSFER_GAS)));
+    }
 }
@@ -27,7 +27,7 @@ async fn e2e_build_sign_bundle_submit_and_mine() {
     let server = MockServer::start();
     let m = server.mock(|when, then| {
         when.method(httpmock::Method::POST).path("/");
-        then.status(200).body(r#"{"result":"accepted"}"#);
+        then.status(200).body(r#"{"jsonrpc":"2.0","id":1,"result":{"bundleHash":"0x2228f5d8954ce31dc1601a8ba264dbd401bf1428388ce88238932815c5d6f23f"}}"#);
     });
 
     // Wire relay URL
@@ -57,7 +57,7 @@ async fn e2e_build_sign_bundle_submit_and_mine() {
     // Submit to flashbots mock relay via RelayClient
     let rc = RelayClient::new().await.unwrap();
     let v = rc.submit_flashbots_bundle(&[raw.clone()], None).await.expect("submit bundle");
-    assert_eq!(v.get("result").unwrap().as_str().unwrap(), "accepted");
+    assert_eq!(format!("{:?}", v.bundle_hash), "0x2228f5d8954ce31dc1601a8ba264dbd401bf1428388ce88238932815c5d6f23f");
     m.assert();
 
     // Submit signed tx to Anvil directly and ensure mining
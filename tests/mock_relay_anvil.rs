@@ -0,0 +1,64 @@
+use std::sync::Arc;
+use ethers_providers::{Provider, Http, Middleware};
+use ethers_core::types::{U256, Address, Bytes};
+use mev_arbitrage_bot::autosubmit::{Autosubmitter, AutosubmitConfig};
+use mev_arbitrage_bot::auth::FlashbotsAuth;
+use mev_arbitrage_bot::executor::{CallBundleParams, RelayClient};
+use mev_arbitrage_bot::mock_relay::{MockRelay, Scenario};
+use mev_arbitrage_bot::signer::{BasicEnvSigner, Signer};
+use mev_arbitrage_bot::tx::build_eip1559_tx;
+
+// Mock relay E2E - ignored by default. Requires env vars:
+// - ANVIL_RPC_URL (default: http://127.0.0.1:8545)
+// - PRIVATE_KEY (hex without 0x) for a funded Anvil account
+
+#[tokio::test]
+#[ignore]
+async fn autosubmit_through_mock_relay_lands_on_anvil() {
+    let anvil_rpc = std::env::var("ANVIL_RPC_URL").unwrap_or_else(|_| "http://127.0.0.1:8545".to_string());
+    let private = match std::env::var("PRIVATE_KEY") {
+        Ok(v) => v,
+        Err(_) => {
+            eprintln!("Skipping mock relay E2E: set PRIVATE_KEY without 0x (Anvil funded key)");
+            return;
+        }
+    };
+
+    let relay = MockRelay::start(Some(anvil_rpc.clone()), true).await.expect("start mock relay");
+    let signer = Arc::new(BasicEnvSigner::from_secret(private));
+    let auth = Arc::new(FlashbotsAuth::from_signer(signer.clone()));
+    let client = RelayClient::with_url(relay.url()).unwrap().with_auth(auth);
+
+    let provider = Provider::<Http>::try_from(anvil_rpc.as_str()).expect("provider");
+    let chain_id = provider.get_chainid().await.expect("chainid").as_u64();
+    let from = signer.address().await.expect("address").expect("signer exposes address");
+    let nonce = provider.get_transaction_count(from, None).await.expect("nonce");
+    let tx = build_eip1559_tx(
+        nonce,
+        Address::zero(),
+        U256::from(0u64),
+        Bytes::from(vec![]),
+        U256::from(21000u64),
+        U256::from(1_000_000_000u64),
+        U256::from(100_000_000_000u64),
+        chain_id,
+    );
+    let raw = signer.sign_typed_transaction(&tx).await.expect("sign tx");
+
+    // Simulation is forwarded to Anvil and reverted afterwards
+    let head = provider.get_block_number().await.expect("block number").as_u64();
+    let sim = client.call_bundle(std::slice::from_ref(&raw), &CallBundleParams::for_block(head + 1)).await.expect("callBundle");
+    assert_eq!(sim.results.len(), 1);
+    assert_eq!(sim.results[0].gas_used, 21000);
+    assert_eq!(provider.get_transaction_count(from, None).await.expect("nonce"), nonce);
+
+    // A rejected first attempt, then inclusion on the rebroadcast
+    relay.push_scenario(Scenario::Reject { code: -32000, message: "bundle underpriced".into() });
+    relay.set_default_scenario(Scenario::Include);
+    let config = AutosubmitConfig { poll_interval_secs: 1, max_wait_secs: 2, ..Default::default() };
+    let autosubmitter = Autosubmitter::new(anvil_rpc.clone(), config);
+    assert!(autosubmitter.submit_and_monitor(std::slice::from_ref(&raw), &client).await.is_err());
+    let receipts = autosubmitter.submit_and_monitor(&[raw], &client).await.expect("included");
+    assert_eq!(receipts.len(), 1);
+    assert_eq!(relay.requests_for("eth_sendBundle").len(), 2);
+}